@@ -26,6 +26,19 @@ enum CreateJobResponse {
     /// The job already exists
     #[oai(status = 409)]
     JobAlreadyExists,
+    /// The job has invalid parameters
+    #[oai(status = 422)]
+    InvalidParams(Json<Vec<api::job::ParamError>>),
+}
+
+#[derive(ApiResponse)]
+enum EstimateJobResponse {
+    /// The request was successful
+    #[oai(status = 200)]
+    Estimate(Json<job::Estimate>),
+    /// The job has invalid parameters
+    #[oai(status = 422)]
+    InvalidParams(Json<Vec<api::job::ParamError>>),
 }
 
 #[derive(ApiResponse)]
@@ -42,7 +55,7 @@ enum GetAllJobsResponse {
 enum GetJobResponse {
     /// The request was successful
     #[oai(status = 200)]
-    Job(Json<job::Job>),
+    Job(Json<Box<job::Job>>),
     /// The job could not be found
     #[oai(status = 404)]
     NotFound(PlainText<String>),
@@ -100,8 +113,29 @@ enum GetStatisticsResponse {
     InternalError,
 }
 
+#[derive(ApiResponse)]
+enum GetLogResponse {
+    /// The end of the output of the world and how it ended,
+    /// which is only how it ended if the worker runs worlds in process
+    #[oai(status = 200)]
+    Log(PlainText<String>),
+    /// The task could not be found or has not ended yet
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+    /// Some problem has occurred on the server
+    #[oai(status = 500)]
+    InternalError,
+}
+
 #[async_trait]
 pub trait ResourceManager {
+    /// Returns the invalid parts of a job, which is empty if it can be run.
+    fn validate_job(&self, config: &job::Config) -> Vec<api::job::ParamError>;
+    /// Returns the resources and the running time of a job, or its invalid parts.
+    fn estimate_job(
+        &self,
+        config: &job::Config,
+    ) -> Result<job::Estimate, Vec<api::job::ParamError>>;
     async fn create_job(&self, config: job::Config) -> Option<String>;
     async fn get_job(&self, id: &str) -> anyhow::Result<Option<job::Job>>;
     async fn get_all_jobs(&self) -> anyhow::Result<Vec<job::Job>>;
@@ -109,6 +143,7 @@ pub trait ResourceManager {
     async fn terminate_job(&self, id: &str) -> anyhow::Result<bool>;
     async fn get_task(&self, id: &str) -> anyhow::Result<Option<task::Task>>;
     async fn get_statistics(&self, id: &str) -> anyhow::Result<Option<Vec<u8>>>;
+    async fn get_log(&self, id: &str) -> anyhow::Result<Option<Vec<u8>>>;
 }
 
 pub struct Api<M: ResourceManager>(pub M);
@@ -117,16 +152,28 @@ pub struct Api<M: ResourceManager>(pub M);
 impl<M: ResourceManager + Send + Sync + 'static> Api<M> {
     #[oai(tag = "ApiTags::Job", path = "/jobs", method = "post")]
     async fn create_job(&self, config: Json<job::Config>) -> poem::Result<CreateJobResponse> {
+        let errors = self.0.validate_job(&config.0);
+        if !errors.is_empty() {
+            return Ok(CreateJobResponse::InvalidParams(Json(errors)));
+        }
         match self.0.create_job(config.0).await {
             Some(id) => Ok(CreateJobResponse::JobId(Json(id))),
             None => Ok(CreateJobResponse::JobAlreadyExists),
         }
     }
 
+    #[oai(tag = "ApiTags::Job", path = "/jobs/estimate", method = "post")]
+    async fn estimate_job(&self, config: Json<job::Config>) -> poem::Result<EstimateJobResponse> {
+        match self.0.estimate_job(&config.0) {
+            Ok(estimate) => Ok(EstimateJobResponse::Estimate(Json(estimate))),
+            Err(errors) => Ok(EstimateJobResponse::InvalidParams(Json(errors))),
+        }
+    }
+
     #[oai(tag = "ApiTags::Job", path = "/jobs/:id", method = "get")]
     async fn get_job(&self, id: Path<String>) -> poem::Result<GetJobResponse> {
         match self.0.get_job(&id.0).await {
-            Ok(Some(job)) => Ok(GetJobResponse::Job(Json(job.clone()))),
+            Ok(Some(job)) => Ok(GetJobResponse::Job(Json(Box::new(job.clone())))),
             Ok(None) => Ok(GetJobResponse::NotFound(PlainText(format!(
                 "Job {} is not found.",
                 id.0
@@ -189,4 +236,20 @@ impl<M: ResourceManager + Send + Sync + 'static> Api<M> {
             Err(_) => Ok(GetStatisticsResponse::InternalError),
         }
     }
+
+    /// Returns the end of the log of a task. Worlds run in process write their output
+    /// to the log of the worker, so the log of such a task only tells how it ended.
+    #[oai(tag = "ApiTags::Task", path = "/tasks/:id/log", method = "get")]
+    async fn get_log(&self, id: Path<String>) -> poem::Result<GetLogResponse> {
+        match self.0.get_log(&id.0).await {
+            Ok(Some(log)) => Ok(GetLogResponse::Log(PlainText(
+                String::from_utf8_lossy(&log).into_owned(),
+            ))),
+            Ok(None) => Ok(GetLogResponse::NotFound(PlainText(format!(
+                "Task {} is not found.",
+                id.0
+            )))),
+            Err(_) => Ok(GetLogResponse::InternalError),
+        }
+    }
 }
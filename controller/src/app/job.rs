@@ -22,8 +22,25 @@ impl Example for Config {
                 world_params: WorldParams {
                     population_size: 1000,
                     infected: 1.0,
+                    commuting: None,
+                    density_map: None,
+                    anti_vax: None,
+                    infection_model: None,
+                    geometry: None,
+                    regions: Vec::new(),
+                    households: None,
+                    mesh: None,
                 },
                 scenario: Vec::new(),
+                tracing: None,
+                home_quarantine: None,
+                hospital: None,
+                immunity: None,
+                vaccination: None,
+                gathering_spots: None,
+                gathering_events: Vec::new(),
+                travel: None,
+                profiling: false,
             },
             iteration_count: 3,
             output_fields: Vec::new(),
@@ -65,3 +82,33 @@ impl Job {
         }
     }
 }
+
+/// Estimate of a job on a worker.
+#[derive(Object, Clone, Debug)]
+#[oai(rename_all = "camelCase")]
+pub struct WorkerEstimate {
+    /// Index of the worker.
+    pub worker: u32,
+    /// Resource units a task takes, which is none if the worker cannot accept the job.
+    pub resource: Option<u32>,
+    /// Resource units of the worker.
+    pub max_resource: u32,
+    /// Tasks of the job the worker can run at once.
+    pub concurrent_tasks: u32,
+    /// Cells the field is divided into on the threads of a task of the worker.
+    pub n_cells: f64,
+    /// Seconds a task takes estimated from the step times measured on the worker.
+    pub task_secs: f64,
+}
+
+/// Estimate of a job without running it.
+#[derive(Object, Clone, Debug)]
+#[oai(rename_all = "camelCase")]
+pub struct Estimate {
+    pub population_size: u32,
+    pub n_steps: u64,
+    /// Estimates on each worker.
+    pub workers: Vec<WorkerEstimate>,
+    /// Seconds to run all the tasks on idle workers, which is none if no worker can accept the job.
+    pub total_secs: Option<f64>,
+}
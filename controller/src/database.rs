@@ -56,11 +56,18 @@ impl Db {
         Ok((job_id, task_ids))
     }
 
-    pub async fn update_task_succeeded(&self, task_id: &TaskId, worker_index: usize) {
+    /// Updates the state of a task which has ended on the worker of `worker_index`,
+    /// where its statistics and log are kept.
+    pub async fn update_task_ended(
+        &self,
+        task_id: &TaskId,
+        worker_index: usize,
+        state: &TaskState,
+    ) {
         self.0
             .execute(
                 "UPDATE task SET worker_index = $1, state = $2 WHERE id = $3",
-                &[&(worker_index as i32), &TaskState::Succeeded, &task_id.0],
+                &[&(worker_index as i32), state, &task_id.0],
             )
             .await
             .unwrap();
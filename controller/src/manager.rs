@@ -15,11 +15,13 @@ use tokio::{
 use tokio_postgres::NoTls;
 use tracing::Instrument;
 use uuid::Uuid;
+use worker_if::Cost;
 
 use crate::{
     app::{
         job::{self, JobState},
-        task, ResourceManager,
+        task::{self, TaskState},
+        ResourceManager,
     },
     database::Db,
     worker::{ServerConfig, TaskId, WorkerManager},
@@ -132,10 +134,20 @@ struct Job {
 
 impl Job {
     async fn consume(self, worker_manager: &WorkerManager, db: &Db) {
+        let costs = match worker_manager.costs(&self.config.param) {
+            Ok(costs) => costs,
+            Err(e) => {
+                tracing::error!("the cost of {} cannot be estimated: {e}", self.id);
+                for task_id in &self.task_ids {
+                    db.update_task_state(task_id, &TaskState::Failed).await;
+                }
+                return;
+            }
+        };
         let mut handles = Vec::new();
         for task_id in self.task_ids {
             tracing::debug!("received task {}", task_id);
-            let lease = worker_manager.lease((&self.config.param).into()).await;
+            let lease = worker_manager.lease(costs.clone()).await;
             let fq_rx = self.fq_rx.clone();
             let db = db.clone();
             let config = self.config.clone();
@@ -144,17 +156,24 @@ impl Job {
                     Ok(_) = fq_rx.clone().notified() => {
                         tracing::info!("{} lease is canceled", task_id);
                     }
-                    Ok(worker) = lease => {
-                        let span = tracing::debug_span!("task", id = task_id.to_string(), worker = worker.index());
-                        worker
-                            .execute(
-                                &task_id,
-                                config,
-                                &db,
-                                fq_rx,
-                            )
-                            .instrument(span)
-                            .await;
+                    res = lease => match res {
+                        Ok(worker) => {
+                            let span = tracing::debug_span!("task", id = task_id.to_string(), worker = worker.index());
+                            worker
+                                .execute(
+                                    &task_id,
+                                    config,
+                                    &db,
+                                    fq_rx,
+                                )
+                                .instrument(span)
+                                .await;
+                        }
+                        Err(_) => {
+                            // no worker has enough resource for the task
+                            tracing::error!("{} cannot be leased to any worker", task_id);
+                            db.update_task_state(&task_id, &TaskState::Failed).await;
+                        }
                     }
                 }
             }));
@@ -302,10 +321,88 @@ impl Manager {
         let client = self.worker_manager.get_worker(worker_index);
         Ok(Some(client.get_statistics(&id).await?))
     }
+
+    async fn get_log(&self, id: &TaskId) -> anyhow::Result<Option<Vec<u8>>> {
+        let Some(worker_index) = self.db.get_worker_index(&id).await? else {
+            return Ok(None);
+        };
+        let client = self.worker_manager.get_worker(worker_index);
+        Ok(Some(client.get_log(&id).await?))
+    }
+}
+
+/// Returns the invalid parts of `param` with the paths relative to the job configuration.
+fn validate_param(param: &api::job::JobParam) -> Vec<api::job::ParamError> {
+    let mut errors = world::validate(param);
+    for path in errors.iter_mut().filter_map(|e| e.path.as_mut()) {
+        *path = format!("param.{path}");
+    }
+    errors
+}
+
+/// Returns the estimate of a job of `iteration_count` tasks from the estimates of a task on each worker.
+fn new_estimate(
+    cost: &Cost,
+    iteration_count: u64,
+    workers: Vec<job::WorkerEstimate>,
+) -> job::Estimate {
+    // tasks are assumed to be spread over the workers in proportion to their throughput,
+    // though the job takes at least as long as a task on the fastest worker
+    let accepting = workers.iter().filter(|w| w.concurrent_tasks > 0);
+    let throughput = accepting
+        .clone()
+        .map(|w| w.concurrent_tasks as f64 / w.task_secs)
+        .sum::<f64>();
+    let total_secs = accepting
+        .map(|w| w.task_secs)
+        .min_by(f64::total_cmp)
+        .map(|secs| (iteration_count as f64 / throughput).max(secs));
+    job::Estimate {
+        population_size: cost.population_size,
+        n_steps: cost.n_steps,
+        workers,
+        total_secs,
+    }
 }
 
 #[async_trait]
 impl ResourceManager for Manager {
+    fn validate_job(&self, config: &job::Config) -> Vec<api::job::ParamError> {
+        let mut errors = validate_param(&config.param);
+        // an invalid field is reported by the validation
+        let accepted = self
+            .worker_manager
+            .costs(&config.param)
+            .map_or(true, |costs| self.worker_manager.can_accept(&costs));
+        if !accepted {
+            errors.push(api::job::ParamError::new(
+                Some("param.worldParams".to_string()),
+                "the job exceeds the capacity of every worker",
+            ));
+        }
+        errors
+    }
+
+    fn estimate_job(
+        &self,
+        config: &job::Config,
+    ) -> Result<job::Estimate, Vec<api::job::ParamError>> {
+        let errors = validate_param(&config.param);
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        let costs = self.worker_manager.costs(&config.param).map_err(|e| {
+            vec![api::job::ParamError::new(
+                Some("param.worldParams".to_string()),
+                e,
+            )]
+        })?;
+        let workers = self.worker_manager.estimate(&costs);
+        // the cells depend on the worker
+        let cost = Cost::new(&config.param, 0, world::STEPS_PER_DAY);
+        Ok(new_estimate(&cost, config.iteration_count, workers))
+    }
+
     async fn create_job(&self, config: job::Config) -> Option<String> {
         match self.create_job(config.clone()).await {
             Ok(id) => Some(id),
@@ -345,6 +442,11 @@ impl ResourceManager for Manager {
         let id = id.try_into()?;
         Ok(self.get_statistics(&id).await?)
     }
+
+    async fn get_log(&self, id: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        let id = id.try_into()?;
+        Ok(self.get_log(&id).await?)
+    }
 }
 
 #[cfg(test)]
@@ -503,4 +605,46 @@ mod tests {
             assert_eq!(test, test2);
         })
     }
+
+    #[test]
+    fn test_estimate() {
+        use poem_openapi::types::Example;
+        use worker_if::Cost;
+
+        use crate::app::job;
+
+        let mut config = job::Config::example();
+        assert!(super::validate_param(&config.param).is_empty());
+        config.param.world_params.mesh = Some(400);
+        let paths = super::validate_param(&config.param)
+            .into_iter()
+            .map(|e| e.path.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["param.worldParams.mesh"]);
+
+        let cost = Cost {
+            population_size: 1000,
+            n_cells: 324.0,
+            gathering_frequency: 50.0,
+            n_steps: 160,
+        };
+        let worker = |worker, concurrent_tasks, task_secs| job::WorkerEstimate {
+            worker,
+            resource: (concurrent_tasks > 0).then_some(1),
+            max_resource: concurrent_tasks,
+            concurrent_tasks,
+            n_cells: 324.0,
+            task_secs,
+        };
+        let workers = vec![worker(0, 2, 10.0), worker(1, 0, 5.0), worker(2, 1, 20.0)];
+        // 2 / 10 + 1 / 20 tasks per second on the workers accepting the job
+        let estimate = super::new_estimate(&cost, 10, workers.clone());
+        assert_eq!(estimate.total_secs, Some(40.0));
+        assert_eq!(estimate.n_steps, 160);
+        // a single task takes as long as on the fastest worker accepting it
+        let estimate = super::new_estimate(&cost, 1, workers);
+        assert_eq!(estimate.total_secs, Some(10.0));
+        let estimate = super::new_estimate(&cost, 10, vec![worker(1, 0, 5.0)]);
+        assert_eq!(estimate.total_secs, None);
+    }
 }
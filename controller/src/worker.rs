@@ -14,7 +14,8 @@ use tokio::sync::{mpsc, oneshot, OwnedSemaphorePermit, Semaphore};
 use tokio_util::codec::{FramedRead, LengthDelimitedCodec};
 use uuid::Uuid;
 
-use worker_if::{Cost, Request, ResourceMeasure, Response};
+use parking_lot::Mutex;
+use worker_if::{Capacity, Cost, Load, Request, ResourceMeasure, Response, TaskExit, WorkerInfo};
 
 use crate::manager::OneshotNotifyReceiver;
 use crate::{
@@ -72,7 +73,12 @@ pub struct ServerConfig {
 pub(super) struct WorkerClient {
     connection: Arc<Connection>,
     semaphore: Arc<Semaphore>,
+    /// limits the tasks running at once
+    tasks: Arc<Semaphore>,
     measure: ResourceMeasure,
+    capacity: Capacity,
+    /// the latest load reported by the worker
+    load: Arc<Mutex<Load>>,
     index: usize,
 }
 
@@ -91,31 +97,75 @@ impl WorkerClient {
             .await?;
 
         let mut recv = connection.accept_uni().await?;
-        let measure = protocol::quic::read_data::<ResourceMeasure>(&mut recv).await?;
-        tracing::debug!("worker {} has {:?}", index, measure);
+        let WorkerInfo { measure, capacity } =
+            protocol::quic::read_data::<WorkerInfo>(&mut recv).await?;
+        tracing::debug!("worker {} has {:?} and {:?}", index, measure, capacity);
+
+        let load = Arc::new(Mutex::new(Load::default()));
+        let connection = Arc::new(connection);
+        tokio::spawn(Self::receive_loads(connection.clone(), load.clone(), index));
 
         Ok(Self {
-            connection: Arc::new(connection),
+            connection,
             semaphore: Arc::new(Semaphore::new(measure.max_resource as usize)),
+            tasks: Arc::new(Semaphore::new(capacity.max_concurrent_tasks as usize)),
             measure,
+            capacity,
+            load,
             index,
         })
     }
 
+    /// Keeps the latest load reported by the worker until the connection is closed.
+    async fn receive_loads(connection: Arc<Connection>, load: Arc<Mutex<Load>>, index: usize) {
+        while let Ok(mut recv) = connection.accept_uni().await {
+            match protocol::quic::read_data::<Load>(&mut recv).await {
+                Ok(l) => *load.lock() = l,
+                Err(e) => tracing::warn!("could not read a load of worker {} due to {}", index, e),
+            }
+        }
+    }
+
+    /// Returns the cost of a job with the mesh laid out on the threads of a task of this worker.
+    fn cost(&self, param: &api::job::JobParam) -> anyhow::Result<Cost> {
+        let threads = self.capacity.threads_per_task as usize;
+        let n_cells = world::n_cells(&param.world_params, threads)?;
+        Ok(Cost::new(param, n_cells, world::STEPS_PER_DAY))
+    }
+
+    /// Returns the CPU usage reported last.
+    fn cpu_usage(&self) -> f64 {
+        self.load.lock().cpu_usage(&self.capacity)
+    }
+
+    /// Returns whether `n` resources and a task slot are free now.
+    fn is_free(&self, n: u32) -> bool {
+        self.semaphore.available_permits() >= n as usize && self.tasks.available_permits() > 0
+    }
+
+    /// Waits for `n` resources and then for a task slot,
+    /// so that no slot is held while the resources are awaited.
     async fn acquire(self, n: u32) -> WorkerClientPermitted {
         let permit = self.semaphore.clone().acquire_many_owned(n).await.unwrap();
-        WorkerClientPermitted(self, permit)
+        let task = self.tasks.clone().acquire_owned().await.unwrap();
+        WorkerClientPermitted(self, permit, task)
+    }
+
+    fn try_acquire(&self, n: u32) -> Option<WorkerClientPermitted> {
+        let permit = self.semaphore.clone().try_acquire_many_owned(n).ok()?;
+        let task = self.tasks.clone().try_acquire_owned().ok()?;
+        Some(WorkerClientPermitted(self.clone(), permit, task))
     }
 
     pub async fn execute(
         &self,
         task_id: &TaskId,
         config: job::Config,
-    ) -> anyhow::Result<impl Future<Output = Option<bool>>> {
+    ) -> anyhow::Result<impl Future<Output = Option<TaskExit>>> {
         let (mut send, recv) = self.connection.open_bi().await?;
         protocol::quic::write_data(
             &mut send,
-            &Request::Execute(task_id.to_string(), config.param),
+            &Request::Execute(task_id.to_string(), Box::new(config.param)),
         )
         .await?;
 
@@ -132,7 +182,7 @@ impl WorkerClient {
                 .await
                 .unwrap()
                 .ok()
-                .map(|data| bincode::deserialize::<bool>(&data).unwrap())
+                .map(|data| bincode::deserialize::<TaskExit>(&data).unwrap())
         })
     }
 
@@ -160,6 +210,17 @@ impl WorkerClient {
         }
     }
 
+    /// Returns the end of the log of a task, where its output and how it ended are written.
+    pub async fn get_log(&self, task_id: &TaskId) -> anyhow::Result<Vec<u8>> {
+        let (mut send, recv) = self.connection.open_bi().await?;
+        protocol::quic::write_data(&mut send, &Request::ReadLog(task_id.to_string())).await?;
+        let mut stream = FramedRead::new(recv, LengthDelimitedCodec::new());
+        Ok(
+            bincode::deserialize::<Response<Vec<u8>>>(&stream.next().await.unwrap()?)?
+                .as_result()?,
+        )
+    }
+
     /// Returns a string vector of `TaskId`s whose statistics could not removed.
     pub async fn remove_statistics(&self, task_ids: &[TaskId]) -> anyhow::Result<Vec<String>> {
         let (mut send, mut recv) = self.connection.open_bi().await?;
@@ -173,7 +234,7 @@ impl WorkerClient {
 }
 
 #[derive(Debug)]
-pub(super) struct WorkerClientPermitted(WorkerClient, OwnedSemaphorePermit);
+pub(super) struct WorkerClientPermitted(WorkerClient, OwnedSemaphorePermit, OwnedSemaphorePermit);
 
 impl WorkerClientPermitted {
     pub fn index(&self) -> usize {
@@ -189,15 +250,20 @@ impl WorkerClientPermitted {
     ) {
         let worker = self.0;
         let semphore = self.1;
+        let task = self.2;
         let index = worker.index;
 
         tracing::debug!("preparing");
         db.update_task_state(&task_id, &TaskState::Assigned).await;
-        let Ok(fut) = worker.execute(&task_id, config).await else {
-                    db.update_task_state(&task_id, &TaskState::Failed).await;
-                    tracing::error!("could not execute");
-                    return;
-                };
+        let fut = match worker.execute(&task_id, config).await {
+            Ok(fut) => fut,
+            Err(e) => {
+                db.update_task_ended(&task_id, index, &TaskState::Failed)
+                    .await;
+                tracing::error!("could not execute due to {e}");
+                return;
+            }
+        };
 
         db.update_task_state(&task_id, &TaskState::Running).await;
         tracing::info!("executing");
@@ -213,15 +279,23 @@ impl WorkerClientPermitted {
         let result = fut.await;
         fq_handle.abort();
         drop(semphore);
+        drop(task);
 
         match result {
-            Some(true) => {
-                db.update_task_succeeded(&task_id, index).await;
+            Some(exit) if exit.success() => {
+                db.update_task_ended(&task_id, index, &TaskState::Succeeded)
+                    .await;
                 tracing::info!("terminated");
             }
-            _ => {
-                db.update_task_state(&task_id, &TaskState::Failed).await;
-                tracing::error!("failed due to an process error");
+            Some(exit) => {
+                db.update_task_ended(&task_id, index, &TaskState::Failed)
+                    .await;
+                tracing::error!("failed with {exit}");
+            }
+            None => {
+                db.update_task_ended(&task_id, index, &TaskState::Failed)
+                    .await;
+                tracing::error!("failed due to a connection error");
             }
         }
     }
@@ -229,7 +303,7 @@ impl WorkerClientPermitted {
 
 pub(super) struct WorkerManager {
     workers: Vec<WorkerClient>,
-    queue_tx: mpsc::Sender<(oneshot::Sender<WorkerClientPermitted>, Cost)>,
+    queue_tx: mpsc::Sender<(oneshot::Sender<WorkerClientPermitted>, Vec<Cost>)>,
 }
 
 impl WorkerManager {
@@ -246,17 +320,30 @@ impl WorkerManager {
 
         let workers = _workers.clone();
         tokio::spawn(async move {
-            while let Some((tx, cost)) = queue_rx.recv().await {
+            while let Some((tx, costs)) = queue_rx.recv().await {
+                let candidates = workers
+                    .iter()
+                    .zip(&costs)
+                    .filter_map(|(client, cost)| Some((client, client.measure.measure(cost).ok()?)))
+                    .collect::<Vec<_>>();
+                // the live load only breaks the tie among the workers which are free now
+                let available = candidates
+                    .iter()
+                    .filter(|(client, res)| client.is_free(*res))
+                    .min_by(|(a, _), (b, _)| a.cpu_usage().total_cmp(&b.cpu_usage()))
+                    .and_then(|(client, res)| client.try_acquire(*res));
                 let mut futs = FuturesUnordered::new();
-                for client in &workers {
-                    let Ok(res) = client.measure.measure(&cost) else {
-                            continue;
-                        };
-                    let client = client.clone();
-                    tracing::debug!("register {}", client.index);
-                    futs.push(client.acquire(res));
+                if available.is_none() {
+                    for (client, res) in candidates {
+                        let client = client.clone();
+                        tracing::debug!("register {}", client.index);
+                        futs.push(client.acquire(res));
+                    }
                 }
-                if let Some(permit) = futs.next().await {
+                if let Some(permit) = match available {
+                    Some(permit) => Some(permit),
+                    None => futs.next().await,
+                } {
                     tracing::debug!("acquired at {}", permit.0.index);
                     if let Err(_) = tx.send(permit) {
                         tracing::debug!("a lease has already dropped");
@@ -271,6 +358,45 @@ impl WorkerManager {
         })
     }
 
+    /// Returns the cost of a job on each worker, which lays out the mesh for its own threads.
+    pub fn costs(&self, param: &api::job::JobParam) -> anyhow::Result<Vec<Cost>> {
+        self.workers
+            .iter()
+            .map(|client| client.cost(param))
+            .collect()
+    }
+
+    /// Returns whether some worker has enough resource for its cost in `costs`.
+    pub fn can_accept(&self, costs: &[Cost]) -> bool {
+        self.workers
+            .iter()
+            .zip(costs)
+            .any(|(client, cost)| client.measure.measure(cost).is_ok())
+    }
+
+    /// Returns the resource and the running time of a task of `costs` on each worker.
+    pub fn estimate(&self, costs: &[Cost]) -> Vec<job::WorkerEstimate> {
+        self.workers
+            .iter()
+            .zip(costs)
+            .map(|(client, cost)| {
+                let resource = client.measure.measure(cost).ok();
+                let max_resource = client.measure.max_resource;
+                let concurrent_tasks = resource.map_or(0, |res| {
+                    (max_resource / res).min(client.capacity.max_concurrent_tasks)
+                });
+                job::WorkerEstimate {
+                    worker: client.index as u32,
+                    resource,
+                    max_resource,
+                    concurrent_tasks,
+                    n_cells: cost.n_cells,
+                    task_secs: client.measure.model.secs(cost),
+                }
+            })
+            .collect()
+    }
+
     pub fn get_worker_count(&self) -> usize {
         self.workers.len()
     }
@@ -279,9 +405,10 @@ impl WorkerManager {
         &self.workers[index]
     }
 
-    pub async fn lease(&self, cost: Cost) -> WorkerLease {
+    /// Waits for a worker which has enough resource for its cost in `costs`.
+    pub async fn lease(&self, costs: Vec<Cost>) -> WorkerLease {
         let (tx, rx) = oneshot::channel();
-        self.queue_tx.send((tx, cost)).await.unwrap();
+        self.queue_tx.send((tx, costs)).await.unwrap();
         WorkerLease(rx)
     }
 }
@@ -0,0 +1,149 @@
+//! Run with `cargo bench -p world-core --features bench`.
+
+use std::time::{Duration, Instant};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use world_core::{
+    scenario::Scenario,
+    util::random::DistInfo,
+    world::{
+        commons::{RuntimeParams, WorldParams},
+        World,
+    },
+};
+
+const POPULATIONS: [u32; 3] = [1_000, 5_000, 20_000];
+/// steps run before measuring so that the infection has spread
+const WARM_UP_STEPS: u32 = 32;
+
+fn new_world_params(init_n_pop: u32) -> WorldParams {
+    WorldParams::new(
+        init_n_pop,
+        360,
+        18,
+        16,
+        1.0.into(),
+        0.0.into(),
+        20.0.into(),
+        50.0.into(),
+        None,
+        150.0.into(),
+        50.0,
+        500.0.into(),
+        40.0.into(),
+        30.0.into(),
+        90.0.into(),
+        95.0.into(),
+        14.0,
+        7.0,
+        120.0,
+        90.0.into(),
+    )
+}
+
+fn new_runtime_params() -> RuntimeParams {
+    RuntimeParams {
+        mass: 50.0.into(),
+        friction: 80.0.into(),
+        avoidance: 50.0,
+        max_speed: 50.0,
+        act_mode: 50.0.into(),
+        act_kurt: 0.0.into(),
+        mob_act: 50.0.into(),
+        gat_act: 50.0.into(),
+        incub_act: 0.0.into(),
+        fatal_act: 0.0.into(),
+        infec: 50.0.into(),
+        infec_dst: 3.0,
+        contag_delay: 0.5,
+        contag_peak: 3.0,
+        incub: DistInfo::new(1.0, 5.0, 14.0),
+        fatal: DistInfo::new(4.0, 16.0, 20.0),
+        therapy_effc: 0.0.into(),
+        imn_max_dur: 200.0,
+        imn_max_dur_sv: 50.0.into(),
+        imn_max_effc: 90.0.into(),
+        imn_max_effc_sv: 20.0.into(),
+        dst_st: 50.0,
+        dst_ob: 20.0.into(),
+        mob_freq: DistInfo::new(40.0.into(), 70.0.into(), 100.0.into()),
+        mob_dist: DistInfo::new(10.0.into(), 30.0.into(), 80.0.into()),
+        back_hm_rt: 75.0.into(),
+        gat_fr: 50.0,
+        gat_rnd_rt: 50.0.into(),
+        gat_sz: DistInfo::new(5.0, 10.0, 20.0),
+        gat_dr: DistInfo::new(6.0, 12.0, 24.0),
+        gat_st: DistInfo::new(50.0, 80.0, 100.0),
+        gat_freq: DistInfo::new(40.0.into(), 70.0.into(), 100.0.into()),
+        cntct_trc: 20.0.into(),
+        tst_delay: 1.0,
+        tst_proc: 1.0,
+        tst_interval: 2.0,
+        tst_sens: 70.0.into(),
+        tst_spec: 99.8.into(),
+        tst_sbj_asy: 1.0.into(),
+        tst_sbj_sym: 99.0.into(),
+        tst_capa: 50.0.into(),
+        tst_dly_lim: 3.0,
+        ..Default::default()
+    }
+}
+
+/// Returns a world with the derived mesh after warming up.
+fn new_world(init_n_pop: u32) -> World {
+    let rp = new_runtime_params();
+    let mut wp = new_world_params(init_n_pop);
+    wp.set_mesh(wp.auto_mesh(rayon::current_num_threads()));
+    let mut world = World::new(
+        "bench".to_string(),
+        rp,
+        wp,
+        Scenario::new(Vec::<()>::new(), |_| unreachable!()),
+    );
+    for _ in 0..WARM_UP_STEPS {
+        world.step();
+    }
+    world
+}
+
+fn bench_step(c: &mut Criterion) {
+    let mut group = c.benchmark_group("step");
+    group.sample_size(10);
+    for n in POPULATIONS {
+        let mut world = new_world(n);
+        group.bench_with_input(BenchmarkId::from_parameter(n), &n, |b, _| {
+            b.iter(|| world.step())
+        });
+    }
+    group.finish();
+}
+
+fn bench_phases(c: &mut Criterion) {
+    let mut group = c.benchmark_group("phase");
+    for n in POPULATIONS {
+        let mut world = new_world(n);
+        group.bench_with_input(BenchmarkId::new("interact", n), &n, |b, _| {
+            b.iter(|| world.bench_interact())
+        });
+        group.bench_with_input(BenchmarkId::new("gatherings", n), &n, |b, _| {
+            b.iter(|| world.bench_gatherings())
+        });
+        // tests are reserved during a step and accepted at the beginning of the next one
+        group.bench_with_input(BenchmarkId::new("accept_tests", n), &n, |b, _| {
+            b.iter_custom(|iters| {
+                let mut total = Duration::ZERO;
+                for _ in 0..iters {
+                    world.step();
+                    let start = Instant::now();
+                    world.bench_accept_tests();
+                    total += start.elapsed();
+                }
+                total
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_step, bench_phases);
+criterion_main!(benches);
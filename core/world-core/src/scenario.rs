@@ -1,9 +1,9 @@
 use std::collections::VecDeque;
 
-use math::Permille;
+use math::{Percentage, Permille};
 use scenario_operation::{
-    accessor, Assign, Assignment, ConditionField, EvalField, Interpolate, MyField, Operation,
-    VaccinationStrategy,
+    accessor, Assign, Assignment, ConditionField, EvalField, GatheringEvent, GatheringSpot,
+    Interpolate, MyField, Operation, VaccinationStrategy,
 };
 
 use crate::world::commons::{self, RuntimeParams};
@@ -25,8 +25,12 @@ impl Scenario {
     }
 
     pub fn exec(&mut self, rp: &mut RuntimeParams) {
-        let op = &self.operations[self.index];
-        if op.condition.eval(rp) {
+        if let Some(op) = self
+            .operations
+            .get(self.index)
+            .filter(|op| op.condition.eval(rp))
+        {
+            self.index += 1;
             for a in &op.assignments {
                 match a {
                     Assignment::Value(v) => self.curr.push(VecDeque::from([v.clone()])),
@@ -61,6 +65,24 @@ accessor!(env: commons::VaccinationStrategy, VaccinationStrategy {
         set { env.perform_rate = Permille(v); }
 });
 
+accessor!(spot: commons::GatheringSpot, GatheringSpot {
+    Open(v) =>
+        get { &spot.open }
+        set { spot.open = v; }
+    Weight(v) =>
+        get { &spot.weight }
+        set { spot.weight = v; }
+});
+
+accessor!(ev: commons::GatheringEvent, GatheringEvent {
+    Cancelled(v) =>
+        get { &ev.cancelled }
+        set { ev.cancelled = v; }
+    Participation(v) =>
+        get { &ev.participation.0 }
+        set { ev.participation = Percentage(v); }
+});
+
 accessor!(rp: RuntimeParams, MyField {
     GatheringFrequency(v) =>
         get { &rp.gat_fr }
@@ -72,6 +94,20 @@ accessor!(rp: RuntimeParams, MyField {
                 Assign::assign(t, v.value);
             }
         }
+    GatheringSpot(v) =>
+        get { &rp.gat_spots[v.index] }
+        set {
+            if let Some(t) = rp.gat_spots.get_mut(v.index) {
+                Assign::assign(t, v.value);
+            }
+        }
+    GatheringEvent(v) =>
+        get { &rp.gat_events[v.index] }
+        set {
+            if let Some(t) = rp.gat_events.get_mut(v.index) {
+                Assign::assign(t, v.value);
+            }
+        }
 });
 
 impl EvalField<ConditionField> for RuntimeParams {
@@ -7,7 +7,7 @@ use std::{
 };
 
 use arrow2::{
-    array::{MutableArray, UInt32Vec},
+    array::{Array, MutableArray, UInt32Vec},
     chunk::Chunk,
     datatypes::{DataType, Field, Schema},
     io::ipc::write::{Compression, FileWriter, WriteOptions},
@@ -43,18 +43,223 @@ impl HistInfo {
     }
 }
 
+#[derive(macros::Enum, Clone, Debug, strum::Display)]
+pub enum TracingType {
+    Tested,
+    Vaccinated,
+    Quarantined,
+    /// members of the household of a positive agent ordered to quarantine
+    HouseholdQuarantined,
+}
+
+#[derive(macros::Enum, Clone, Debug, strum::Display)]
+pub enum IsolationType {
+    Hospitalized,
+    WaitingForBed,
+    HomeQuarantined,
+}
+
+/// where an agent gets infected
+#[derive(macros::Enum, Clone, Debug, strum::Display)]
+pub enum InfectionPlace {
+    Home,
+    Work,
+    Elsewhere,
+    /// by a household member regardless of distance
+    Household,
+}
+
+/// what happens to agents at the border of a region
+#[derive(macros::Enum, Clone, Debug, strum::Display)]
+pub enum BorderEvent {
+    Entered,
+    Refused,
+    Tested,
+    Quarantined,
+    Imported,
+}
+
+/// whether a new infection is the first one of the agent
+#[derive(macros::Enum, Clone, Debug, strum::Display)]
+pub enum InfectionType {
+    Infected,
+    Reinfected,
+}
+
 #[derive(Default)]
 pub struct Stat {
     pub hists: Vec<HistInfo>,
     pub infcts: Vec<InfectionCntInfo>,
-    pub health_stat: HealthStat,
+    pub health_stat: CountStat<HealthType>,
+    pub tracing_count: EnumMap<TracingType, u32>,
+    pub tracing_stat: CountStat<TracingType>,
+    pub isolation_stat: CountStat<IsolationType>,
+    pub infection_place_count: EnumMap<InfectionPlace, u32>,
+    pub infection_place_stat: CountStat<InfectionPlace>,
+    pub infection_type_count: EnumMap<InfectionType, u32>,
+    pub infection_type_stat: CountStat<InfectionType>,
+    /// health count of vaccine refusers
+    pub refuser_count: HealthCount,
+    pub refuser_stat: CountStat<HealthType>,
+    pub border_count: EnumMap<BorderEvent, u32>,
+    pub border_stat: CountStat<BorderEvent>,
+    /// health count of the residents of each region
+    pub region_count: Vec<HealthCount>,
+    pub region_stat: Vec<CountStat<HealthType>>,
+    /// agents who have received each number of doses from one
+    pub dose_count: Vec<u32>,
+    pub dose_stat: Vec<UInt32Vec>,
+    /// infections in each scheduled event
+    pub event_count: Vec<u32>,
+    pub event_stat: Vec<UInt32Vec>,
 }
 
 impl Stat {
     pub fn reset(&mut self) {
         self.hists.clear();
         self.infcts.clear();
-        self.health_stat = HealthStat::default();
+        self.health_stat = CountStat::default();
+        self.tracing_count = EnumMap::default();
+        self.tracing_stat = CountStat::default();
+        self.isolation_stat = CountStat::default();
+        self.infection_place_count = EnumMap::default();
+        self.infection_place_stat = CountStat::default();
+        self.infection_type_count = EnumMap::default();
+        self.infection_type_stat = CountStat::default();
+        self.refuser_count = HealthCount::default();
+        self.refuser_stat = CountStat::default();
+        self.border_count = EnumMap::default();
+        self.border_stat = CountStat::default();
+        self.region_count.clear();
+        self.region_stat.clear();
+        self.event_count.clear();
+        self.event_stat.clear();
+        self.dose_count.clear();
+        self.dose_stat.clear();
+    }
+
+    /// Moves an agent who has received the `n`-th dose from the count of `n - 1` doses.
+    pub fn add_dose(&mut self, n: u32) {
+        let n = n as usize;
+        if self.dose_count.len() < n {
+            self.dose_count.resize(n, 0);
+        }
+        if n > 1 {
+            self.dose_count[n - 2] -= 1;
+        }
+        self.dose_count[n - 1] += 1;
+    }
+
+    pub fn set_events(&mut self, n: usize) {
+        self.event_count = vec![0; n];
+        self.event_stat = (0..n).map(|_| UInt32Vec::new()).collect();
+    }
+
+    pub fn set_regions(&mut self, counts: Vec<HealthCount>) {
+        self.region_stat = counts.iter().map(|_| CountStat::default()).collect();
+        self.region_count = counts;
+    }
+
+    /// Applies the difference to the refusers' and the region's counts if needed.
+    pub fn apply_difference(&mut self, hd: &HealthDiff) {
+        if hd.refuser {
+            self.refuser_count.0[&hd.from] -= 1;
+            self.refuser_count.0[&hd.to] += 1;
+        }
+        if let Some(r) = hd.region {
+            self.region_count[r].0[&hd.from] -= 1;
+            self.region_count[r].0[&hd.to] += 1;
+        }
+    }
+
+    pub fn add_tracing_count(&mut self, count: &EnumMap<TracingType, u32>) {
+        for (t, c) in count {
+            self.tracing_count[&t] += c;
+        }
+    }
+
+    /// records the counts of the current step
+    pub fn push(
+        &mut self,
+        health_count: &HealthCount,
+        isolation_count: &EnumMap<IsolationType, u32>,
+    ) {
+        let len = self.health_stat.len();
+        self.health_stat.push(&health_count.0);
+        self.isolation_stat.push(isolation_count);
+        self.infection_place_stat
+            .push(&std::mem::take(&mut self.infection_place_count));
+        self.infection_type_stat
+            .push(&std::mem::take(&mut self.infection_type_count));
+        self.refuser_stat.push(&self.refuser_count.0);
+        self.border_stat
+            .push(&std::mem::take(&mut self.border_count));
+        for (s, c) in self.region_stat.iter_mut().zip(&self.region_count) {
+            s.push(&c.0);
+        }
+        // the columns of new numbers of doses are zero before
+        self.dose_stat.resize_with(self.dose_count.len(), || {
+            let mut v = UInt32Vec::new();
+            v.extend_constant(len, Some(0));
+            v
+        });
+        for (s, c) in self.dose_stat.iter_mut().zip(&self.dose_count) {
+            s.push(Some(*c));
+        }
+        for (s, c) in self.event_stat.iter_mut().zip(self.event_count.iter_mut()) {
+            s.push(Some(std::mem::take(c)));
+        }
+        self.tracing_stat
+            .push(&std::mem::take(&mut self.tracing_count));
+    }
+
+    pub fn export(&mut self, path: &Path) -> anyhow::Result<()> {
+        let mut fields = Vec::new();
+        let mut arrays = Vec::new();
+        for (field, array) in self
+            .health_stat
+            .columns("")
+            .into_iter()
+            .chain(self.tracing_stat.columns("Traced"))
+            .chain(self.isolation_stat.columns(""))
+            .chain(self.infection_place_stat.columns("InfectedAt"))
+            .chain(self.infection_type_stat.columns("New"))
+            .chain(self.refuser_stat.columns("AntiVax"))
+            .chain(self.border_stat.columns("Border"))
+            .chain(
+                self.region_stat
+                    .iter_mut()
+                    .enumerate()
+                    .flat_map(|(i, s)| s.columns(&format!("Region{i}"))),
+            )
+            .chain(self.dose_stat.iter_mut().enumerate().map(|(i, v)| {
+                (
+                    Field::new(format!("Doses{}", i + 1), DataType::UInt32, false),
+                    v.as_box(),
+                )
+            }))
+            .chain(self.event_stat.iter_mut().enumerate().map(|(i, v)| {
+                (
+                    Field::new(format!("InfectedAtEvent{i}"), DataType::UInt32, false),
+                    v.as_box(),
+                )
+            }))
+        {
+            fields.push(field);
+            arrays.push(array);
+        }
+        let chunk = Chunk::try_new(arrays)?;
+        let mut writer = FileWriter::try_new(
+            File::create(path)?,
+            Schema::from(fields),
+            None,
+            WriteOptions {
+                compression: Some(Compression::ZSTD),
+            },
+        )?;
+        writer.write(&chunk, None)?;
+        writer.finish()?;
+        Ok(())
     }
 }
 
@@ -62,11 +267,19 @@ impl Stat {
 pub struct HealthDiff {
     from: HealthType,
     to: HealthType,
+    refuser: bool,
+    /// region of residence
+    region: Option<usize>,
 }
 
 impl HealthDiff {
-    pub fn new(from: HealthType, to: HealthType) -> Self {
-        Self { from, to }
+    pub fn new(from: HealthType, to: HealthType, refuser: bool, region: Option<usize>) -> Self {
+        Self {
+            from,
+            to,
+            refuser,
+            region,
+        }
     }
 }
 
@@ -98,40 +311,39 @@ impl<'a> IndexMut<&'a HealthType> for HealthCount {
     }
 }
 
-#[derive(Default, Debug)]
-pub struct HealthStat(EnumMap<HealthType, UInt32Vec>);
+/// time series of the counts for each kind of `K`
+#[derive(Debug)]
+pub struct CountStat<K: Enum>(EnumMap<K, UInt32Vec>);
 
-impl HealthStat {
-    pub fn push(&mut self, count: HealthCount) {
-        for health in &HealthType::ALL {
-            self.0[health].push(Some(count[health]));
+impl<K: Enum> Default for CountStat<K> {
+    fn default() -> Self {
+        Self(EnumMap::default())
+    }
+}
+
+impl<K: Enum + std::fmt::Display> CountStat<K> {
+    /// Returns the number of pushed counts.
+    fn len(&self) -> usize {
+        self.0.values().first().map_or(0, |v| v.len())
+    }
+
+    pub fn push(&mut self, count: &EnumMap<K, u32>) {
+        for (k, c) in count {
+            self.0[&k].push(Some(*c));
         }
     }
 
-    pub fn export(&mut self, path: &Path) -> anyhow::Result<()> {
-        let schema = Schema::from(
-            HealthType::ALL
-                .into_iter()
-                .map(|h| Field::new(h.to_string(), DataType::UInt32, false))
-                .collect::<Vec<_>>(),
-        );
-        let chunk = Chunk::try_new(
-            self.0
-                .values_mut()
-                .into_iter()
-                .map(|v| v.as_box())
-                .collect(),
-        )?;
-        let mut writer = FileWriter::try_new(
-            File::create(path)?,
-            schema,
-            None,
-            WriteOptions {
-                compression: Some(Compression::ZSTD),
-            },
-        )?;
-        writer.write(&chunk, None)?;
-        writer.finish()?;
-        Ok(())
+    /// Returns arrow columns named by `prefix` followed by each kind.
+    pub fn columns(&mut self, prefix: &str) -> Vec<(Field, Box<dyn Array>)> {
+        K::ALL
+            .into_iter()
+            .zip(self.0.values_mut())
+            .map(|(k, v)| {
+                (
+                    Field::new(format!("{prefix}{k}"), DataType::UInt32, false),
+                    v.as_box(),
+                )
+            })
+            .collect()
     }
 }
@@ -1,27 +1,35 @@
 mod agent;
 pub mod commons;
 mod contact;
+pub mod density;
+pub mod geometry;
+pub mod immunity;
+pub mod infection;
+pub mod profile;
 pub(super) mod testing;
 
 use enum_map::{enum_map, EnumMap};
 use rand::{seq::SliceRandom, Rng};
-use std::path::Path;
+use std::{collections::BTreeMap, path::Path};
 
 use self::{
     agent::{
-        cemetery::Cemetery, field::Field, gathering::Gatherings, hospital::Hospital, warp::Warps,
-        Agent, AgentRef,
+        cemetery::Cemetery, field::Field, gathering::Gatherings, home_quarantine::HomeQuarantine,
+        hospital::Hospital, household::Households, warp::Warps, Agent, AgentRef,
     },
     commons::{
-        FiniteTypePool, HealthType, ParamsForStep, RuntimeParams, VaccinePriority, WorldParams,
+        FiniteTypePool, HealthType, ParamsForStep, RuntimeParams, VaccinePriority, WorkPlaceMode,
+        WorldParams,
     },
+    profile::{Phase, Profile},
     testing::TestQueue,
 };
 use crate::{
     scenario::Scenario,
-    stat::{HealthCount, Stat},
+    stat::{BorderEvent, HealthCount, IsolationType, Stat},
 };
 use math::Point;
+use table::Table;
 
 pub struct World {
     pub id: String,
@@ -32,6 +40,7 @@ pub struct World {
     field: Field,
     warps: Warps,
     hospital: Hospital,
+    home_quarantine: HomeQuarantine,
     cemetery: Cemetery,
     test_queue: TestQueue,
     //[todo] predicate_to_stop: bool,
@@ -39,12 +48,15 @@ pub struct World {
     stat: Stat,
     scenario: Scenario,
     gatherings: Gatherings,
-    gat_spots_fixed: Vec<Point>,
+    households: Households,
     //[todo] n_mesh: usize,
     //[todo] n_pop: usize,
     // variant_info: Vec<VariantInfo>,
     vaccine_queue: EnumMap<VaccinePriority, Vec<AgentRef>>,
     vaccine_queue_idx: EnumMap<VaccinePriority, usize>,
+    /// fractions of doses carried over to the next step for each strategy
+    vcn_subj_rem: BTreeMap<usize, f64>,
+    profile: Profile,
 }
 
 impl World {
@@ -60,19 +72,22 @@ impl World {
             runtime_params,
             scenario,
             agents: Vec::with_capacity(n_pop),
-            field: Field::new(world_params.mesh),
+            field: Field::new(&world_params),
             world_params,
             warps: Warps::new(n_pop),
             hospital: Hospital::new(n_pop),
+            home_quarantine: HomeQuarantine::new(n_pop),
             cemetery: Cemetery::new(n_pop),
             agent_origins: Vec::with_capacity(n_pop),
-            gat_spots_fixed: Vec::new(),
             health_count: Default::default(),
             stat: Stat::default(),
             gatherings: Gatherings::new(),
+            households: Households::new(),
             test_queue: TestQueue::new(),
             vaccine_queue: enum_map!(VaccinePriority { _ => Vec::new(),}),
             vaccine_queue_idx: enum_map!(VaccinePriority { _ => 0,}),
+            vcn_subj_rem: BTreeMap::new(),
+            profile: Profile::default(),
         };
 
         w.reset();
@@ -83,21 +98,15 @@ impl World {
         //[todo] set runtime params of scenario != None
         self.field.clear(&mut self.agents);
         self.hospital.clear(&mut self.agents);
+        self.home_quarantine.clear(&mut self.agents);
         self.cemetery.clear(&mut self.agents);
         self.warps.clear(&mut self.agents);
         self.agent_origins.clear();
 
         let n_pop = self.world_params.init_n_pop as usize;
         let n_dist = (self.runtime_params.dst_ob.r() * self.world_params.init_n_pop()) as usize;
-        let n_infected = (self.world_params.init_n_pop() * self.world_params.infected.r()) as usize;
-        let n_recovered = {
-            let k = (self.world_params.init_n_pop() * self.world_params.recovered.r()) as usize;
-            if n_infected + k > n_pop {
-                n_pop - n_infected
-            } else {
-                k
-            }
-        };
+        let groups = self.world_params.initial_groups();
+        let n_infected = groups.iter().map(|g| g.1).sum::<usize>();
 
         let cur_len = self.agents.len();
         if n_pop < cur_len {
@@ -110,28 +119,24 @@ impl World {
             }
         }
 
-        let n_symptomatic = agent::allocation::allocate_agents(
+        let (cats, n_symptomatic) = agent::allocation::reset_agents(
             &mut self.agents,
-            &mut self.field,
-            &mut self.hospital,
             &mut self.agent_origins,
-            n_pop,
-            n_infected,
-            n_recovered,
+            &groups,
             n_dist,
             &self.world_params,
             &self.runtime_params,
         );
 
-        // reset test queue
-        self.runtime_params.step = 0;
-        self.health_count[&HealthType::Susceptible] = (n_pop - n_infected) as u32;
-        self.health_count[&HealthType::Symptomatic] = n_symptomatic as u32;
-        self.health_count[&HealthType::Asymptomatic] = (n_infected - n_symptomatic) as u32;
-
-        self.stat.reset();
-        self.scenario.reset();
-        self.gatherings.clear();
+        self.households.assign(
+            &mut self.agents,
+            &mut self.agent_origins,
+            &self.world_params,
+        );
+        agent::allocation::assign_workplaces(&mut self.agents, &self.world_params);
+        let refuser_count =
+            agent::allocation::assign_refusers(&mut self.agents, &self.world_params);
+        let region_counts = agent::allocation::count_regions(&self.agents, &self.world_params);
 
         // reset vaccine queue
         let q = {
@@ -149,17 +154,18 @@ impl World {
                 }
                 VaccinePriority::Central => {
                     let cx = self.world_params.field_size() / 2.0;
+                    let cy = self.world_params.field_height() / 2.0;
                     let mut q = match self.world_params.wrk_plc_mode {
                         None => (0..n_pop)
                             .map(|i| {
                                 let p = self.agents[i].get_pt();
-                                (i, (p.x + cx).hypot(p.y + cx))
+                                (i, (p.x + cx).hypot(p.y + cy))
                             })
                             .collect::<Vec<_>>(),
                         Some(_) => (0..n_pop)
                             .map(|i| {
                                 let p = &self.agent_origins[i];
-                                (i, (p.x + cx).hypot(p.y + cx))
+                                (i, (p.x + cx).hypot(p.y + cy))
                             })
                             .collect::<Vec<_>>(),
                     };
@@ -168,6 +174,37 @@ impl World {
                         queue.push((&self.agents[idx]).into());
                     }
                 }
+                VaccinePriority::PopulationDensity => {
+                    let wp = &self.world_params;
+                    let home = |i: usize| match wp.wrk_plc_mode {
+                        None => self.agents[i].get_pt(),
+                        Some(_) => &self.agent_origins[i],
+                    };
+                    let mut q = match &wp.wrk_plc_mode {
+                        Some(WorkPlaceMode::PopDistImg(map)) => (0..n_pop)
+                            .map(|i| {
+                                (
+                                    i,
+                                    map.density_at(home(i), wp.field_size(), wp.field_height()),
+                                )
+                            })
+                            .collect::<Vec<_>>(),
+                        _ => {
+                            let mut counts = Table::new(wp.mesh_rows(), wp.mesh, || 0u32);
+                            for i in 0..n_pop {
+                                counts[wp.into_grid_index(home(i))] += 1;
+                            }
+                            (0..n_pop)
+                                .map(|i| (i, counts[wp.into_grid_index(home(i))] as f64))
+                                .collect::<Vec<_>>()
+                        }
+                    };
+                    // the densest first
+                    q.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+                    for (idx, _) in q {
+                        queue.push((&self.agents[idx]).into());
+                    }
+                }
                 _ => {
                     for idx in 0..n_pop {
                         queue.push((&self.agents[idx]).into());
@@ -176,6 +213,32 @@ impl World {
             }
         }
 
+        agent::allocation::place_agents(
+            &mut self.agents,
+            &cats,
+            n_infected,
+            n_symptomatic,
+            &mut self.field,
+            &mut self.hospital,
+            &self.world_params,
+        );
+
+        // reset test queue
+        self.runtime_params.step = 0;
+        self.runtime_params.local_step = 0;
+        self.runtime_params.days_elapsed = 0;
+        self.health_count[&HealthType::Susceptible] = (n_pop - n_infected) as u32;
+        self.health_count[&HealthType::Symptomatic] = n_symptomatic as u32;
+        self.health_count[&HealthType::Asymptomatic] = (n_infected - n_symptomatic) as u32;
+
+        self.stat.reset();
+        self.stat.refuser_count = refuser_count;
+        self.stat.set_regions(region_counts);
+        self.stat.set_events(self.runtime_params.gat_events.len());
+        self.vcn_subj_rem.clear();
+        self.scenario.reset();
+        self.gatherings.clear();
+
         for idx in self.vaccine_queue_idx.values_mut() {
             *idx = 0;
         }
@@ -183,30 +246,26 @@ impl World {
 
     pub fn step(&mut self) {
         let pfs = ParamsForStep::new(&self.world_params, &self.runtime_params);
+        let mut laps = self.profile.start();
 
         let mut count_reason = EnumMap::default();
         let mut count_result = EnumMap::default();
         self.test_queue
             .accept(&pfs, &mut count_reason, &mut count_result);
+        laps.lap(Phase::TestQueue);
 
-        if !pfs.go_home_back() {
-            self.gatherings.step(
-                &mut self.field,
-                &self.gat_spots_fixed,
-                &self.agent_origins,
-                &pfs,
-            );
-        }
+        self.gatherings
+            .step(&mut self.field, &self.agent_origins, &pfs);
+        laps.lap(Phase::Gatherings);
 
         // distribute vaccines
-        let mut vcn_subj_rem = vec![0.0];
         // let mut trc_vcn_set = Vec::new();
         let n_pop = pfs.wp.init_n_pop as usize;
         for (&index, vp) in &pfs.rp.vx_stg {
             if vp.perform_rate.r() <= 0.0 {
                 continue;
             }
-            let v = &mut vcn_subj_rem[index];
+            let v = self.vcn_subj_rem.entry(index).or_default();
             let f = pfs.wp.init_n_pop() * vp.perform_rate.r() * pfs.wp.days_per_step() + *v;
             let mut cnt = f.floor() as usize;
             *v = f.fract();
@@ -217,13 +276,14 @@ impl World {
             // tracing vaccination targets
             let idx = &mut self.vaccine_queue_idx[&vp.priority];
             let queue = &self.vaccine_queue[&vp.priority];
-            let vaccine = pfs.rp.vaccine_pool.get(index);
+            let vaccine = pfs.rp.vaccine_pool.get(vp.vaccine);
+            let booster = matches!(vp.priority, VaccinePriority::Booster);
 
             if matches!(vp.priority, VaccinePriority::Random) || vp.regularity.r() >= 1.0 {
                 let (ql, qr) = queue.split_at(*idx);
                 let q_iter = qr.into_iter().chain(ql.into_iter());
                 for a in q_iter {
-                    if a.try_give_vaccine_ticket(vaccine.clone()) {
+                    if a.try_give_vaccine_ticket(vaccine.clone(), booster, &pfs) {
                         cnt -= 1;
                     }
                     *idx += 1;
@@ -240,7 +300,7 @@ impl World {
                         0
                     };
                     let j = (*idx + d) % n_pop;
-                    if queue[j].try_give_vaccine_ticket(vaccine.clone()) {
+                    if queue[j].try_give_vaccine_ticket(vaccine.clone(), booster, &pfs) {
                         cnt -= 1;
                     }
                     *idx += 1;
@@ -251,7 +311,31 @@ impl World {
             }
             *idx = *idx % n_pop;
         }
+        laps.lap(Phase::Vaccination);
+
+        self.households
+            .step(&mut self.stat, &mut self.health_count, &pfs);
+        laps.lap(Phase::Households);
 
+        for (&region, bc) in &pfs.rp.brd_ctrl {
+            if bc.imp_rate <= 0.0 {
+                continue;
+            }
+            let f = bc.imp_rate * pfs.wp.days_per_step();
+            let mut n = f as usize;
+            if f.fract() > rand::thread_rng().gen() {
+                n += 1;
+            }
+            if n > 0 {
+                let variant = pfs.rp.variant_pool.get(bc.imp_variant);
+                let n = self.field.import_cases(region, n, &variant, &pfs);
+                self.stat.border_count[&BorderEvent::Imported] += n as u32;
+            }
+        }
+        laps.lap(Phase::Imports);
+
+        self.field.interact(&pfs);
+        laps.lap(Phase::Interact);
         self.field.step(
             &mut self.warps,
             &mut self.test_queue,
@@ -259,23 +343,55 @@ impl World {
             &mut self.health_count,
             &pfs,
         );
+        laps.lap(Phase::Field);
         self.hospital.step(
             &mut self.warps,
             &mut self.stat,
             &mut self.health_count,
             &pfs,
         );
+        laps.lap(Phase::Hospital);
+        self.home_quarantine.step(
+            &mut self.warps,
+            &mut self.stat,
+            &mut self.health_count,
+            &pfs,
+        );
+        laps.lap(Phase::HomeQuarantine);
         self.warps.step(
             &mut self.field,
             &mut self.hospital,
+            &mut self.home_quarantine,
             &mut self.cemetery,
             &mut self.test_queue,
+            &mut self.stat,
             &pfs,
         );
+        laps.lap(Phase::Warps);
 
-        self.stat.health_stat.push(self.health_count.clone());
+        self.stat.push(
+            &self.health_count,
+            &enum_map!(IsolationType {
+                Hospitalized => self.hospital.n_occupied() as u32,
+                WaitingForBed => self.hospital.n_waiting() as u32,
+                HomeQuarantined => self.home_quarantine.len() as u32,
+                _ => 0,
+            }),
+        );
+        laps.lap(Phase::Stat);
         self.scenario.exec(&mut self.runtime_params);
-        self.runtime_params.step += 1;
+        self.runtime_params.step(&self.world_params);
+        laps.lap(Phase::Scenario);
+    }
+
+    /// Enables measuring the time spent in each phase of a step, which resets the measurement.
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.profile.set_enabled(enabled);
+    }
+
+    #[inline]
+    pub fn profile(&self) -> &Profile {
+        &self.profile
     }
 
     #[inline]
@@ -286,7 +402,29 @@ impl World {
     pub fn export(&mut self, dir: &str) -> anyhow::Result<()> {
         let path = Path::new(dir);
         self.stat
-            .health_stat
             .export(&path.join(&self.id).with_extension("arrow"))
     }
 }
+
+/// Phases of a step run alone for benchmarks.
+#[cfg(feature = "bench")]
+impl World {
+    /// Makes the agents in the field interact without applying the results.
+    pub fn bench_interact(&mut self) {
+        let pfs = ParamsForStep::new(&self.world_params, &self.runtime_params);
+        self.field.interact(&pfs);
+        self.field.discard_interactions();
+    }
+
+    pub fn bench_gatherings(&mut self) {
+        let pfs = ParamsForStep::new(&self.world_params, &self.runtime_params);
+        self.gatherings
+            .step(&mut self.field, &self.agent_origins, &pfs);
+    }
+
+    pub fn bench_accept_tests(&mut self) {
+        let pfs = ParamsForStep::new(&self.world_params, &self.runtime_params);
+        self.test_queue
+            .accept(&pfs, &mut EnumMap::default(), &mut EnumMap::default());
+    }
+}
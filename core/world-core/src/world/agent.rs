@@ -1,21 +1,25 @@
 pub(super) mod cemetery;
 pub(super) mod field;
 pub(super) mod gathering;
+pub(super) mod home_quarantine;
 pub(super) mod hospital;
+pub(super) mod household;
 pub(super) mod param;
+pub(super) mod snapshot;
 pub(super) mod warp;
 
-use self::{allocation::InitialHealth, gathering::Gathering, param::*};
+use self::{allocation::InitialHealth, gathering::Gathering, household::Household, param::*};
 use super::{
     commons::{
-        FiniteTypePool, HealthType, ParamsForStep, RuntimeParams, Vaccine, Variant, WorkPlaceMode,
+        Commute, FiniteTypePool, HealthType, ParamsForStep, RuntimeParams, Vaccine, Variant,
         WorldParams,
     },
-    contact::Contacts,
-    testing::{TestResult, Testee},
+    contact::{Contacts, TracedContacts},
+    infection::Infector,
+    testing::TestResult,
 };
 use crate::{
-    stat::{HealthDiff, HistInfo, InfectionCntInfo},
+    stat::{HealthDiff, HistInfo, InfectionCntInfo, InfectionPlace, TracingType},
     util::random::{self, modified_prob, DistInfo},
 };
 
@@ -29,13 +33,15 @@ use math::{Percentage, Point};
 use table::TableIndex;
 
 use parking_lot::RwLock;
-use rand::{self, Rng};
+use rand::{self, seq::SliceRandom, Rng};
 
 const AGENT_RADIUS: f64 = 0.75;
 //[todo] static AGENT_SIZE: f64 = 0.665;
 const AVOIDANCE: f64 = 0.2;
 
 const BACK_HOME_RATE: bool = true;
+/// number of random destinations drawn until one is not behind a wall
+const MAX_GOAL_DRAWS: usize = 10;
 
 /*
 fn cummulate_histgrm(h: &mut Vec<MyCounter>, d: f64) {
@@ -92,37 +98,44 @@ impl From<&HealthState> for HealthType {
 struct VaccineState {
     pub param: Option<VaccinationParam>,
     pub vaccine_ticket: Option<Vaccine>,
+    /// refuses any vaccine
+    pub refuser: bool,
 }
 
 impl VaccineState {
+    /// `last` is the number of doses, that of the primary schedule and the date of the last dose.
     fn vaccinate(
         &mut self,
         immunity: f64,
+        last: Option<(u32, u32, f64)>,
         days_to: &mut DaysTo,
         pfs: &ParamsForStep,
     ) -> Option<HealthState> {
         let vaccine = self.vaccine_ticket.take()?;
-        let vp = {
-            let today = pfs.rp.step as f64 * pfs.wp.days_per_step();
-            if let Some(mut vp) = self.param.take() {
-                if (vp.dose_date + vaccine.interval as f64) < today {
-                    // first done
-                    days_to.update_recover(pfs.wp);
-                }
-                vp.dose_date = today;
-                vp.vaccine = vaccine;
-                vp
-            } else {
-                // first done
-                days_to.update_recover(pfs.wp);
-                VaccinationParam {
-                    dose_date: today,
-                    vaccine,
-                    immunity,
-                }
-            }
+        let today = pfs.rp.step as f64 * pfs.wp.days_per_step();
+        self.param = None;
+        let n_doses = last.map_or(0, |(n, _, _)| n) + 1;
+        // the primary schedule is that of the first vaccine
+        let n_primary_doses = last.map_or(vaccine.n_primary_doses() as u32, |(_, n_primary, _)| {
+            n_primary
+        });
+        let completed = if vaccine.doses.is_empty() {
+            last.is_none_or(|(_, _, date)| date + vaccine.interval() < today)
+        } else {
+            n_doses == n_primary_doses
         };
-        Some(HealthState::Vaccinated(vp))
+        if completed {
+            // first done
+            days_to.update_recover(pfs.wp);
+        }
+        Some(HealthState::Vaccinated(VaccinationParam {
+            dose_date: today,
+            vaccine,
+            immunity,
+            base: immunity,
+            n_doses,
+            n_primary_doses,
+        }))
     }
 
     fn insert_param(&mut self, param: VaccinationParam) {
@@ -204,10 +217,26 @@ impl DaysTo {
     }
 }
 
+/// what the immune system remembers beyond the current health state
+#[derive(Default)]
+struct ImmuneMemory {
+    n_infections: u32,
+    vaccinated: bool,
+}
+
+impl ImmuneMemory {
+    fn is_hybrid(&self) -> bool {
+        self.n_infections > 0 && self.vaccinated
+    }
+}
+
 #[derive(Default)]
 pub struct AgentHealth {
+    /// region of residence
+    region: Option<usize>,
     days_to: DaysTo,
     vaccine_state: VaccineState,
+    memory: ImmuneMemory,
     state: HealthState,
 }
 
@@ -219,9 +248,18 @@ impl AgentHealth {
         wp: &WorldParams,
         rp: &RuntimeParams,
         ih: &mut InitialHealth,
+        region: Option<usize>,
     ) {
+        self.region = region;
         self.days_to.reset(activeness, age, wp, rp);
         self.vaccine_state = VaccineState::default();
+        self.memory = ImmuneMemory {
+            n_infections: match ih {
+                InitialHealth::Susceptible => 0,
+                _ => 1,
+            },
+            vaccinated: false,
+        };
         self.state = match ih {
             InitialHealth::Susceptible => HealthState::Susceptible,
             InitialHealth::Infected { symptomatic } => {
@@ -249,29 +287,35 @@ impl AgentHealth {
         }
     }
 
-    fn infected_by(&self, b: &Self, d: f64, pfs: &ParamsForStep) -> Option<(f64, Variant)> {
-        let ip = b.get_infected()?;
-        let immunity = self.get_immune_factor(&ip.virus_variant, pfs)?;
-        if ip.check_infection(immunity, d, b.days_to.onset, pfs) {
-            Some((immunity, ip.virus_variant.clone()))
-        } else {
-            None
-        }
+    /// Returns the infector with the index of its variant if infected.
+    fn infector(&self) -> Option<(Infector, usize)> {
+        let ip = self.get_infected()?;
+        Some((ip.infector(self.days_to.onset), ip.virus_variant.index))
     }
 
-    fn get_immune_factor(&self, virus_variant: &Variant, pfs: &ParamsForStep) -> Option<f64> {
-        let immune_factor = match &self.state {
-            HealthState::Susceptible => 0.0,
-            HealthState::Recovered(rp) => {
-                rp.immunity
-                    * pfs.rp.variant_pool.efficacy[rp.virus_variant.index][virus_variant.index]
-            }
-            HealthState::Vaccinated(vp) => {
-                vp.immunity * pfs.rp.vaccine_pool.efficacy[vp.vaccine.index][virus_variant.index]
-            }
+    /// Returns the immunity against infection, or `None` if it cannot be infected.
+    fn susceptibility(&self) -> Option<Susceptibility> {
+        let (immunity, source) = match &self.state {
+            HealthState::Susceptible => (0.0, ImmunitySource::Naive),
+            HealthState::Recovered(rp) => (
+                rp.immunity,
+                ImmunitySource::Infection(rp.virus_variant.index),
+            ),
+            HealthState::Vaccinated(vp) => (vp.immunity, ImmunitySource::Vaccine(vp.vaccine.index)),
             _ => return None,
         };
-        Some(immune_factor)
+        Some(Susceptibility {
+            immunity,
+            source,
+            hybrid: self.memory.is_hybrid(),
+        })
+    }
+
+    fn get_immune_factor(&self, virus_variant: &Variant, pfs: &ParamsForStep) -> Option<f64> {
+        Some(
+            self.susceptibility()?
+                .immune_factor(virus_variant.index, pfs),
+        )
     }
 
     fn get_immunity(&self) -> Option<f64> {
@@ -290,6 +334,38 @@ impl AgentHealth {
         }
     }
 
+    fn is_refuser(&self) -> bool {
+        self.vaccine_state.refuser
+    }
+
+    fn last_dose(&self) -> Option<&VaccinationParam> {
+        match &self.state {
+            HealthState::Vaccinated(vp) => Some(vp),
+            _ => self.vaccine_state.param.as_ref(),
+        }
+    }
+
+    fn n_doses(&self) -> u32 {
+        self.last_dose().map_or(0, |vp| vp.n_doses)
+    }
+
+    /// Returns `true` if the agent is due for the next dose of the vaccine in its primary schedule,
+    /// or for a booster if `booster` is `true`.
+    fn is_eligible(&self, vaccine: &Vaccine, booster: bool, pfs: &ParamsForStep) -> bool {
+        let Some(vp) = self.last_dose() else {
+            return !booster;
+        };
+        let days = pfs.rp.step as f64 * pfs.wp.days_per_step() - vp.dose_date;
+        let n = vp.n_doses as usize;
+        if booster {
+            vp.n_doses >= vp.n_primary_doses && days >= pfs.rp.vcn_bst_days
+        } else if vaccine.doses.is_empty() {
+            true
+        } else {
+            n < vaccine.doses.len() && days >= vaccine.doses[n].interval
+        }
+    }
+
     fn is_symptomatic(&self) -> bool {
         matches!(&self.state, HealthState::Infected(_, InfMode::Sym))
     }
@@ -301,6 +377,23 @@ impl AgentHealth {
         }
     }
 
+    /// Infects the agent immediately and returns the difference of the health.
+    fn infect(&mut self, immunity: f64, virus_variant: Variant) -> HealthDiff {
+        let from_hd = (&self.state).into();
+        self.memory.n_infections += 1;
+        let new_state =
+            HealthState::Infected(InfectionParam::new(immunity, virus_variant), InfMode::Asym);
+        if let HealthState::Vaccinated(vp) = std::mem::replace(&mut self.state, new_state) {
+            self.vaccine_state.insert_param(vp);
+        }
+        HealthDiff::new(
+            from_hd,
+            (&self.state).into(),
+            self.vaccine_state.refuser,
+            self.region,
+        )
+    }
+
     fn field_step(
         &mut self,
         infected: Option<(f64, Variant)>,
@@ -314,29 +407,37 @@ impl AgentHealth {
 
         let new_state = 'block: {
             if let Some(immunity) = self.get_immunity() {
+                let last = self
+                    .last_dose()
+                    .map(|vp| (vp.n_doses, vp.n_primary_doses, vp.dose_date));
                 if let Some(new_state) =
                     self.vaccine_state
-                        .vaccinate(immunity, &mut self.days_to, pfs)
+                        .vaccinate(immunity, last, &mut self.days_to, pfs)
                 {
+                    self.memory.vaccinated = true;
                     break 'block Some(new_state);
                 }
             }
             match &mut self.state {
-                HealthState::Infected(ip, inf_mode) => ip.step::<false>(
+                HealthState::Infected(ip, inf_mode) => ip.step(
+                    Care::None,
                     &mut self.days_to,
                     inf_mode,
                     &self.vaccine_state.param,
                     hist_info,
                     pfs,
                 ),
-                HealthState::Recovered(rp) => rp.step(&mut self.days_to, activeness, age, pfs),
-                HealthState::Vaccinated(vp) => vp.step(&mut self.days_to, activeness, age, pfs),
-                _ => infected.map(|(immunity, virus_variant)| {
+                // the immunity of recovered or vaccinated agents may have waned enough
+                _ if infected.is_some() => infected.map(|(immunity, virus_variant)| {
+                    self.memory.n_infections += 1;
                     HealthState::Infected(
                         InfectionParam::new(immunity, virus_variant),
                         InfMode::Asym,
                     )
                 }),
+                HealthState::Recovered(rp) => rp.step(&mut self.days_to, activeness, age, pfs),
+                HealthState::Vaccinated(vp) => vp.step(&mut self.days_to, activeness, age, pfs),
+                _ => None,
             }
         };
 
@@ -352,7 +453,12 @@ impl AgentHealth {
         };
         let to_hd = (&self.state).into();
         if from_hd != to_hd {
-            *health_diff = Some(HealthDiff::new(from_hd, to_hd));
+            *health_diff = Some(HealthDiff::new(
+                from_hd,
+                to_hd,
+                self.vaccine_state.refuser,
+                self.region,
+            ));
         }
         warp
     }
@@ -360,6 +466,7 @@ impl AgentHealth {
     fn hospital_step(
         &mut self,
         back_to: Point,
+        care: Care,
         hist_info: &mut Option<HistInfo>,
         health_diff: &mut Option<HealthDiff>,
         pfs: &ParamsForStep,
@@ -370,7 +477,8 @@ impl AgentHealth {
             return None;
         };
         let mut warp = None;
-        if let Some(new_state) = ip.step::<true>(
+        if let Some(new_state) = ip.step(
+            care,
             &mut self.days_to,
             inf_mode,
             &self.vaccine_state.param,
@@ -385,7 +493,12 @@ impl AgentHealth {
 
         let to_hd = (&self.state).into();
         if from_hd != to_hd {
-            *health_diff = Some(HealthDiff::new(from_hd, to_hd));
+            *health_diff = Some(HealthDiff::new(
+                from_hd,
+                to_hd,
+                self.vaccine_state.refuser,
+                self.region,
+            ));
         }
         warp
     }
@@ -400,7 +513,7 @@ struct Body {
 }
 
 impl Body {
-    fn reset(&mut self, wp: &WorldParams) {
+    fn reset(&mut self, wp: &WorldParams, region: Option<usize>) {
         let rng = &mut rand::thread_rng();
         self.app = rng.gen();
         self.prf = rng.gen();
@@ -408,10 +521,7 @@ impl Body {
         self.v.x = th.cos();
         self.v.y = th.sin();
 
-        self.pt = match wp.wrk_plc_mode {
-            None | Some(WorkPlaceMode::Uniform) => wp.random_point(),
-            Some(WorkPlaceMode::Centered) => wp.centered_point(),
-        };
+        self.pt = wp.random_place_in(region);
     }
 
     fn calc_dist(&self, b: &Self) -> f64 {
@@ -439,25 +549,42 @@ impl Body {
         Some((df, d))
     }
 
+    /// Returns a random destination in the region of the agent or in the field,
+    /// which is drawn again while a wall is in the way; the agent stays if every draw is blocked.
     fn get_new_pt(&self, pfs: &ParamsForStep) -> Point {
-        let field_size = pfs.wp.field_size();
+        let (lo, hi) = match pfs.wp.region_at(&self.pt) {
+            Some(i) => (pfs.wp.regions[i].min, pfs.wp.regions[i].max),
+            None => (
+                Point::default(),
+                Point::new(pfs.wp.field_size(), pfs.wp.field_height()),
+            ),
+        };
         let rng = &mut rand::thread_rng();
-        let dst = random::my_random(rng, &pfs.rp.mob_dist).r() * field_size;
+        (0..MAX_GOAL_DRAWS)
+            .map(|_| self.draw_new_pt(lo, hi, rng, pfs))
+            .find(|p| !pfs.wp.is_walled(&self.pt, p))
+            .unwrap_or(self.pt)
+    }
+
+    fn draw_new_pt<R: Rng>(&self, lo: Point, hi: Point, rng: &mut R, pfs: &ParamsForStep) -> Point {
+        let dst = random::my_random(rng, pfs.mob_dist()).r() * pfs.wp.field_size();
         let th = rng.gen::<f64>() * f64::consts::PI * 2.;
         let mut new_pt = Point {
             x: self.pt.x + th.cos() * dst,
             y: self.pt.y + th.sin() * dst,
         };
-        if new_pt.x < 3. {
-            new_pt.x = 3. - new_pt.x;
-        } else if new_pt.x > field_size - 3. {
-            new_pt.x = (field_size - 3.) * 2. - new_pt.x;
+        if new_pt.x < lo.x + 3. {
+            new_pt.x = lo.x * 2. + 3. - new_pt.x;
+        } else if new_pt.x > hi.x - 3. {
+            new_pt.x = (hi.x - 3.) * 2. - new_pt.x;
         }
-        if new_pt.y < 3. {
-            new_pt.y = 3. - new_pt.y;
-        } else if new_pt.y > field_size - 3. {
-            new_pt.y = (field_size - 3.) * 2. - new_pt.y;
+        if new_pt.y < lo.y + 3. {
+            new_pt.y = lo.y * 2. + 3. - new_pt.y;
+        } else if new_pt.y > hi.y - 3. {
+            new_pt.y = (hi.y - 3.) * 2. - new_pt.y;
         }
+        new_pt.x = new_pt.x.clamp(lo.x, hi.x);
+        new_pt.y = new_pt.y.clamp(lo.y, hi.y);
 
         new_pt
     }
@@ -485,13 +612,20 @@ impl Body {
         pfs: &ParamsForStep,
     ) {
         self.update_velocity(is_symptomatic, gat_dist, f, pfs);
+        let prev = self.pt;
         self.pt += self.v * pfs.wp.days_per_step();
+        for w in &pfs.wp.walls {
+            if w.blocks(&prev, &self.pt) {
+                self.pt = w.reflect(&self.pt);
+                self.v = w.reflect_vec(&self.v);
+            }
+        }
 
         if let Some(x) = Self::check_bounce(self.pt.x, pfs.wp.field_size()) {
             self.pt.x = x;
             self.v.x = -self.v.x;
         }
-        if let Some(y) = Self::check_bounce(self.pt.y, pfs.wp.field_size()) {
+        if let Some(y) = Self::check_bounce(self.pt.y, pfs.wp.field_height()) {
             self.pt.y = y;
             self.v.y = -self.v.y;
         }
@@ -504,7 +638,8 @@ impl Body {
         f: Point,
         pfs: &ParamsForStep,
     ) {
-        let mut fric = ((1.0 - pfs.rp.friction.r()) * 0.99).powf(pfs.wp.days_per_step());
+        let friction = pfs.wp.friction_at(&self.pt, pfs.rp);
+        let mut fric = ((1.0 - friction.r()) * 0.99).powf(pfs.wp.days_per_step());
         if let Some(dist) = gat_dist {
             fric *= dist * 0.5 + 0.5;
         }
@@ -537,6 +672,8 @@ impl Body {
 #[derive(Default)]
 struct AgentLog {
     n_infects: u32,
+    /// whether the agent was around its home at its last step in the field
+    at_home: bool,
 }
 
 impl AgentLog {
@@ -561,9 +698,13 @@ pub struct GatheringInfo {
 
 #[derive(Default)]
 pub struct InnerAgent {
+    /// index in the agents of the world
+    id: usize,
     body: Body,
     /// [`None`] means it has no home. (e.g. [`wrk_plc_mode`](WorldParams::wrk_plc_mode) equals [`WorkPlaceMode::None`].)
     pub origin: Option<Point>,
+    /// [`None`] means it does not commute.
+    workplace: Option<Point>,
 
     distancing: bool,
     activeness: f64,
@@ -575,8 +716,10 @@ pub struct InnerAgent {
     pub health: Arc<RwLock<AgentHealth>>,
     pub testing: Arc<RwLock<TestState>>,
     contacts: Contacts,
+    /// members of the household including itself
+    household: Household,
 
-    log: AgentLog,
+    log: Arc<RwLock<AgentLog>>,
 }
 
 impl InnerAgent {
@@ -586,14 +729,15 @@ impl InnerAgent {
         rp: &RuntimeParams,
         distancing: bool,
         ih: &mut InitialHealth,
+        region: Option<usize>,
     ) {
         self.testing.write().reset();
-        self.log.reset();
+        self.log.write().reset();
 
         let rng = &mut rand::thread_rng();
         self.health
             .write()
-            .reset(self.activeness, self.age, wp, rp, ih);
+            .reset(self.activeness, self.age, wp, rp, ih, region);
 
         self.activeness = random::random_mk(rng, rp.act_mode.r(), rp.act_kurt.r());
         let d_info = DistInfo::new(0.0, 0.5, 1.0);
@@ -618,13 +762,15 @@ impl InnerAgent {
         );
 
         self.distancing = distancing;
-        self.body.reset(wp);
+        self.body.reset(wp, region);
 
         self.origin = if wp.wrk_plc_mode.is_none() {
             None
         } else {
             Some(self.body.pt)
         };
+        self.workplace = None;
+        self.household = Household::default();
     }
 
     #[inline]
@@ -636,26 +782,65 @@ impl InnerAgent {
         self.origin.unwrap_or(self.body.pt)
     }
 
-    fn calc_gathering_effect(&self) -> (Option<Point>, Option<f64>) {
+    /// Returns the point the agent stays around, i.e. the workplace in working hours or the home.
+    fn anchor(&self, pfs: &ParamsForStep) -> Option<Point> {
+        match self.workplace {
+            Some(w) if pfs.is_work_time() => Some(w),
+            _ => self.origin,
+        }
+    }
+
+    /// symptomatic commuters do not go to work but still come back home
+    fn commute_goal(&self, is_symptomatic: bool, pfs: &ParamsForStep) -> Option<Point> {
+        let workplace = self.workplace?;
+        match pfs.commute()? {
+            Commute::ToWork if !is_symptomatic => Some(workplace),
+            Commute::ToWork => None,
+            Commute::ToHome => self.origin,
+        }
+    }
+
+    fn whereabouts(&self, pfs: &ParamsForStep) -> InfectionPlace {
+        let rad = pfs.wp.wrk_plc_rad.r() * pfs.wp.field_size();
+        let is_near = |p: Point| {
+            let dp = self.body.pt - p;
+            dp.x.hypot(dp.y) <= rad
+        };
+        match (self.workplace, self.origin) {
+            (Some(w), _) if pfs.is_work_time() && is_near(w) => InfectionPlace::Work,
+            (_, Some(o)) if is_near(o) => InfectionPlace::Home,
+            _ => InfectionPlace::Elsewhere,
+        }
+    }
+
+    /// Returns the index of the scheduled event if the agent is inside of it.
+    fn attending_event(&self, wp: &WorldParams) -> Option<usize> {
+        let gat = self.gat_info.read().gathering.upgrade()?;
+        let gat = gat.read();
+        gat.event()
+            .filter(|_| gat.get_effect(&self.body.pt, wp).1.is_some())
+    }
+
+    fn calc_gathering_effect(&self, wp: &WorldParams) -> (Option<Point>, Option<f64>) {
         match self.gat_info.read().gathering.upgrade() {
             None => (None, None),
-            Some(gat) => gat.read().get_effect(&self.body.pt),
+            Some(gat) => gat.read().get_effect(&self.body.pt, wp),
         }
     }
 
     fn moves_inside(&self, pfs: &ParamsForStep) -> bool {
         random::at_least_once_hit_in(
             pfs.wp.days_per_step(),
-            modified_prob(self.mob_freq, &pfs.rp.mob_freq).r(),
+            modified_prob(self.mob_freq, pfs.mob_freq()).r(),
         )
     }
 
     fn is_away_from_home(dp: &Point, pfs: &ParamsForStep) -> bool {
-        dp.x.hypot(dp.y) > pfs.rp.mob_dist.min.max(&MIN_AWAY_TO_HOME).r() * pfs.wp.field_size()
+        dp.x.hypot(dp.y) > pfs.mob_dist().min.max(&MIN_AWAY_TO_HOME).r() * pfs.wp.field_size()
     }
 
     fn get_warp_inside_goal(&self, pfs: &ParamsForStep) -> Option<Point> {
-        let Some(origin) = self.origin else {
+        let Some(origin) = self.anchor(pfs) else {
             if self.moves_inside(pfs) {
                 return Some(self.body.get_new_pt(pfs));
             }
@@ -666,7 +851,7 @@ impl InnerAgent {
         if BACK_HOME_RATE {
             if pfs.go_home_back()
                 && Self::is_away_from_home(&dp, pfs)
-                && random::at_least_once_hit_in(pfs.wp.days_per_step() * 3.0, pfs.rp.back_hm_rt.r())
+                && random::at_least_once_hit_in(pfs.wp.days_per_step() * 3.0, pfs.back_hm_rt().r())
             {
                 return Some(origin);
             }
@@ -685,10 +870,38 @@ impl InnerAgent {
         }
     }
 
+    /// Returns a random point in another region if the agent travels there.
+    /// Whether to travel is drawn against the total rate of the row,
+    /// and the destination is then chosen in proportion to its rate.
+    fn travel_goal(&self, pfs: &ParamsForStep) -> Option<Point> {
+        let from = pfs.wp.region_at(&self.body.pt)?;
+        let rates = pfs.rp.rgn_od.get(from)?;
+        let rates = rates
+            .iter()
+            .enumerate()
+            .filter(|&(to, _)| to != from)
+            .collect::<Vec<_>>();
+        let total = rates.iter().map(|(_, rate)| **rate).sum::<f64>();
+        if total <= 0.0 || !random::at_least_once_hit_in(pfs.wp.days_per_step(), total.min(1.0)) {
+            return None;
+        }
+        let (to, _) = rates
+            .choose_weighted(&mut rand::thread_rng(), |(_, rate)| **rate)
+            .ok()?;
+        Some(pfs.wp.random_place_in(Some(*to)))
+    }
+
     fn warp_inside(&self, pfs: &ParamsForStep) -> Option<WarpParam> {
-        if self.health.read().is_symptomatic() {
+        let is_symptomatic = self.health.read().is_symptomatic();
+        if let Some(goal) = self.commute_goal(is_symptomatic, pfs) {
+            return Some(WarpParam::inside(goal));
+        }
+        if is_symptomatic {
             return None;
         }
+        if let Some(goal) = self.travel_goal(pfs) {
+            return Some(WarpParam::inside(goal));
+        }
         if let Some(goal) = self.get_warp_inside_goal(pfs) {
             return Some(WarpParam::inside(goal));
         }
@@ -703,14 +916,14 @@ impl InnerAgent {
     ) -> (Point, Option<f64>) {
         let mut gat_dist = None;
         let mut f = force;
-        match self.origin {
+        match self.anchor(pfs) {
             Some(origin) if pfs.go_home_back() => {
                 if let Some(df) = back_home_force(&self.body.pt, &origin) {
                     f += df;
                 }
             }
             _ => {
-                let (df, dist) = self.calc_gathering_effect();
+                let (df, dist) = self.calc_gathering_effect(pfs.wp);
                 if let Some(df) = df {
                     f += df;
                 }
@@ -719,19 +932,20 @@ impl InnerAgent {
         }
 
         if self.distancing {
-            f *= 1.0 + pfs.rp.dst_st / 5.0;
+            f *= 1.0 + pfs.dst_st() / 5.0;
         }
-        f += self.best_point_force(&best.map(|(p, _)| p), pfs.wp.field_size());
+        f += self.best_point_force(&best.map(|(p, _)| p), pfs.wp);
         (f, gat_dist)
     }
 
-    fn best_point_force(&self, best_pt: &Option<Point>, field_size: f64) -> Point {
+    fn best_point_force(&self, best_pt: &Option<Point>, wp: &WorldParams) -> Point {
         fn wall(d: f64) -> f64 {
             let d = if d < 0.02 { 0.02 } else { d };
             AVOIDANCE * 20. / d / d
         }
         let pt = &self.body.pt;
-        let mut f = pt.map(wall) - pt.map(|p| wall(field_size - p));
+        let rest = Point::new(wp.field_size() - pt.x, wp.field_height() - pt.y);
+        let mut f = pt.map(wall) - rest.map(wall);
         if let (Some(bp), false) = (best_pt, self.distancing) {
             let dp = bp - &self.body.pt;
             let d = dp.x.hypot(dp.y).max(0.01) * 20.0;
@@ -758,15 +972,33 @@ impl InnerAgent {
         }
     }
 
-    fn check_quarantine(&mut self, pfs: &ParamsForStep) -> Option<(WarpParam, Vec<Testee>)> {
-        //[todo] prms.rp.trc_ope != TrcTst
-        if matches!(
-            self.testing.write().read_result(),
-            Some(TestResult::Positive)
-        ) {
+    fn check_quarantine(&mut self, pfs: &ParamsForStep) -> Option<(WarpParam, TracedContacts)> {
+        let (result, quarantine_order) = {
+            let mut testing = self.testing.write();
+            (testing.read_result(), testing.read_quarantine_order())
+        };
+        if matches!(result, Some(TestResult::Positive)) {
+            let w = if rand::thread_rng().gen::<f64>() < pfs.hm_qrn_rt().r() {
+                WarpParam::home_quarantine(self.get_back_to(), pfs.rp.hm_qrn_dur, pfs)
+            } else {
+                WarpParam::hospital(self.get_back_to(), pfs.wp)
+            };
+            let mut traced = self.contacts.drain_traced(pfs);
+            if pfs.rp.hh_qrn {
+                for m in self.household.iter() {
+                    if !Arc::ptr_eq(&m.testing, &self.testing)
+                        && m.location.read().in_field()
+                        && m.testing.write().order_quarantine()
+                    {
+                        traced.count[&TracingType::HouseholdQuarantined] += 1;
+                    }
+                }
+            }
+            Some((w, traced))
+        } else if quarantine_order {
             Some((
-                WarpParam::hospital(self.get_back_to(), pfs.wp),
-                self.contacts.drain_testees(pfs),
+                WarpParam::home_quarantine(self.get_back_to(), pfs.rp.trc_qrn_dur, pfs),
+                TracedContacts::default(),
             ))
         } else {
             None
@@ -796,34 +1028,47 @@ impl DerefMut for Agent {
     }
 }
 
+#[derive(Clone)]
 pub struct AgentRef {
     pub testing: Arc<RwLock<TestState>>,
     pub health: Arc<RwLock<AgentHealth>>,
     pub location: Arc<RwLock<Location>>,
+    log: Arc<RwLock<AgentLog>>,
 }
 
 impl AgentRef {
-    pub fn new(
+    fn new(
         testing: Arc<RwLock<TestState>>,
         health: Arc<RwLock<AgentHealth>>,
         location: Arc<RwLock<Location>>,
+        log: Arc<RwLock<AgentLog>>,
     ) -> Self {
         Self {
             testing,
             health,
             location,
+            log,
         }
     }
 
-    pub fn try_give_vaccine_ticket(&self, vaccine: Vaccine) -> bool {
+    pub fn try_give_vaccine_ticket(
+        &self,
+        vaccine: Vaccine,
+        booster: bool,
+        pfs: &ParamsForStep,
+    ) -> bool {
         if !self.location.read().in_field() {
             return false;
         }
-        match self.health.read().state {
+        let health = &mut *self.health.write();
+        if health.is_refuser() || !health.is_eligible(&vaccine, booster, pfs) {
+            return false;
+        }
+        match health.state {
             HealthState::Susceptible
             | HealthState::Infected(_, InfMode::Asym)
             | HealthState::Vaccinated(_) => {
-                let ticket = &mut self.health.write().vaccine_state.vaccine_ticket;
+                let ticket = &mut health.vaccine_state.vaccine_ticket;
                 if ticket.is_some() {
                     return false;
                 };
@@ -841,6 +1086,7 @@ impl From<&Agent> for AgentRef {
             value.testing.clone(),
             value.health.clone(),
             value.location.clone(),
+            value.log.clone(),
         )
     }
 }
@@ -850,6 +1096,7 @@ pub struct TestState {
     reserved: bool,
     last_tested: Option<u32>,
     unread_result: Option<TestResult>,
+    quarantine_order: bool,
 }
 
 impl TestState {
@@ -887,6 +1134,19 @@ impl TestState {
     fn read_result(&mut self) -> Option<TestResult> {
         self.unread_result.take()
     }
+
+    /// Returns `false` if the order has already been issued.
+    pub fn order_quarantine(&mut self) -> bool {
+        !std::mem::replace(&mut self.quarantine_order, true)
+    }
+
+    fn read_quarantine_order(&mut self) -> bool {
+        std::mem::take(&mut self.quarantine_order)
+    }
+
+    fn cancel_quarantine_order(&mut self) {
+        self.quarantine_order = false;
+    }
 }
 
 #[derive(Default, Debug)]
@@ -895,6 +1155,7 @@ pub enum Location {
     #[default]
     Field,
     Hospital,
+    HomeQuarantine,
     Warp,
 }
 
@@ -917,6 +1178,8 @@ pub enum WarpMode {
     Back,
     Inside,
     Hospital(Point),
+    /// stay at home until the given step
+    HomeQuarantine(u32),
     Cemetery,
 }
 
@@ -930,7 +1193,7 @@ impl WarpParam {
         Self { mode, goal }
     }
 
-    fn back(goal: Point) -> Self {
+    pub fn back(goal: Point) -> Self {
         Self::new(WarpMode::Back, goal)
     }
 
@@ -942,27 +1205,38 @@ impl WarpParam {
         let rng = &mut rand::thread_rng();
         let goal = Point::new(
             (rng.gen::<f64>() * 0.248 + 1.001) * wp.field_size(),
-            (rng.gen::<f64>() * 0.458 + 0.501) * wp.field_size(),
+            (rng.gen::<f64>() * 0.458 + 0.501) * wp.field_height(),
         );
         Self::new(WarpMode::Hospital(back_to), goal)
     }
 
+    /// `days` of home quarantine at `home`
+    fn home_quarantine(home: Point, days: f64, pfs: &ParamsForStep) -> Self {
+        let until = pfs.rp.step + (days * pfs.wp.steps_per_day()) as u32;
+        Self::new(WarpMode::HomeQuarantine(until), home)
+    }
+
     fn cemetery(wp: &WorldParams) -> Self {
         let rng = &mut rand::thread_rng();
         let goal = Point::new(
             (rng.gen::<f64>() * 0.248 + 1.001) * wp.field_size(),
-            (rng.gen::<f64>() * 0.468 + 0.001) * wp.field_size(),
+            (rng.gen::<f64>() * 0.468 + 0.001) * wp.field_height(),
         );
         Self::new(WarpMode::Cemetery, goal)
     }
 }
 
 pub(super) mod allocation {
+    use std::collections::HashMap;
+
     use math::Point;
-    use rand::Rng;
+    use rand::{seq::SliceRandom, Rng};
 
     use super::{field::Field, hospital::Hospital, Agent};
-    use crate::world::commons::{RuntimeParams, WorldParams};
+    use crate::{
+        stat::HealthCount,
+        world::commons::{RuntimeParams, WorldParams},
+    };
 
     #[derive(Clone)]
     pub enum InitialHealth {
@@ -1014,23 +1288,33 @@ pub(super) mod allocation {
         cats
     }
 
-    /// Returns the number of symptomatic agents
-    pub fn allocate_agents(
-        agents: &mut Vec<Agent>,
-        field: &mut Field,
-        hospital: &mut Hospital,
+    /// Resets the agents by initial health categories of each group given by
+    /// [`WorldParams::initial_groups`], which are mixed in random order if regions exist.
+    /// Returns the categories and the number of symptomatic agents.
+    pub fn reset_agents(
+        agents: &mut [Agent],
         origins: &mut Vec<Point>,
-        n_pop: usize,
-        n_infected: usize,
-        n_recovered: usize,
+        groups: &[(usize, usize, usize)],
         mut n_dist: usize,
         wp: &WorldParams,
         rp: &RuntimeParams,
-    ) -> usize {
-        let mut cats = make_categories(n_pop, n_infected, n_recovered);
+    ) -> (Vec<InitialHealth>, usize) {
+        let mut cats = Vec::with_capacity(agents.len());
+        for (i, &(n_pop, n_infected, n_recovered)) in groups.iter().enumerate() {
+            let region = (!wp.regions.is_empty()).then_some(i);
+            cats.extend(
+                make_categories(n_pop, n_infected, n_recovered)
+                    .into_iter()
+                    .map(|ih| (ih, region)),
+            );
+        }
+        if !wp.regions.is_empty() {
+            cats.shuffle(&mut rand::thread_rng());
+        }
         let mut n_symptomatic = 0;
-        for (ih, agent) in cats.iter_mut().zip(agents.iter_mut()) {
-            agent.reset(wp, rp, n_dist > 0, ih);
+        for (id, ((ih, region), agent)) in cats.iter_mut().zip(agents.iter_mut()).enumerate() {
+            agent.id = id;
+            agent.reset(wp, rp, n_dist > 0, ih, *region);
             if let Some(p) = agent.origin {
                 origins.push(p);
             }
@@ -1041,7 +1325,114 @@ pub(super) mod allocation {
                 n_symptomatic += 1;
             }
         }
+        (cats.into_iter().map(|(ih, _)| ih).collect(), n_symptomatic)
+    }
 
+    /// Assigns workplaces scattered around clusters to commuters among the agents with a home.
+    pub fn assign_workplaces(agents: &mut [Agent], wp: &WorldParams) {
+        if wp.cmt_rt.0 <= 0.0 || wp.wrk_plc_n == 0 {
+            return;
+        }
+        let rng = &mut rand::thread_rng();
+        // commuters work in the region they live in
+        let regions = if wp.regions.is_empty() {
+            vec![None]
+        } else {
+            (0..wp.regions.len()).map(Some).collect()
+        };
+        let clusters = regions
+            .iter()
+            .map(|&region| {
+                (0..wp.wrk_plc_n)
+                    .map(|_| wp.random_place_in(region))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let rad = wp.wrk_plc_rad.r() * wp.field_size();
+        for agent in agents.iter_mut() {
+            let Some(origin) = agent.origin else {
+                continue;
+            };
+            if rng.gen::<f64>() >= wp.cmt_rt.r() {
+                continue;
+            }
+            let region = wp.region_at(&origin);
+            let clusters = &clusters[region.unwrap_or(0)];
+            let c = clusters[rng.gen_range(0..clusters.len())];
+            let th = rng.gen::<f64>() * std::f64::consts::PI * 2.0;
+            let r = rng.gen::<f64>().sqrt() * rad;
+            let mut p = Point::new(c.x + th.cos() * r, c.y + th.sin() * r);
+            match region {
+                Some(i) => p = wp.regions[i].clamp(&p),
+                None => {
+                    p.x = p.x.clamp(0.0, wp.field_size());
+                    p.y = p.y.clamp(0.0, wp.field_height());
+                }
+            }
+            agent.workplace = Some(p);
+        }
+    }
+
+    /// Flags vaccine refusers in square blocks of the field, which are chosen in random order
+    /// until the rate is reached, and returns the health count of the refusers.
+    pub fn assign_refusers(agents: &mut [Agent], wp: &WorldParams) -> HealthCount {
+        let mut count = HealthCount::default();
+        let n = (agents.len() as f64 * wp.av_clstr_rate.r()).round() as usize;
+        if n == 0 {
+            return count;
+        }
+        let rng = &mut rand::thread_rng();
+        // each agent is a cluster of itself if granularity is zero
+        let n_blocks = if wp.av_clstr_gran.0 > 0.0 {
+            (1.0 / wp.av_clstr_gran.r()).ceil() as usize
+        } else {
+            0
+        };
+        let block = |v: f64, size: f64| ((v / size * n_blocks as f64) as usize).min(n_blocks - 1);
+        // only the blocks someone lives in are scored, however fine they are
+        let mut block_scores = HashMap::new();
+        let mut scores = Vec::with_capacity(agents.len());
+        for (i, agent) in agents.iter().enumerate() {
+            let s = if n_blocks == 0 {
+                rng.gen::<f64>()
+            } else {
+                let p = agent.get_back_to();
+                let b = (block(p.x, wp.field_size()), block(p.y, wp.field_height()));
+                *block_scores.entry(b).or_insert_with(|| rng.gen::<f64>())
+            };
+            scores.push((i, s, rng.gen::<f64>()));
+        }
+        scores.sort_by(|a, b| b.1.total_cmp(&a.1).then(b.2.total_cmp(&a.2)));
+        for (i, _, _) in scores.into_iter().take(n) {
+            let mut health = agents[i].health.write();
+            health.vaccine_state.refuser = true;
+            count[&(&health.state).into()] += 1;
+        }
+        count
+    }
+
+    /// Returns the health count of the residents of each region.
+    pub fn count_regions(agents: &[Agent], wp: &WorldParams) -> Vec<HealthCount> {
+        let mut counts = vec![HealthCount::default(); wp.regions.len()];
+        for agent in agents {
+            let health = agent.health.read();
+            if let Some(r) = health.region {
+                counts[r][&(&health.state).into()] += 1;
+            }
+        }
+        counts
+    }
+
+    /// Moves all the agents into the field or the hospital.
+    pub fn place_agents(
+        agents: &mut Vec<Agent>,
+        cats: &[InitialHealth],
+        n_infected: usize,
+        n_symptomatic: usize,
+        field: &mut Field,
+        hospital: &mut Hospital,
+        wp: &WorldParams,
+    ) {
         let mut n_q_symptomatic = (n_symptomatic as f64 * wp.q_symptomatic.r()) as u32;
         let mut n_q_asymptomatic =
             ((n_infected - n_symptomatic) as f64 * wp.q_asymptomatic.r()) as u32;
@@ -1064,8 +1455,6 @@ pub(super) mod allocation {
                 }
             }
         }
-
-        n_symptomatic
     }
 
     fn reservoir_sampling(n: usize, k: usize) -> Vec<usize> {
@@ -1110,3 +1499,351 @@ pub(super) mod allocation {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use math::Point;
+
+    use super::{
+        allocation,
+        hospital::Hospital,
+        param::{RecoverParam, VaccinationParam},
+        Agent, AgentHealth, AgentRef, HealthState, Location, WarpMode,
+    };
+    use crate::{
+        stat::TracingType,
+        util::random::DistInfo,
+        world::{
+            commons::{
+                Dose, FiniteTypePool, HealthType, ParamsForStep, RuntimeParams, TracingOperation,
+                VaccineInfo, VaccinePool, WorldParams,
+            },
+            contact::{Contacts, TracedContacts},
+            geometry::{Region, Wall},
+            immunity::WaningCurve,
+            testing::TestResult,
+        },
+    };
+
+    /// Returns the parameters of a square field of 360 divided into 18 cells with 16 steps a day.
+    fn world_params(init_n_pop: u32) -> WorldParams {
+        WorldParams::new(
+            init_n_pop,
+            360,
+            18,
+            16,
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            None,
+            0.0.into(),
+            0.0,
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            0.0,
+            0.0,
+            0.0,
+            0.0.into(),
+        )
+    }
+
+    /// Traces three contacts in the field and one in the hospital.
+    fn trace(trc_ope: TracingOperation) -> (Vec<Agent>, TracedContacts) {
+        let wp = world_params(4);
+        let rp = RuntimeParams {
+            trc_ope,
+            step: 1,
+            ..Default::default()
+        };
+        let pfs = ParamsForStep::new(&wp, &rp);
+        let agents = (0..4).map(|_| Agent::new()).collect::<Vec<_>>();
+        *agents[3].location.write() = Location::Hospital;
+        let mut contacts = Contacts::default();
+        contacts.append(agents.iter().map(AgentRef::from).collect(), 0);
+        let traced = contacts.drain_traced(&pfs);
+        (agents, traced)
+    }
+
+    #[test]
+    fn test_tracing_operation() {
+        let (agents, traced) = trace(TracingOperation::TEST);
+        assert_eq!(traced.count[&TracingType::Tested], 3);
+        assert_eq!(traced.count[&TracingType::Vaccinated], 0);
+        assert_eq!(traced.testees.len(), 3);
+        assert!(agents.iter().take(3).all(|a| a.testing.read().reserved));
+        assert!(!agents[3].testing.read().reserved);
+
+        let (agents, traced) = trace(TracingOperation::TEST | TracingOperation::VACCINATE);
+        assert_eq!(traced.count[&TracingType::Tested], 3);
+        assert_eq!(traced.count[&TracingType::Vaccinated], 3);
+        assert!(agents.iter().take(3).all(|a| a
+            .health
+            .read()
+            .vaccine_state
+            .vaccine_ticket
+            .is_some()));
+        assert!(agents[3]
+            .health
+            .read()
+            .vaccine_state
+            .vaccine_ticket
+            .is_none());
+
+        // quarantined contacts are not tested in addition
+        let (agents, traced) = trace(TracingOperation::TEST | TracingOperation::QUARANTINE);
+        assert_eq!(traced.count[&TracingType::Quarantined], 3);
+        assert_eq!(traced.count[&TracingType::Tested], 0);
+        assert!(traced.testees.is_empty());
+        assert!(agents
+            .iter()
+            .take(3)
+            .all(|a| a.testing.read().quarantine_order));
+        assert!(!agents[3].testing.read().quarantine_order);
+    }
+
+    #[test]
+    fn test_reinfection() {
+        let mut wp = world_params(1);
+        wp.vcn_max_effc = 80.0.into();
+        let rp = RuntimeParams {
+            imn_wane: Some(WaningCurve::Exponential { half_life: 10.0 }),
+            vcn_wane: Some(WaningCurve::Exponential { half_life: 10.0 }),
+            ..Default::default()
+        };
+        let pfs = ParamsForStep::new(&wp, &rp);
+        let mut recovered = RecoverParam::new(0.8, rp.variant_pool.get(0));
+        recovered.days_recovered = 10.0;
+        let vaccinated = VaccinationParam {
+            vaccine: rp.vaccine_pool.get(0),
+            // fully effective 21 days after the first dose
+            dose_date: -31.0,
+            immunity: 0.8,
+            base: 0.8,
+            n_doses: 2,
+            n_primary_doses: 2,
+        };
+        for state in [
+            HealthState::Recovered(recovered),
+            HealthState::Vaccinated(vaccinated),
+        ] {
+            let vaccinated = matches!(state, HealthState::Vaccinated(_));
+            let mut health = AgentHealth {
+                state,
+                ..Default::default()
+            };
+            health.field_step(None, 0.5, 40.0, &mut None, &mut None, &pfs);
+            // about a half of the immunity is left
+            let immunity = health.susceptibility().unwrap().immunity;
+            assert!(immunity > 0.0 && immunity < 0.8, "immunity = {immunity}");
+
+            let mut health_diff = None;
+            let infected = Some((immunity, rp.variant_pool.get(0)));
+            health.field_step(infected, 0.5, 40.0, &mut None, &mut health_diff, &pfs);
+            assert!(
+                matches!(&health.state, HealthState::Infected(ip, _) if ip.immunity == immunity)
+            );
+            assert_eq!(health.memory.n_infections, 1);
+            assert!(health_diff.is_some());
+            // the record of the vaccination is kept
+            assert_eq!(health.n_doses(), if vaccinated { 2 } else { 0 });
+        }
+    }
+
+    #[test]
+    fn test_booster_eligibility() {
+        let wp = world_params(1);
+        let dose = |interval| Dose {
+            interval,
+            efficacy: 90.0.into(),
+        };
+        let rp = RuntimeParams {
+            vaccine_pool: VaccinePool::from_vec(
+                vec![
+                    VaccineInfo::with_doses(vec![dose(0.0), dose(21.0), dose(90.0)]),
+                    VaccineInfo::with_doses(vec![dose(0.0)]),
+                ],
+                vec![vec![1.0], vec![1.0]],
+            ),
+            vcn_bst_days: 0.0,
+            step: 16 * 100,
+            ..Default::default()
+        };
+        let pfs = ParamsForStep::new(&wp, &rp);
+        let mut health = AgentHealth {
+            state: HealthState::Vaccinated(VaccinationParam {
+                vaccine: rp.vaccine_pool.get(0),
+                dose_date: 0.0,
+                immunity: 0.9,
+                base: 0.9,
+                n_doses: 2,
+                n_primary_doses: 3,
+            }),
+            ..Default::default()
+        };
+        // the single dose of the booster does not complete the primary schedule of three doses
+        let booster = rp.vaccine_pool.get(1);
+        assert!(!health.is_eligible(&booster, true, &pfs));
+        assert!(health.is_eligible(&rp.vaccine_pool.get(0), false, &pfs));
+
+        // the record of the doses survives an infection
+        health.infect(0.9, rp.variant_pool.get(0));
+        assert_eq!(health.n_doses(), 2);
+        assert!(!health.is_eligible(&booster, true, &pfs));
+    }
+
+    #[test]
+    fn test_quarantine_order() {
+        let wp = world_params(1);
+        let rp = RuntimeParams {
+            trc_qrn_dur: 7.0,
+            step: 3,
+            ..Default::default()
+        };
+        let pfs = ParamsForStep::new(&wp, &rp);
+        let mut agent = Agent::new();
+        assert!(agent.testing.write().order_quarantine());
+        assert!(!agent.testing.write().order_quarantine());
+        let (w, traced) = agent.check_quarantine(&pfs).unwrap();
+        assert!(matches!(w.mode, WarpMode::HomeQuarantine(until) if until == 3 + 7 * 16));
+        assert!(traced.testees.is_empty());
+        // the order is read once
+        assert!(agent.check_quarantine(&pfs).is_none());
+
+        // an order is void once the agent is admitted to the hospital
+        assert!(agent.testing.write().order_quarantine());
+        let testing = agent.testing.clone();
+        Hospital::new(1).add(agent, Point::new(0.0, 0.0));
+        assert!(!testing.read().quarantine_order);
+    }
+
+    #[test]
+    fn test_positive_isolation() {
+        let wp = world_params(1);
+        for (rate, at_home) in [(100.0, true), (0.0, false)] {
+            let rp = RuntimeParams {
+                hm_qrn_rt: rate.into(),
+                hm_qrn_dur: 10.0,
+                ..Default::default()
+            };
+            let pfs = ParamsForStep::new(&wp, &rp);
+            let mut agent = Agent::new();
+            agent.testing.write().notify_result(0, TestResult::Positive);
+            let (w, _) = agent.check_quarantine(&pfs).unwrap();
+            if at_home {
+                assert!(matches!(w.mode, WarpMode::HomeQuarantine(160)));
+            } else {
+                assert!(matches!(w.mode, WarpMode::Hospital(_)));
+            }
+        }
+    }
+
+    /// Returns the homes of the refusers among agents living on a grid of 10 x 10.
+    fn refuser_homes(gran: f64) -> Vec<Point> {
+        let mut wp = world_params(100);
+        wp.av_clstr_rate = 25.0.into();
+        wp.av_clstr_gran = gran.into();
+        let mut agents = (0..100)
+            .map(|i| {
+                let mut agent = Agent::new();
+                let (x, y) = ((i % 10) as f64, (i / 10) as f64);
+                agent.origin = Some(Point::new(18.0 + x * 36.0, 18.0 + y * 36.0));
+                agent
+            })
+            .collect::<Vec<_>>();
+        let count = allocation::assign_refusers(&mut agents, &wp);
+        assert_eq!(count[&HealthType::Susceptible], 25);
+        agents
+            .iter()
+            .filter(|a| a.health.read().vaccine_state.refuser)
+            .map(|a| a.get_back_to())
+            .collect()
+    }
+
+    #[test]
+    fn test_refuser_cluster() {
+        // the refusers fill one of the 2 x 2 blocks
+        let homes = refuser_homes(50.0);
+        let quadrant = |p: &Point| (p.x < 180.0, p.y < 180.0);
+        assert!(homes.iter().all(|p| quadrant(p) == quadrant(&homes[0])));
+        // as many blocks as the agents at most are scored for a fine granularity
+        assert_eq!(refuser_homes(1e-12).len(), 25);
+        assert_eq!(refuser_homes(0.0).len(), 25);
+    }
+
+    #[test]
+    fn test_goal_behind_wall() {
+        let mut wp = world_params(1);
+        wp.walls
+            .push(Wall::new(Point::new(110.0, 0.0), Point::new(110.0, 360.0)));
+        let rp = RuntimeParams {
+            mob_dist: DistInfo::new(5.0.into(), 10.0.into(), 20.0.into()),
+            ..Default::default()
+        };
+        let pfs = ParamsForStep::new(&wp, &rp);
+        let mut agent = Agent::new();
+        agent.body.pt = Point::new(100.0, 100.0);
+        for _ in 0..100 {
+            assert!(agent.body.get_new_pt(&pfs).x < 110.0);
+        }
+    }
+
+    /// Returns a world with three regions side by side.
+    fn three_regions() -> WorldParams {
+        let mut wp = world_params(300);
+        wp.regions = (0..3)
+            .map(|i| Region {
+                min: Point::new(i as f64 * 130.0, 0.0),
+                max: Point::new(i as f64 * 130.0 + 100.0, 360.0),
+                population: 100,
+                infected: 0.0.into(),
+            })
+            .collect();
+        wp
+    }
+
+    #[test]
+    fn test_travel_goal() {
+        let wp = three_regions();
+        // the diagonal is ignored and the others add up to one
+        let rp = RuntimeParams {
+            rgn_od: vec![vec![0.5, 0.75, 0.25], vec![0.0; 3], vec![0.0; 3]],
+            ..Default::default()
+        };
+        let pfs = ParamsForStep::new(&wp, &rp);
+        let mut agent = Agent::new();
+        agent.body.pt = Point::new(50.0, 50.0);
+        let mut counts = [0; 3];
+        for _ in 0..1000 {
+            let goal = agent.travel_goal(&pfs).unwrap();
+            counts[wp.region_at(&goal).unwrap()] += 1;
+        }
+        assert_eq!(counts[0], 0);
+        assert!((150..350).contains(&counts[2]), "{counts:?}");
+
+        // nobody leaves a region without any rate
+        agent.body.pt = Point::new(180.0, 50.0);
+        assert!(agent.travel_goal(&pfs).is_none());
+    }
+
+    #[test]
+    fn test_workplace_region() {
+        let mut wp = three_regions();
+        wp.cmt_rt = 100.0.into();
+        let mut agents = (0..300)
+            .map(|i| {
+                let mut agent = Agent::new();
+                agent.origin = Some(wp.random_place_in(Some(i % 3)));
+                agent
+            })
+            .collect::<Vec<_>>();
+        allocation::assign_workplaces(&mut agents, &wp);
+        for agent in &agents {
+            let home = wp.region_at(&agent.origin.unwrap());
+            assert_eq!(wp.region_at(&agent.workplace.unwrap()), home);
+        }
+    }
+}
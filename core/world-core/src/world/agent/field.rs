@@ -2,15 +2,19 @@ use std::sync::Arc;
 
 use super::{
     super::{
-        commons::ParamsForStep,
+        commons::{FiniteTypePool, ParamsForStep, WorldParams},
+        contact::TracedContacts,
         testing::{TestQueue, Testee},
     },
     gathering::Gathering,
+    snapshot::Snapshot,
     warp::Warps,
-    Agent, AgentHealth, AgentRef, Body, Location, LocationLabel, WarpParam,
+    Agent, AgentRef, Body, HealthState, Location, LocationLabel, WarpParam,
 };
 use crate::{
-    stat::{HealthCount, HealthDiff, HistInfo, InfectionCntInfo, Stat},
+    stat::{
+        HealthCount, HealthDiff, HistInfo, InfectionCntInfo, InfectionPlace, InfectionType, Stat,
+    },
     util::{
         random::{self},
         DrainMap,
@@ -20,10 +24,13 @@ use crate::{
 
 use math::Point;
 use parking_lot::RwLock;
-use rand::Rng;
+use rand::{seq::SliceRandom, Rng};
 use table::{Table, TableIndex};
 
-use rayon::{iter::ParallelIterator, prelude::IntoParallelIterator};
+use rayon::{
+    iter::{IndexedParallelIterator, ParallelIterator},
+    prelude::IntoParallelIterator,
+};
 
 #[derive(Default)]
 struct TempParam {
@@ -52,10 +59,10 @@ impl TempParam {
         }
     }
 
-    fn infected(&mut self, a: &AgentHealth, b: &AgentHealth, d: f64, pfs: &ParamsForStep) {
+    fn infected(&mut self, snapshot: &Snapshot, a: usize, b: usize, d: f64, pfs: &ParamsForStep) {
         if self.infected.is_none() {
-            if let Some(infected) = a.infected_by(&b, d, pfs) {
-                self.infected = Some(infected);
+            if let Some((immunity, variant)) = snapshot.infected_by(a, b, d, pfs) {
+                self.infected = Some((immunity, pfs.rp.variant_pool.get(variant)));
                 self.new_n_infects += 1;
                 // fb.new_n_infects = 1;
             }
@@ -75,11 +82,17 @@ impl LocationLabel for FieldAgent {
 
 #[derive(Default)]
 struct FieldStepInfo {
-    contacted_testees: Option<Vec<Testee>>,
+    traced: Option<TracedContacts>,
     testee: Option<Testee>,
     infct_info: Option<InfectionCntInfo>,
     hist_info: Option<HistInfo>,
     health_diff: Option<HealthDiff>,
+    infection_place: Option<InfectionPlace>,
+    infection_type: Option<InfectionType>,
+    /// number of doses if a dose is given
+    n_doses: Option<u32>,
+    /// index of the scheduled event where the agent got infected
+    infection_event: Option<usize>,
 }
 
 enum Transfer {
@@ -97,35 +110,46 @@ impl FieldAgent {
     }
 
     fn step(&mut self, pfs: &ParamsForStep) -> (FieldStepInfo, Option<Transfer>) {
+        let pfs = &pfs.in_region(pfs.wp.region_at(self.agent.get_pt()));
         let mut fsi = FieldStepInfo::default();
         // let agent = &mut self.agent; //.write();
 
         let temp = std::mem::replace(&mut self.temp, TempParam::default());
         self.agent.contacts.append(temp.new_contacts, pfs.rp.step);
-        self.agent
-            .log
-            .update_n_infects(temp.new_n_infects, &mut fsi.infct_info);
+        {
+            let mut log = self.agent.log.write();
+            log.update_n_infects(temp.new_n_infects, &mut fsi.infct_info);
+            // an agent without a home is with its household anywhere
+            log.at_home = self.agent.origin.is_none()
+                || matches!(self.agent.whereabouts(pfs), InfectionPlace::Home);
+        }
 
         let transfer = 'block: {
             // let agent = agent.deref_mut();
-            if let Some((w, testees)) = self.agent.check_quarantine(pfs) {
-                fsi.contacted_testees = Some(testees);
+            if let Some((w, traced)) = self.agent.check_quarantine(pfs) {
+                fsi.traced = Some(traced);
                 break 'block Some(Transfer::Extra(w));
             }
             if self.agent.testing.read().is_reservable(pfs) {
                 let mut r = None;
-                if let Some(ip) = self.agent.health.read().get_symptomatic() {
+                let health = self.agent.health.read();
+                let tst_rt = if health.is_refuser() {
+                    pfs.wp.av_test_rate.r()
+                } else {
+                    1.0
+                };
+                if let Some(ip) = health.get_symptomatic() {
                     if ip.days_diseased >= pfs.rp.tst_delay
                         && random::at_least_once_hit_in(
                             pfs.wp.days_per_step(),
-                            pfs.rp.tst_sbj_sym.r(),
+                            pfs.tst_sbj_sym().r() * tst_rt,
                         )
                     {
                         r = Some(TestReason::AsSymptom);
                     }
                 } else if random::at_least_once_hit_in(
                     pfs.wp.days_per_step(),
-                    pfs.rp.tst_sbj_asy.r(),
+                    pfs.tst_sbj_asy().r() * tst_rt,
                 ) {
                     r = Some(TestReason::AsSuspected);
                 }
@@ -134,14 +158,31 @@ impl FieldAgent {
                     fsi.testee = Some(Testee::new((&self.agent).into(), r, pfs.rp.step));
                 }
             }
-            if let Some(w) = self.agent.health.write().field_step(
+            let mut health = self.agent.health.write();
+            let was_infected = health.get_infected().is_some();
+            let n_doses = health.n_doses();
+            let warp = health.field_step(
                 temp.infected,
                 self.agent.activeness,
                 self.agent.age,
                 &mut fsi.hist_info,
                 &mut fsi.health_diff,
                 pfs,
-            ) {
+            );
+            if health.n_doses() > n_doses {
+                fsi.n_doses = Some(health.n_doses());
+            }
+            if !was_infected && health.get_infected().is_some() {
+                fsi.infection_place = Some(self.agent.whereabouts(pfs));
+                fsi.infection_type = Some(if health.memory.n_infections > 1 {
+                    InfectionType::Reinfected
+                } else {
+                    InfectionType::Infected
+                });
+                fsi.infection_event = self.agent.attending_event(pfs.wp);
+            }
+            drop(health);
+            if let Some(w) = warp {
                 break 'block Some(Transfer::Extra(w));
             }
             if let Some(w) = self.agent.warp_inside(pfs) {
@@ -155,19 +196,20 @@ impl FieldAgent {
         (fsi, transfer)
     }
 
-    fn interacts(&mut self, fb: &mut Self, pfs: &ParamsForStep) {
+    fn interacts(&mut self, fb: &mut Self, snapshot: &Snapshot, pfs: &ParamsForStep) {
         let a = &mut self.agent; //.write();
         let b = &mut fb.agent; //.write();
         if let Some((df, d)) = a.body.calc_force_delta(&b.body, pfs) {
+            if pfs.wp.is_walled(&a.body.pt, &b.body.pt) {
+                return;
+            }
             self.temp.force -= df;
             fb.temp.force += df;
             self.temp.update_best(&a.body, &b.body);
             fb.temp.update_best(&b.body, &a.body);
 
-            let a_health = a.health.read();
-            let b_health = b.health.read();
-            self.temp.infected(&a_health, &b_health, d, pfs);
-            fb.temp.infected(&b_health, &a_health, d, pfs);
+            self.temp.infected(snapshot, a.id, b.id, d, pfs);
+            fb.temp.infected(snapshot, b.id, a.id, d, pfs);
 
             self.temp.record_contact(&b, d, pfs);
             fb.temp.record_contact(&a, d, pfs);
@@ -175,14 +217,106 @@ impl FieldAgent {
     }
 }
 
+/// amount of virus left in the air by an emitter below which it is cleared
+const MIN_AMOUNT: f64 = 1e-6;
+
+/// virus left in the air of a cell by an agent
+struct Emission {
+    id: usize,
+    emitter: AgentRef,
+    variant: Variant,
+    amount: f64,
+}
+
+/// virus accumulated in the air of a cell
+#[derive(Default)]
+struct Airborne {
+    emissions: Vec<Emission>,
+}
+
+impl Airborne {
+    /// Infects the agents in the cell with the virus in the air, crediting the emitters.
+    /// Returns the infection counts of the emitters credited.
+    fn step(&mut self, ags: &mut [FieldAgent], pfs: &ParamsForStep) -> Vec<InfectionCntInfo> {
+        let model = &pfs.wp.infec_model;
+        let dps = pfs.wp.days_per_step();
+        let retention = model.retention().powf(dps);
+        for em in &mut self.emissions {
+            em.amount *= retention;
+        }
+        self.emissions.retain(|em| em.amount >= MIN_AMOUNT);
+        for fa in ags.iter() {
+            let health = fa.agent.health.read();
+            let Some(ip) = health.get_infected() else {
+                continue;
+            };
+            let e = model.emission(&ip.infector(health.days_to.onset), pfs) * dps;
+            if e <= 0.0 {
+                continue;
+            }
+            match self.emissions.iter_mut().find(|em| em.id == fa.agent.id) {
+                Some(em) => {
+                    em.amount += e;
+                    em.variant = ip.virus_variant.clone();
+                }
+                None => self.emissions.push(Emission {
+                    id: fa.agent.id,
+                    emitter: (&fa.agent).into(),
+                    variant: ip.virus_variant.clone(),
+                    amount: e,
+                }),
+            }
+        }
+        let amount = self.emissions.iter().map(|em| em.amount).sum::<f64>();
+        // the virus spreads over the whole cell, so the amount is scaled to a square of the view
+        // range for the same exposure whatever the mesh
+        let scale = (pfs.wp.view_range() / pfs.wp.cell_size()).powi(2);
+        let prob = model.exposure_prob(amount * scale, pfs);
+        let mut infcts = Vec::new();
+        if prob <= 0.0 {
+            return infcts;
+        }
+        let rng = &mut rand::thread_rng();
+        for fa in ags.iter_mut() {
+            if fa.temp.infected.is_some() {
+                continue;
+            }
+            // the emitter is drawn in proportion to the virus it left
+            let Ok(em) = self.emissions.choose_weighted(rng, |em| em.amount) else {
+                break;
+            };
+            if em.id == fa.agent.id {
+                continue;
+            }
+            let Some(immunity) = fa.agent.health.read().get_immune_factor(&em.variant, pfs) else {
+                continue;
+            };
+            if random::at_least_once_hit_in(dps, prob * (1.0 - immunity)) {
+                fa.temp.infected = Some((immunity, em.variant.clone()));
+                let mut infct_info = None;
+                em.emitter.log.write().update_n_infects(1, &mut infct_info);
+                infcts.extend(infct_info);
+                if random::at_least_once_hit_in(dps, pfs.rp.cntct_trc.r()) {
+                    fa.temp.new_contacts.push(em.emitter.clone());
+                }
+            }
+        }
+        infcts
+    }
+}
+
 pub struct Field {
     table: Table<Vec<FieldAgent>>,
+    air: Table<Airborne>,
+    snapshot: Snapshot,
 }
 
 impl Field {
-    pub fn new(mesh: usize) -> Self {
+    pub fn new(wp: &WorldParams) -> Self {
         Self {
-            table: Table::new(mesh, mesh, Vec::new),
+            table: Table::new(wp.mesh_rows(), wp.mesh, Vec::new),
+            air: Table::new(wp.mesh_rows(), wp.mesh, Airborne::default),
+            snapshot: Snapshot::default(),
         }
     }
 
@@ -193,6 +327,9 @@ impl Field {
                 agents.push(fa.agent);
             }
         }
+        for (_, air) in self.air.iter_mut().horizontal() {
+            *air = Airborne::default();
+        }
     }
 
     pub fn step(
@@ -203,7 +340,16 @@ impl Field {
         health_count: &mut HealthCount,
         pfs: &ParamsForStep,
     ) {
-        self.interact(&pfs);
+        if pfs.wp.infec_model.is_airborne() {
+            let infcts = self
+                .table
+                .par_iter_mut()
+                .horizontal()
+                .zip(self.air.par_iter_mut().horizontal())
+                .flat_map_iter(|((_, ags), (_, air))| air.step(ags, pfs))
+                .collect::<Vec<_>>();
+            stat.infcts.extend(infcts);
+        }
         let tmp = self
             .table
             .par_iter_mut()
@@ -219,10 +365,24 @@ impl Field {
                 stat.infcts.push(infct);
             }
             if let Some(hd) = fsi.health_diff {
+                stat.apply_difference(&hd);
                 health_count.apply_difference(hd);
             }
-            if let Some(testees) = fsi.contacted_testees {
-                test_queue.extend(testees);
+            if let Some(traced) = fsi.traced {
+                stat.add_tracing_count(&traced.count);
+                test_queue.extend(traced.testees);
+            }
+            if let Some(place) = fsi.infection_place {
+                stat.infection_place_count[&place] += 1;
+            }
+            if let Some(n) = fsi.n_doses {
+                stat.add_dose(n);
+            }
+            if let Some(t) = fsi.infection_type {
+                stat.infection_type_count[&t] += 1;
+            }
+            if let Some(i) = fsi.infection_event {
+                stat.event_count[i] += 1;
             }
             if let Some(testee) = fsi.testee {
                 test_queue.push(testee);
@@ -237,13 +397,22 @@ impl Field {
     }
 
     pub fn replace_gathering(&self, gathering: &Arc<RwLock<Gathering>>, pfs: &ParamsForStep) {
-        let locs = gathering.read().get_locations(pfs.wp);
+        let (locs, participation, region) = {
+            let gat = gathering.read();
+            (
+                gat.get_locations(pfs.wp),
+                gat.participation(),
+                gat.region(pfs.wp),
+            )
+        };
+        let pfs = &pfs.in_region(region);
         locs.into_par_iter().for_each(|loc| {
             for fa in &self.table[loc] {
+                let prob = participation.unwrap_or_else(|| {
+                    random::modified_prob(fa.agent.gat_info.read().gat_freq, pfs.gat_freq())
+                });
                 if !fa.agent.health.read().is_symptomatic()
-                    && rand::thread_rng().gen::<f64>()
-                        < random::modified_prob(fa.agent.gat_info.read().gat_freq, &pfs.rp.gat_freq)
-                            .r()
+                    && rand::thread_rng().gen::<f64>() < prob.r()
                 {
                     fa.agent.gat_info.write().gathering = Arc::downgrade(gathering);
                 }
@@ -251,93 +420,292 @@ impl Field {
         });
     }
 
+    /// Infects at most `n` susceptible agents chosen at random in the region with the variant.
+    /// Returns the number of infected agents.
+    pub fn import_cases(
+        &mut self,
+        region: usize,
+        n: usize,
+        variant: &Variant,
+        pfs: &ParamsForStep,
+    ) -> usize {
+        let region = &pfs.wp.regions[region];
+        let mut iter = self.table.iter_mut();
+        let mut candidates = iter
+            .horizontal()
+            .flat_map(|(_, c)| c.iter_mut())
+            .filter(|fa| {
+                fa.temp.infected.is_none()
+                    && region.contains(&fa.agent.body.pt)
+                    && matches!(fa.agent.health.read().state, HealthState::Susceptible)
+            })
+            .collect::<Vec<_>>();
+        let (chosen, _) = candidates.partial_shuffle(&mut rand::thread_rng(), n);
+        for fa in chosen.iter_mut() {
+            fa.temp.infected = Some((0.0, variant.clone()));
+        }
+        chosen.len()
+    }
+
     pub fn add(&mut self, agent: Agent, idx: TableIndex) {
         self.table[idx.clone()].push(FieldAgent::new(agent, idx));
     }
 
-    fn interact(&mut self, pfs: &ParamsForStep) {
-        // |-|a|b|a|b|
-        self.table
-            .par_iter_mut()
-            .east()
-            .for_each(move |((_, a_ags), (_, b_ags))| {
-                Self::interact_intercells(a_ags, b_ags, pfs);
-            });
-        // |a|b|a|b|
-        self.table
-            .par_iter_mut()
-            .west()
-            .for_each(|((_, a_ags), (_, b_ags))| {
-                Self::interact_intercells(a_ags, b_ags, pfs);
-            });
-
-        // |a|
-        // |b|
+    #[cfg(feature = "bench")]
+    pub fn discard_interactions(&mut self) {
         self.table
             .par_iter_mut()
-            .north()
-            .for_each(|((_, a_ags), (_, b_ags))| {
-                Self::interact_intercells(a_ags, b_ags, pfs);
-            });
-
-        // |-|
-        // |a|
-        // |b|
-        self.table
-            .par_iter_mut()
-            .south()
-            .for_each(|((_, a_ags), (_, b_ags))| {
-                Self::interact_intercells(a_ags, b_ags, pfs);
-            });
+            .horizontal()
+            .for_each(|(_, ags)| ags.iter_mut().for_each(|fa| fa.temp = TempParam::default()));
+    }
 
-        // | |a|
-        // |b| |
+    /// Makes the agents close to each other interact, which is applied to them in [`Field::step`].
+    pub fn interact(&mut self, pfs: &ParamsForStep) {
+        self.snapshot.take(
+            self.table
+                .par_iter()
+                .horizontal()
+                .flat_map_iter(|(_, ags)| ags.iter().map(|fa| &fa.agent)),
+        );
+        let snapshot = &self.snapshot;
+
+        // |ab|
         self.table
             .par_iter_mut()
-            .northeast()
-            .for_each(|((_, a_ags), (_, b_ags))| {
-                Self::interact_intercells(a_ags, b_ags, pfs);
-            });
+            .horizontal()
+            .for_each(|(_, ags)| Self::interact_intracell(ags, snapshot, pfs));
+
+        // cells as far as the view range reaches, each pair of which is visited once
+        let cell_size = pfs.wp.cell_size();
+        let view_range = pfs.wp.view_range();
+        let rings = ((view_range / cell_size).ceil() as isize).max(1);
+        for row in 0..=rings {
+            for column in -rings..=rings {
+                if row == 0 && column <= 0 {
+                    continue;
+                }
+                // the closest points of the cells
+                let gap = |n: isize| (n.abs() - 1).max(0) as f64 * cell_size;
+                if gap(row).hypot(gap(column)) >= view_range {
+                    continue;
+                }
+                self.table
+                    .par_for_each_pair_mut(row as usize, column, |(_, a_ags), (_, b_ags)| {
+                        Self::interact_intercells(a_ags, b_ags, snapshot, pfs);
+                    });
+            }
+        }
+    }
 
-        // |a| |
-        // | |b|
-        self.table
-            .par_iter_mut()
-            .northwest()
-            .for_each(|((_, a_ags), (_, b_ags))| {
-                Self::interact_intercells(a_ags, b_ags, pfs);
-            });
-
-        // |-|-|
-        // |a| |
-        // | |b|
-        self.table
-            .par_iter_mut()
-            .southeast()
-            .for_each(|((_, a_ags), (_, b_ags))| {
-                Self::interact_intercells(a_ags, b_ags, pfs);
-            });
-
-        // |-|-|
-        // | |a|
-        // |b| |
-        self.table
-            .par_iter_mut()
-            .southwest()
-            .for_each(|((_, a_ags), (_, b_ags))| {
-                Self::interact_intercells(a_ags, b_ags, pfs);
-            });
+    /// Makes each pair of agents in a cell interact once.
+    fn interact_intracell(ags: &mut [FieldAgent], snapshot: &Snapshot, pfs: &ParamsForStep) {
+        for i in 1..ags.len() {
+            let (former, latter) = ags.split_at_mut(i);
+            let fb = &mut latter[0];
+            for fa in former {
+                fa.interacts(fb, snapshot, pfs);
+            }
+        }
     }
 
     fn interact_intercells(
         a_ags: &mut [FieldAgent],
         b_ags: &mut [FieldAgent],
+        snapshot: &Snapshot,
         pfs: &ParamsForStep,
     ) {
         for fa in a_ags {
             for fb in b_ags.iter_mut() {
-                fa.interacts(fb, pfs);
+                fa.interacts(fb, snapshot, pfs);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use math::Point;
+    use rand::Rng;
+
+    use super::{Airborne, Field, FieldAgent};
+    use crate::world::{
+        agent::Agent,
+        commons::{FiniteTypePool, ParamsForStep, RuntimeParams, WorldParams},
+        infection::{InfectionModel, Infector},
+    };
+
+    /// infects every agent in the view range
+    #[derive(Debug)]
+    struct Certain;
+
+    impl InfectionModel for Certain {
+        fn contact_prob(&self, _infector: &Infector, _d: f64, _pfs: &ParamsForStep) -> f64 {
+            1.0
+        }
+    }
+
+    /// emits a unit of virus per day which infects anyone exposed to it
+    #[derive(Debug)]
+    struct Cloud;
+
+    impl InfectionModel for Cloud {
+        fn contact_prob(&self, _infector: &Infector, _d: f64, _pfs: &ParamsForStep) -> f64 {
+            0.0
+        }
+
+        fn is_airborne(&self) -> bool {
+            true
+        }
+
+        fn emission(&self, _infector: &Infector, _pfs: &ParamsForStep) -> f64 {
+            1.0
+        }
+
+        fn retention(&self) -> f64 {
+            0.01
+        }
+
+        fn exposure_prob(&self, amount: f64, _pfs: &ParamsForStep) -> f64 {
+            if amount > 0.0 {
+                1.0
+            } else {
+                0.0
+            }
+        }
+    }
+
+    #[test]
+    fn test_airborne() {
+        let mut wp = WorldParams::new(
+            3,
+            100,
+            5,
+            16,
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            None,
+            0.0.into(),
+            0.0,
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            0.0,
+            0.0,
+            0.0,
+            0.0.into(),
+        );
+        wp.infec_model = Arc::new(Cloud);
+        let rp = RuntimeParams {
+            cntct_trc: 100.0.into(),
+            ..Default::default()
+        };
+        let pfs = ParamsForStep::new(&wp, &rp);
+        let p = Point::new(10.0, 10.0);
+        let new_agent = |id: usize| {
+            let mut agent = Agent::new();
+            agent.id = id;
+            agent.body.pt = p;
+            FieldAgent::new(agent, wp.into_grid_index(&p))
+        };
+        let mut ags = (0..3).map(new_agent).collect::<Vec<_>>();
+        ags[0]
+            .agent
+            .health
+            .write()
+            .infect(0.0, rp.variant_pool.get(0));
+
+        // the emitter is credited with both infections and traced by both
+        let mut air = Airborne::default();
+        let infcts = air.step(&mut ags, &pfs);
+        assert_eq!(infcts.len(), 2);
+        assert_eq!(ags[0].agent.log.read().n_infects, 2);
+        for fa in &ags[1..] {
+            assert!(fa.temp.infected.is_some());
+            assert_eq!(fa.temp.new_contacts.len(), 1);
+        }
+
+        // the virus is cleared once the emitter has left
+        let mut ags = vec![new_agent(3)];
+        for _ in 0..100 {
+            air.step(&mut [], &pfs);
+        }
+        assert!(air.emissions.is_empty());
+        assert!(air.step(&mut ags, &pfs).is_empty());
+        assert!(ags[0].temp.infected.is_none());
+    }
+
+    #[test]
+    fn test_interact() {
+        let mut wp = WorldParams::new(
+            200,
+            100,
+            5,
+            16,
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            None,
+            0.0.into(),
+            0.0,
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            0.0,
+            0.0,
+            0.0,
+            0.0.into(),
+        );
+        wp.infec_model = Arc::new(Certain);
+        let rp = RuntimeParams::default();
+
+        let rng = &mut rand::thread_rng();
+        let pts = (0..wp.init_n_pop)
+            .map(|_| Point::new(rng.gen::<f64>() * 100.0, rng.gen::<f64>() * 100.0))
+            .collect::<Vec<_>>();
+        let is_infected = |i: usize| i % 20 == 0;
+        let expected = (0..pts.len())
+            .filter(|&i| {
+                !is_infected(i)
+                    && (0..pts.len()).any(|j| {
+                        let d = pts[j] - pts[i];
+                        is_infected(j) && d.x.hypot(d.y) < wp.view_range()
+                    })
+            })
+            .count();
+        assert!(expected > 0);
+
+        // cells of 20, of about 14 and of 5 within the view range of 20
+        for mesh in [5, 7, 20] {
+            wp.set_mesh(mesh);
+            let pfs = ParamsForStep::new(&wp, &rp);
+            let mut field = Field::new(&wp);
+            for (i, p) in pts.iter().enumerate() {
+                let mut agent = Agent::new();
+                agent.id = i;
+                agent.body.pt = *p;
+                if is_infected(i) {
+                    agent.health.write().infect(0.0, rp.variant_pool.get(0));
+                }
+                field.add(agent, wp.into_grid_index(p));
             }
+            field.interact(&pfs);
+            let n = field
+                .table
+                .iter_mut()
+                .horizontal()
+                .flat_map(|(_, ags)| ags.iter())
+                .filter(|fa| fa.temp.infected.is_some())
+                .count();
+            assert_eq!(n, expected, "mesh = {mesh}");
         }
     }
 }
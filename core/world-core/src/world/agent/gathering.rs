@@ -1,5 +1,5 @@
 use super::{
-    super::commons::{CenteredBias, RuntimeParams, WorkPlaceMode, WorldParams},
+    super::commons::{CenteredBias, GatheringEvent, RuntimeParams, WorkPlaceMode, WorldParams},
     field::Field,
 };
 use crate::{
@@ -9,7 +9,7 @@ use crate::{
 
 use std::{f64, ops, sync::Arc};
 
-use math::{self, Point};
+use math::{self, Percentage, Point};
 
 use parking_lot::RwLock;
 use rand::{seq::SliceRandom, Rng};
@@ -23,28 +23,36 @@ pub struct Gathering {
     duration: f64,
     strength: f64,
     p: Point,
+    /// index of the scheduled event
+    event: Option<(usize, Percentage)>,
 }
 
 impl Gathering {
-    pub fn new(
-        gat_spots_fixed: &[Point],
-        agent_origins: &Vec<Point>,
-        wp: &WorldParams,
-        rp: &RuntimeParams,
-    ) -> Self {
+    pub fn new(agent_origins: &Vec<Point>, wp: &WorldParams, rp: &RuntimeParams) -> Self {
         let rng = &mut rand::thread_rng();
-        let p = if !gat_spots_fixed.is_empty() && rp.gat_rnd_rt.r() < rng.gen::<f64>() {
-            *gat_spots_fixed.choose(rng).unwrap()
+        let spot = if rp.gat_rnd_rt.r() < rng.gen::<f64>() {
+            rp.gat_spots
+                .iter()
+                .filter(|spot| spot.open)
+                .collect::<Vec<_>>()
+                .choose_weighted(rng, |spot| spot.weight)
+                .ok()
+                .map(|spot| spot.p)
         } else {
+            None
+        };
+        let p = spot.unwrap_or_else(|| {
             *agent_origins.choose(rng).unwrap_or(&Point {
                 x: rng.gen::<f64>() * wp.field_size(),
-                y: rng.gen::<f64>() * wp.field_size(),
+                y: rng.gen::<f64>() * wp.field_height(),
             })
-        };
+        });
         let size = {
             let size = random::my_random(rng, &rp.gat_sz);
             if matches!(wp.wrk_plc_mode, Some(WorkPlaceMode::Centered)) {
-                size * p.map(|c| c / wp.field_size() * 2.0 - 1.0).centered_bias()
+                size * Point::new(p.x / wp.field_size(), p.y / wp.field_height())
+                    .map(|c| c * 2.0 - 1.0)
+                    .centered_bias()
                     * f64::consts::SQRT_2
             } else {
                 size
@@ -55,13 +63,41 @@ impl Gathering {
             duration: random::my_random(rng, &rp.gat_dr),
             strength: random::my_random(rng, &rp.gat_st),
             p,
+            event: None,
+        }
+    }
+
+    pub fn from_event(index: usize, ev: &GatheringEvent) -> Self {
+        Self {
+            size: ev.size,
+            duration: ev.duration,
+            strength: ev.strength,
+            p: ev.p,
+            event: Some((index, ev.participation)),
         }
     }
 
-    pub fn get_effect(&self, pt: &Point) -> (Option<Point>, Option<f64>) {
+    /// Returns the index of the region it is held in.
+    pub fn region(&self, wp: &WorldParams) -> Option<usize> {
+        wp.region_at(&self.p)
+    }
+
+    /// Returns the index of the scheduled event.
+    pub fn event(&self) -> Option<usize> {
+        self.event.map(|(i, _)| i)
+    }
+
+    /// Returns the participation probability if it is a scheduled event.
+    pub fn participation(&self) -> Option<Percentage> {
+        self.event.map(|(_, p)| p)
+    }
+
+    /// Returns the force pulling an agent at `pt` and its relative distance if inside.
+    /// A gathering does not reach agents behind a wall.
+    pub fn get_effect(&self, pt: &Point, wp: &WorldParams) -> (Option<Point>, Option<f64>) {
         let delta = self.p - *pt;
         let d = delta.x.hypot(delta.y);
-        if d > self.size + SURROUND || d < 0.01 {
+        if d > self.size + SURROUND || d < 0.01 || wp.is_walled(&self.p, pt) {
             return (None, None);
         }
         let mut f_norm = self.strength / SURROUND * GATHERING_FORCE;
@@ -98,9 +134,10 @@ impl Gathering {
     pub fn get_locations(&self, wp: &WorldParams) -> Vec<(usize, usize)> {
         let r = self.size + SURROUND;
         let p = self.p;
-        let bottom = commons::quantize(0f64.max(p.y - r), wp.res_rate(), wp.mesh);
-        let top = commons::quantize(wp.field_size().min(p.y + r), wp.res_rate(), wp.mesh);
-        let center = commons::quantize(p.y + 0.5, wp.res_rate(), wp.mesh); // rounding
+        let rows = wp.mesh_rows();
+        let bottom = commons::quantize(0f64.max(p.y - r), wp.res_rate(), rows);
+        let top = commons::quantize(wp.field_height().min(p.y + r), wp.res_rate(), rows);
+        let center = commons::quantize(p.y + 0.5, wp.res_rate(), rows); // rounding
 
         let mut locs = Vec::new();
         for row in bottom..center {
@@ -118,45 +155,254 @@ impl Gathering {
     }
 }
 
-pub struct Gatherings(Vec<Arc<RwLock<Gathering>>>);
+pub struct Gatherings {
+    gats: Vec<Arc<RwLock<Gathering>>>,
+    /// whether each scheduled event has started
+    started: Vec<bool>,
+}
 
 impl Gatherings {
     pub fn new() -> Self {
-        Self(Vec::new())
+        Self {
+            gats: Vec::new(),
+            started: Vec::new(),
+        }
     }
 
     pub fn clear(&mut self) {
-        self.0.clear()
+        self.gats.clear();
+        self.started.clear();
     }
 
-    pub fn step(
-        &mut self,
-        field: &Field,
-        gat_spots_fixed: &[Point],
-        agent_origins: &Vec<Point>,
-        pfs: &ParamsForStep,
-    ) {
-        self.0
-            .retain_mut(|gat| !gat.write().step(pfs.wp.days_per_step()));
+    pub fn step(&mut self, field: &Field, agent_origins: &Vec<Point>, pfs: &ParamsForStep) {
+        self.gats.retain_mut(|gat| {
+            let mut gat = gat.write();
+            let cancelled = matches!(gat.event(), Some(i) if pfs.rp.gat_events[i].cancelled);
+            !(gat.step(pfs.wp.days_per_step()) || cancelled)
+        });
+
+        let today = pfs.rp.step as f64 * pfs.wp.days_per_step();
+        self.started.resize(pfs.rp.gat_events.len(), false);
+        for (i, ev) in pfs.rp.gat_events.iter().enumerate() {
+            if !self.started[i] && !ev.cancelled && ev.start <= today {
+                self.started[i] = true;
+                let gat = Arc::new(RwLock::new(Gathering::from_event(i, ev)));
+                field.replace_gathering(&gat, pfs);
+                self.gats.push(gat);
+            }
+        }
+
+        // agents are pulled back home instead of to gatherings, so no random one is held
+        if pfs.go_home_back() {
+            return;
+        }
 
         // caliculate the number of gathering circles
         // using random number in exponetial distribution.
         let rng = &mut rand::thread_rng();
-        let n_new_gat = (pfs.rp.gat_fr
-            * pfs.wp.days_per_step()
-            * (pfs.wp.field_size * pfs.wp.field_size) as f64
-            / 1e5
-            * (-(rng.gen::<f64>() * 0.9999 + 0.0001).ln()))
-        .round() as usize;
+        let gat_fr = pfs
+            .rp
+            .rgn_prm
+            .values()
+            .filter_map(|r| r.gat_fr)
+            .fold(pfs.rp.gat_fr, f64::max);
+        let n_new_gat =
+            (gat_fr * pfs.wp.days_per_step() * pfs.wp.field_size() * pfs.wp.field_height() / 1e5
+                * (-(rng.gen::<f64>() * 0.9999 + 0.0001).ln()))
+            .round() as usize;
         for _ in 0..n_new_gat {
-            let gat = Arc::new(RwLock::new(Gathering::new(
-                gat_spots_fixed,
-                agent_origins,
-                pfs.wp,
-                pfs.rp,
-            )));
+            let gat = Gathering::new(agent_origins, pfs.wp, pfs.rp);
+            // thinned out to the frequency of the region it is held in
+            let local_fr = pfs.in_region(gat.region(pfs.wp)).gat_fr();
+            if rng.gen::<f64>() * gat_fr >= local_fr {
+                continue;
+            }
+            let gat = Arc::new(RwLock::new(gat));
             field.replace_gathering(&gat, pfs);
-            self.0.push(gat);
+            self.gats.push(gat);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use math::Point;
+
+    use super::{super::field::Field, Gathering, Gatherings};
+    use crate::{
+        util::random::DistInfo,
+        world::{
+            commons::{
+                GatheringEvent, GatheringSpot, ParamsForStep, RegionParams, RuntimeParams,
+                WorkPlaceMode, WorldParams,
+            },
+            geometry::{Region, Wall},
+        },
+    };
+
+    /// Returns the parameters of a square field of 360 divided into 18 cells with 16 steps a day.
+    fn world_params(init_n_pop: u32) -> WorldParams {
+        WorldParams::new(
+            init_n_pop,
+            360,
+            18,
+            16,
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            None,
+            0.0.into(),
+            0.0,
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            0.0,
+            0.0,
+            0.0,
+            0.0.into(),
+        )
+    }
+
+    #[test]
+    fn test_spot() {
+        let wp = world_params(1);
+        let (closed, open, unweighted) = (
+            Point::new(10.0, 10.0),
+            Point::new(20.0, 20.0),
+            Point::new(30.0, 30.0),
+        );
+        let mut rp = RuntimeParams {
+            gat_spots: vec![
+                GatheringSpot {
+                    open: false,
+                    ..GatheringSpot::new(closed, 1.0)
+                },
+                GatheringSpot::new(open, 1.0),
+                GatheringSpot::new(unweighted, 0.0),
+            ],
+            ..Default::default()
+        };
+        let origins = vec![Point::new(40.0, 40.0)];
+        for _ in 0..100 {
+            assert_eq!(Gathering::new(&origins, &wp, &rp).p, open);
+        }
+        // gatherings are around a home without any open spot
+        rp.gat_spots[1].open = false;
+        assert_eq!(Gathering::new(&origins, &wp, &rp).p, origins[0]);
+    }
+
+    #[test]
+    fn test_event() {
+        let wp = world_params(1);
+        let field = Field::new(&wp);
+        let event = GatheringEvent {
+            p: Point::new(100.0, 100.0),
+            size: 10.0,
+            start: 1.0,
+            duration: 12.0,
+            strength: 50.0,
+            participation: 50.0.into(),
+            cancelled: false,
+        };
+        let mut rp = RuntimeParams {
+            gat_fr: 0.0,
+            gat_events: vec![
+                event.clone(),
+                GatheringEvent {
+                    cancelled: true,
+                    ..event
+                },
+            ],
+            ..Default::default()
+        };
+        let mut gats = Gatherings::new();
+        // 16 steps a day, each of which lasts 1.5 hours
+        let mut n_gats = Vec::new();
+        for step in 0..32 {
+            rp.step = step;
+            gats.step(&field, &Vec::new(), &ParamsForStep::new(&wp, &rp));
+            n_gats.push(gats.gats.len());
+        }
+        let expected = (0..32)
+            .map(|s| (16..24).contains(&s) as usize)
+            .collect::<Vec<_>>();
+        assert_eq!(n_gats, expected);
+        assert_eq!(gats.started, [true, false]);
+
+        // events start on time while commuters are pulled back home
+        let mut commuting = wp.clone();
+        commuting.wrk_plc_mode = Some(WorkPlaceMode::Uniform);
+        gats.clear();
+        rp.step = 16;
+        let pfs = ParamsForStep::new(&commuting, &rp);
+        assert!(pfs.go_home_back());
+        gats.step(&field, &Vec::new(), &pfs);
+        assert_eq!(gats.gats.len(), 1);
+
+        // an event cancelled while it takes place ends
+        gats.clear();
+        rp.step = 16;
+        gats.step(&field, &Vec::new(), &ParamsForStep::new(&wp, &rp));
+        assert_eq!(gats.gats[0].read().event(), Some(0));
+        rp.gat_events[0].cancelled = true;
+        rp.step = 17;
+        gats.step(&field, &Vec::new(), &ParamsForStep::new(&wp, &rp));
+        assert!(gats.gats.is_empty());
+    }
+
+    #[test]
+    fn test_region() {
+        let mut wp = world_params(2);
+        wp.regions = (0..2)
+            .map(|i| Region {
+                min: Point::new(i as f64 * 180.0, 0.0),
+                max: Point::new(i as f64 * 180.0 + 160.0, 360.0),
+                population: 1,
+                infected: 0.0.into(),
+            })
+            .collect();
+        let field = Field::new(&wp);
+        let origins = vec![Point::new(80.0, 180.0), Point::new(260.0, 180.0)];
+        // no gathering is held in the first region
+        let mut rp = RuntimeParams {
+            gat_fr: 100.0,
+            gat_dr: DistInfo::new(24.0, 24.0, 24.0),
+            rgn_prm: [(
+                0,
+                RegionParams {
+                    gat_fr: Some(0.0),
+                    ..Default::default()
+                },
+            )]
+            .into(),
+            ..Default::default()
+        };
+        let mut gats = Gatherings::new();
+        for step in 0..10 {
+            rp.step = step;
+            gats.step(&field, &origins, &ParamsForStep::new(&wp, &rp));
+        }
+        assert!(!gats.gats.is_empty());
+        assert!(gats.gats.iter().all(|g| g.read().region(&wp) == Some(1)));
+    }
+
+    #[test]
+    fn test_wall() {
+        let mut wp = world_params(1);
+        let gat = Gathering {
+            size: 10.0,
+            duration: 1.0,
+            strength: 50.0,
+            p: Point::new(120.0, 100.0),
+            event: None,
+        };
+        let pt = Point::new(105.0, 100.0);
+        assert!(matches!(gat.get_effect(&pt, &wp), (Some(_), None)));
+        wp.walls
+            .push(Wall::new(Point::new(110.0, 0.0), Point::new(110.0, 360.0)));
+        assert!(matches!(gat.get_effect(&pt, &wp), (None, None)));
+    }
+}
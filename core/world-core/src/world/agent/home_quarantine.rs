@@ -0,0 +1,147 @@
+use super::{warp::Warps, Agent, Location, LocationLabel, ParamsForStep, WarpParam};
+use crate::{
+    stat::{HealthCount, HealthDiff, HistInfo, Stat},
+    util::DrainMap,
+};
+
+#[derive(Default)]
+pub struct HomeQuarantineStepInfo {
+    pub hist_info: Option<HistInfo>,
+    pub health_diff: Option<HealthDiff>,
+}
+
+/// an agent isolated at its origin without moving nor joining gatherings
+pub struct HomeQuarantineAgent {
+    agent: Agent,
+    /// the step when the agent is released
+    until: u32,
+}
+
+impl LocationLabel for HomeQuarantineAgent {
+    const LABEL: Location = Location::HomeQuarantine;
+}
+
+impl HomeQuarantineAgent {
+    fn new(agent: Agent, until: u32) -> Self {
+        Self {
+            agent: Self::label(agent),
+            until,
+        }
+    }
+
+    fn step(&mut self, pfs: &ParamsForStep) -> (HomeQuarantineStepInfo, Option<WarpParam>) {
+        let mut hqsi = HomeQuarantineStepInfo::default();
+        let agent = &mut self.agent;
+        let mut warp = agent.health.write().field_step(
+            None,
+            agent.activeness,
+            agent.age,
+            &mut hqsi.hist_info,
+            &mut hqsi.health_diff,
+            pfs,
+        );
+        if warp.is_none() && pfs.rp.step >= self.until {
+            warp = Some(WarpParam::back(agent.body.pt));
+        }
+        (hqsi, warp)
+    }
+}
+
+pub struct HomeQuarantine(Vec<HomeQuarantineAgent>);
+
+impl HomeQuarantine {
+    pub fn new(capacity: usize) -> Self {
+        Self(Vec::with_capacity(capacity))
+    }
+
+    pub fn clear(&mut self, agents: &mut Vec<Agent>) {
+        for hqa in self.0.drain(..) {
+            agents.push(hqa.agent);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn add(&mut self, agent: Agent, until: u32) {
+        self.0.push(HomeQuarantineAgent::new(agent, until));
+    }
+
+    pub fn step(
+        &mut self,
+        warps: &mut Warps,
+        stat: &mut Stat,
+        health_count: &mut HealthCount,
+        pfs: &ParamsForStep,
+    ) {
+        let tmp = self.0.drain_map_mut(|hqa| hqa.step(pfs));
+
+        for (hqsi, opt) in tmp.into_iter() {
+            if let Some(hist) = hqsi.hist_info {
+                stat.hists.push(hist);
+            }
+            if let Some(hd) = hqsi.health_diff {
+                stat.apply_difference(&hd);
+                health_count.apply_difference(hd);
+            }
+            if let Some((param, hqa)) = opt {
+                warps.add(hqa.agent, param);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HomeQuarantine;
+    use crate::{
+        stat::{HealthCount, Stat},
+        world::{
+            agent::{warp::Warps, Agent},
+            commons::{ParamsForStep, RuntimeParams, WorldParams},
+        },
+    };
+
+    #[test]
+    fn test_release() {
+        let wp = WorldParams::new(
+            1,
+            360,
+            18,
+            16,
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            None,
+            0.0.into(),
+            0.0,
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            0.0,
+            0.0,
+            0.0,
+            0.0.into(),
+        );
+        let mut rp = RuntimeParams::default();
+        let mut home_quarantine = HomeQuarantine::new(1);
+        let mut warps = Warps::new(1);
+        let mut stat = Stat::default();
+        let mut health_count = HealthCount::default();
+        home_quarantine.add(Agent::new(), 3);
+        for step in 0..=3 {
+            assert_eq!(home_quarantine.len(), 1);
+            rp.step = step;
+            let pfs = ParamsForStep::new(&wp, &rp);
+            home_quarantine.step(&mut warps, &mut stat, &mut health_count, &pfs);
+        }
+        assert_eq!(home_quarantine.len(), 0);
+        let mut agents = Vec::new();
+        warps.clear(&mut agents);
+        assert_eq!(agents.len(), 1);
+    }
+}
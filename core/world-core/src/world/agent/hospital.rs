@@ -1,4 +1,4 @@
-use super::{warp::Warps, Agent, Location, LocationLabel, ParamsForStep, WarpParam};
+use super::{param::Care, warp::Warps, Agent, Location, LocationLabel, ParamsForStep, WarpParam};
 use crate::{
     stat::{HealthCount, HealthDiff, HistInfo, Stat},
     util::DrainMap,
@@ -14,6 +14,8 @@ pub struct HospitalStepInfo {
 pub struct HospitalAgent {
     agent: Agent,
     back_to: Point,
+    /// the order of arrival
+    order: usize,
 }
 
 impl LocationLabel for HospitalAgent {
@@ -21,18 +23,22 @@ impl LocationLabel for HospitalAgent {
 }
 
 impl HospitalAgent {
-    fn new(agent: Agent, back_to: Point) -> Self {
+    fn new(agent: Agent, back_to: Point, order: usize) -> Self {
+        // an order to quarantine at home is void for an admitted agent
+        agent.testing.write().cancel_quarantine_order();
         Self {
             agent: Self::label(agent),
             back_to,
+            order,
         }
     }
 
-    fn step(&mut self, pfs: &ParamsForStep) -> (HospitalStepInfo, Option<WarpParam>) {
+    fn step(&mut self, care: Care, pfs: &ParamsForStep) -> (HospitalStepInfo, Option<WarpParam>) {
         // let agent = &mut self.agent.write();
         let mut hsi = HospitalStepInfo::default();
         let warp = self.agent.health.write().hospital_step(
             self.back_to,
+            care,
             &mut hsi.hist_info,
             &mut hsi.health_diff,
             pfs,
@@ -42,21 +48,58 @@ impl HospitalAgent {
     }
 }
 
-pub struct Hospital(Vec<HospitalAgent>);
+/// patients in beds and those waiting for a bed in the order of arrival
+pub struct Hospital {
+    beds: Vec<HospitalAgent>,
+    waiting: Vec<HospitalAgent>,
+    n_arrivals: usize,
+}
 
 impl Hospital {
     pub fn new(capacity: usize) -> Self {
-        Self(Vec::with_capacity(capacity))
+        Self {
+            beds: Vec::with_capacity(capacity),
+            waiting: Vec::new(),
+            n_arrivals: 0,
+        }
     }
 
     pub fn clear(&mut self, agents: &mut Vec<Agent>) {
-        for ha in self.0.drain(..) {
+        for ha in self.beds.drain(..).chain(self.waiting.drain(..)) {
             agents.push(ha.agent);
         }
+        self.n_arrivals = 0;
+    }
+
+    /// the number of occupied beds
+    pub fn n_occupied(&self) -> usize {
+        self.beds.len()
+    }
+
+    /// the number of patients waiting for a bed
+    pub fn n_waiting(&self) -> usize {
+        self.waiting.len()
     }
 
     pub fn add(&mut self, agent: Agent, back_to: Point) {
-        self.0.push(HospitalAgent::new(agent, back_to));
+        self.waiting
+            .push(HospitalAgent::new(agent, back_to, self.n_arrivals));
+        self.n_arrivals += 1;
+    }
+
+    fn admit(&mut self, pfs: &ParamsForStep) {
+        let n_free = if pfs.rp.hsp_capa.0 > 0.0 {
+            let n_beds = (pfs.rp.hsp_capa.r() * pfs.wp.init_n_pop()).round() as usize;
+            n_beds.saturating_sub(self.beds.len())
+        } else {
+            usize::MAX
+        };
+        if n_free == 0 || self.waiting.is_empty() {
+            return;
+        }
+        self.waiting.sort_unstable_by_key(|ha| ha.order);
+        let n = n_free.min(self.waiting.len());
+        self.beds.extend(self.waiting.drain(..n));
     }
 
     pub fn step(
@@ -66,13 +109,16 @@ impl Hospital {
         health_count: &mut HealthCount,
         pfs: &ParamsForStep,
     ) {
-        let tmp = self.0.drain_map_mut(|ha| ha.step(pfs));
+        self.admit(pfs);
+        let mut tmp = self.beds.drain_map_mut(|ha| ha.step(Care::Bed, pfs));
+        tmp.extend(self.waiting.drain_map_mut(|ha| ha.step(Care::Waiting, pfs)));
 
         for (hsi, opt) in tmp.into_iter() {
             if let Some(hist) = hsi.hist_info {
                 stat.hists.push(hist);
             }
             if let Some(hd) = hsi.health_diff {
+                stat.apply_difference(&hd);
                 health_count.apply_difference(hd);
             }
             if let Some((param, ha)) = opt {
@@ -81,3 +127,76 @@ impl Hospital {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use math::Point;
+
+    use super::Hospital;
+    use crate::{
+        stat::{HealthCount, Stat},
+        world::{
+            agent::{warp::Warps, Agent},
+            commons::{ParamsForStep, RuntimeParams, WorldParams},
+        },
+    };
+
+    #[test]
+    fn test_capacity() {
+        let wp = WorldParams::new(
+            1000,
+            360,
+            18,
+            16,
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            None,
+            0.0.into(),
+            0.0,
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            0.0,
+            0.0,
+            0.0,
+            0.0.into(),
+        );
+        // two beds for 1000 agents
+        let mut rp = RuntimeParams {
+            hsp_capa: 2.0.into(),
+            ..Default::default()
+        };
+        let mut hospital = Hospital::new(5);
+        let mut warps = Warps::new(5);
+        let mut stat = Stat::default();
+        let mut health_count = HealthCount::default();
+        for id in 0..5 {
+            let mut agent = Agent::new();
+            agent.id = id;
+            hospital.add(agent, Point::new(0.0, 0.0));
+        }
+        let pfs = ParamsForStep::new(&wp, &rp);
+        hospital.step(&mut warps, &mut stat, &mut health_count, &pfs);
+        assert_eq!(hospital.n_occupied(), 2);
+        assert_eq!(hospital.n_waiting(), 3);
+        // beds are given in the order of arrival
+        let mut ids = hospital
+            .beds
+            .iter()
+            .map(|ha| ha.agent.id)
+            .collect::<Vec<_>>();
+        ids.sort();
+        assert_eq!(ids, [0, 1]);
+
+        // no limit
+        rp.hsp_capa = 0.0.into();
+        let pfs = ParamsForStep::new(&wp, &rp);
+        hospital.step(&mut warps, &mut stat, &mut health_count, &pfs);
+        assert_eq!(hospital.n_occupied(), 5);
+        assert_eq!(hospital.n_waiting(), 0);
+    }
+}
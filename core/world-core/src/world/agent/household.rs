@@ -0,0 +1,199 @@
+use std::sync::Arc;
+
+use math::Point;
+use rand::seq::SliceRandom;
+
+use super::{Agent, AgentRef, HealthState, Location};
+use crate::{
+    stat::{HealthCount, InfectionPlace, InfectionType, Stat},
+    util::random,
+    world::commons::{ParamsForStep, WorldParams},
+};
+
+/// agents living together, who share an origin and infect each other regardless of distance
+pub type Household = Arc<Vec<AgentRef>>;
+
+pub struct Households(Vec<Household>);
+
+impl Households {
+    pub fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    /// Groups the agents in each region into households of sizes drawn from
+    /// [`hh_size`](WorldParams::hh_size), where the members move to the origin of the first one.
+    /// `origins` are collected again from the agents.
+    pub fn assign(&mut self, agents: &mut [Agent], origins: &mut Vec<Point>, wp: &WorldParams) {
+        self.0.clear();
+        if wp.hh_size.is_empty() {
+            return;
+        }
+        let rng = &mut rand::thread_rng();
+        let sizes = (1..=wp.hh_size.len()).collect::<Vec<_>>();
+        let mut groups = vec![Vec::new(); wp.regions.len().max(1)];
+        for (i, agent) in agents.iter().enumerate() {
+            let region = agent.health.read().region;
+            groups[region.unwrap_or(0)].push(i);
+        }
+        for mut group in groups {
+            group.shuffle(rng);
+            let mut rest = &group[..];
+            while !rest.is_empty() {
+                let size = *sizes
+                    .choose_weighted(rng, |s| wp.hh_size[s - 1])
+                    .unwrap_or(&1);
+                let (members, r) = rest.split_at(size.min(rest.len()));
+                rest = r;
+                let home = agents[members[0]].origin;
+                let household: Household =
+                    Arc::new(members.iter().map(|&i| (&agents[i]).into()).collect());
+                for &i in members {
+                    let agent = &mut agents[i];
+                    if let Some(p) = home {
+                        agent.origin = Some(p);
+                        agent.body.pt = p;
+                    }
+                    agent.household = household.clone();
+                }
+                self.0.push(household);
+            }
+        }
+        origins.clear();
+        origins.extend(agents.iter().filter_map(|a| a.origin));
+    }
+
+    /// Infects susceptible members at home by the infected ones at home, who are those in home
+    /// quarantine and those around their home at their last step in the field.
+    pub fn step(&self, stat: &mut Stat, health_count: &mut HealthCount, pfs: &ParamsForStep) {
+        if pfs.rp.hh_infec.r() <= 0.0 {
+            return;
+        }
+        let rng = &mut rand::thread_rng();
+        for household in &self.0 {
+            let is_home = |m: &AgentRef| match *m.location.read() {
+                Location::HomeQuarantine => true,
+                Location::Field => m.log.read().at_home,
+                _ => false,
+            };
+            let infectors = household
+                .iter()
+                .filter(|m| is_home(m))
+                .filter_map(|m| Some((m, m.health.read().get_infected()?.virus_variant.clone())))
+                .collect::<Vec<_>>();
+            if infectors.is_empty() {
+                continue;
+            }
+            for m in household.iter().filter(|m| is_home(m)) {
+                let mut health = m.health.write();
+                if !matches!(health.state, HealthState::Susceptible) {
+                    continue;
+                }
+                let (infector, variant) = infectors.choose(rng).unwrap();
+                let Some(immunity) = health.get_immune_factor(variant, pfs) else {
+                    continue;
+                };
+                let p = 1.0 - (1.0 - pfs.rp.hh_infec.r()).powi(infectors.len() as i32);
+                if !random::at_least_once_hit_in(pfs.wp.days_per_step(), p * (1.0 - immunity)) {
+                    continue;
+                }
+                let hd = health.infect(immunity, variant.clone());
+                stat.infection_place_count[&InfectionPlace::Household] += 1;
+                stat.infection_type_count[&if health.memory.n_infections > 1 {
+                    InfectionType::Reinfected
+                } else {
+                    InfectionType::Infected
+                }] += 1;
+                let mut infct_info = None;
+                infector.log.write().update_n_infects(1, &mut infct_info);
+                stat.infcts.extend(infct_info);
+                stat.apply_difference(&hd);
+                health_count.apply_difference(hd);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use math::Point;
+
+    use super::{super::Location, Agent, Households};
+    use crate::{
+        stat::{HealthCount, Stat},
+        world::commons::{FiniteTypePool, HealthType, ParamsForStep, RuntimeParams, WorldParams},
+    };
+
+    /// Returns the number of members infected in a day by the other member of each of
+    /// 1000 households of two, who is at `location()` and around its home if `at_home`.
+    fn secondary_cases(location: fn() -> Location, at_home: bool) -> (usize, Stat) {
+        let mut wp = WorldParams::new(
+            2000,
+            360,
+            18,
+            16,
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            None,
+            0.0.into(),
+            0.0,
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            0.0,
+            0.0,
+            0.0,
+            0.0.into(),
+        );
+        wp.hh_size = vec![0.0, 1.0];
+        let mut rp = RuntimeParams {
+            hh_infec: 20.0.into(),
+            ..Default::default()
+        };
+        let mut agents = (0..2000).map(|_| Agent::new()).collect::<Vec<_>>();
+        let mut households = Households::new();
+        households.assign(&mut agents, &mut Vec::<Point>::new(), &wp);
+        for household in &households.0 {
+            assert_eq!(household.len(), 2);
+            household[0]
+                .health
+                .write()
+                .infect(0.0, rp.variant_pool.get(0));
+            household[0].log.write().at_home = at_home;
+            *household[0].location.write() = location();
+            household[1].log.write().at_home = true;
+        }
+        let mut stat = Stat::default();
+        let mut health_count = HealthCount::default();
+        health_count[&HealthType::Susceptible] = 1000;
+        for step in 0..16 {
+            rp.step = step;
+            households.step(&mut stat, &mut health_count, &ParamsForStep::new(&wp, &rp));
+        }
+        let n = households
+            .0
+            .iter()
+            .filter(|h| h[1].health.read().get_infected().is_some())
+            .count();
+        (n, stat)
+    }
+
+    #[test]
+    fn test_secondary_attack_rate() {
+        // 20% a day
+        let (n, stat) = secondary_cases(|| Location::Field, true);
+        assert!((140..260).contains(&n), "{n}");
+        // the index cases are credited with the secondary ones
+        assert_eq!(stat.infcts.len(), n);
+
+        // nobody is infected by a member away from home
+        assert_eq!(secondary_cases(|| Location::Field, false).0, 0);
+        assert_eq!(secondary_cases(|| Location::Hospital, true).0, 0);
+        assert_eq!(secondary_cases(|| Location::Warp, true).0, 0);
+        let (n, _) = secondary_cases(|| Location::HomeQuarantine, false);
+        assert!((140..260).contains(&n), "{n}");
+    }
+}
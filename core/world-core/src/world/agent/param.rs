@@ -5,7 +5,11 @@ use super::{
 use crate::{
     stat::{HistInfo, HistgramType},
     util::random,
-    world::commons::{Vaccine, Variant},
+    world::{
+        commons::{Vaccine, Variant},
+        immunity,
+        infection::{exacerbation, Infector},
+    },
 };
 
 use std::f64;
@@ -13,8 +17,47 @@ use std::f64;
 const MAX_DAYS_FOR_RECOVERY: f64 = 7.0;
 const TOXICITY_LEVEL: f64 = 0.5;
 
-fn exacerbation(reproductivity: f64) -> f64 {
-    reproductivity.powf(1.0 / 3.0)
+/// Returns `true` if the infector at distance `d` infects an agent with the immune factor.
+pub fn check_infection(infector: &Infector, immunity: f64, d: f64, pfs: &ParamsForStep) -> bool {
+    // check contact and infection
+    let prob = pfs.wp.infec_model.contact_prob(infector, d, pfs);
+    prob > 0.0 && random::at_least_once_hit_in(pfs.wp.days_per_step(), prob * (1.0 - immunity))
+}
+
+/// what the immunity of an agent comes from
+#[derive(Debug, Clone, Copy)]
+pub enum ImmunitySource {
+    Naive,
+    /// index of the variant infected before
+    Infection(usize),
+    /// index of the vaccine
+    Vaccine(usize),
+}
+
+/// Immunity of an agent who can be infected, which gives its immune factor against each variant.
+#[derive(Debug, Clone, Copy)]
+pub struct Susceptibility {
+    pub immunity: f64,
+    pub source: ImmunitySource,
+    /// whether it has both been infected and vaccinated
+    pub hybrid: bool,
+}
+
+impl Susceptibility {
+    pub fn immune_factor(&self, variant: usize, pfs: &ParamsForStep) -> f64 {
+        let immune_factor = match self.source {
+            ImmunitySource::Naive => 0.0,
+            ImmunitySource::Infection(v) => {
+                self.immunity * pfs.rp.variant_pool.efficacy[v][variant]
+            }
+            ImmunitySource::Vaccine(v) => self.immunity * pfs.rp.vaccine_pool.efficacy[v][variant],
+        };
+        if self.hybrid {
+            immune_factor + (1.0 - immune_factor) * pfs.rp.imn_hybrid.r()
+        } else {
+            immune_factor
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -23,6 +66,17 @@ pub enum InfMode {
     Sym,
 }
 
+/// how an infected agent is cared for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Care {
+    /// not in the hospital
+    None,
+    /// in a hospital bed
+    Bed,
+    /// in the hospital waiting for a bed
+    Waiting,
+}
+
 #[derive(Debug)]
 pub struct InfectionParam {
     pub virus_variant: Variant,
@@ -45,49 +99,17 @@ impl InfectionParam {
         }
     }
 
-    pub fn check_infection(
-        &self,
-        immunity: f64,
-        d: f64,
-        days_to_onset: f64,
-        pfs: &ParamsForStep,
-    ) -> bool {
-        // check contact and infection
-        let virus_x = self.virus_variant.reproductivity;
-        let infec_d_max = pfs.rp.infec_dst * virus_x.sqrt();
-        if d > infec_d_max {
-            return false;
-        }
-
-        let exacerbate = exacerbation(virus_x);
-        let contag_delay = pfs.rp.contag_delay / exacerbate;
-        let contag_peak = pfs.rp.contag_peak / exacerbate;
-
-        if self.days_infected <= contag_delay {
-            return false;
-        }
-
-        let time_factor = 1f64.min(
-            (self.days_infected - contag_delay) / (contag_peak.min(days_to_onset) - contag_delay),
-        );
-        let distance_factor = 1f64.min(((infec_d_max - d) / 2.0).powf(2.0));
-        let infec_prob = if virus_x < 1.0 {
-            pfs.rp.infec.r() * virus_x
-        } else {
-            1.0 - (1.0 - pfs.rp.infec.r()) / virus_x
-        };
-
-        if !random::at_least_once_hit_in(
-            pfs.wp.days_per_step(),
-            infec_prob * time_factor * distance_factor * (1.0 - immunity),
-        ) {
-            return false;
+    pub fn infector(&self, days_to_onset: f64) -> Infector {
+        Infector {
+            days_infected: self.days_infected,
+            days_to_onset,
+            reproductivity: self.virus_variant.reproductivity,
         }
-        true
     }
 
-    pub(crate) fn step<const IS_IN_HOSPITAL: bool>(
+    pub(crate) fn step(
         &mut self,
+        care: Care,
         days_to: &mut DaysTo,
         inf_mode: &mut InfMode,
         vp: &Option<VaccinationParam>,
@@ -117,7 +139,7 @@ impl InfectionParam {
 
         let excrbt = exacerbation(self.virus_variant.reproductivity);
 
-        let days_to_recov = self.get_days_to_recov::<IS_IN_HOSPITAL>(days_to, pfs.rp);
+        let days_to_recov = self.get_days_to_recov(care, days_to, pfs.rp);
         if inf_mode == &InfMode::Asym {
             if self.days_infected < days_to.onset / excrbt {
                 if self.days_infected > days_to_recov {
@@ -138,6 +160,9 @@ impl InfectionParam {
             if self.severity > TOXICITY_LEVEL {
                 v *= self.virus_variant.toxicity;
             }
+            if care == Care::Waiting {
+                v *= 1.0 + pfs.rp.ovf_svr.r();
+            }
             v
         };
 
@@ -163,14 +188,12 @@ impl InfectionParam {
         None
     }
 
-    fn get_days_to_recov<const IS_IN_HOSPITAL: bool>(
-        &self,
-        days_to: &DaysTo,
-        rp: &RuntimeParams,
-    ) -> f64 {
+    fn get_days_to_recov(&self, care: Care, days_to: &DaysTo, rp: &RuntimeParams) -> f64 {
         let mut v = (1.0 - self.immunity) * days_to.recover;
-        if IS_IN_HOSPITAL {
-            v *= 1.0 - rp.therapy_effc.r()
+        match care {
+            Care::None => {}
+            Care::Bed => v *= 1.0 - rp.therapy_effc.r(),
+            Care::Waiting => v *= 1.0 + rp.ovf_rcv_dly.r(),
         }
         v
     }
@@ -181,6 +204,8 @@ pub struct RecoverParam {
     pub virus_variant: Variant,
     pub days_recovered: f64,
     pub immunity: f64,
+    /// immunity at the recovery
+    peak: f64,
 }
 
 impl RecoverParam {
@@ -189,6 +214,7 @@ impl RecoverParam {
             immunity,
             virus_variant,
             days_recovered: 0.0,
+            peak: immunity,
         }
     }
 
@@ -200,6 +226,15 @@ impl RecoverParam {
         pfs: &ParamsForStep,
     ) -> Option<HealthState> {
         self.days_recovered += pfs.wp.days_per_step();
+        if let Some(curve) = &pfs.rp.imn_wane {
+            let level = curve.level(self.days_recovered);
+            self.immunity = self.peak * level;
+            return if level < immunity::MIN_LEVEL {
+                Some(days_to.expire_immunity(activeness, age, pfs))
+            } else {
+                None
+            };
+        }
         if self.days_recovered > days_to.expire_immunity {
             Some(days_to.expire_immunity(activeness, age, pfs))
         } else {
@@ -213,6 +248,12 @@ pub struct VaccinationParam {
     pub vaccine: Vaccine,
     pub dose_date: f64,
     pub immunity: f64,
+    /// immunity when the last dose was given
+    pub base: f64,
+    /// number of doses received including boosters
+    pub n_doses: u32,
+    /// number of doses in the primary schedule of the first vaccine
+    pub n_primary_doses: u32,
     //[todo] days_vaccinated: f64,
     //[todo] first_dose_date: f64,
 }
@@ -238,7 +279,34 @@ impl VaccinationParam {
         pfs.rp.step as f64 * pfs.wp.days_per_step() - self.dose_date
     }
 
+    /// Returns the immunity ramped up from `base` to the efficacy of the last dose.
+    /// Boosters reach the efficacy of the last dose of the primary schedule.
+    fn scheduled_immunity(&self, pfs: &ParamsForStep) -> Option<f64> {
+        let doses = &self.vaccine.doses;
+        let target = doses[(self.n_doses as usize).clamp(1, doses.len()) - 1]
+            .efficacy
+            .r();
+        let days = self.days_vaccinated(pfs);
+        if days < pfs.wp.vcn_e_delay {
+            return Some(self.base + (target - self.base) * days / pfs.wp.vcn_e_delay);
+        }
+        let days = days - pfs.wp.vcn_e_delay;
+        if let Some(curve) = &pfs.rp.vcn_wane {
+            let level = curve.level(days);
+            (level >= immunity::MIN_LEVEL).then_some(target * level)
+        } else if days < pfs.wp.vcn_e_decay {
+            Some(target)
+        } else if days < pfs.wp.vcn_e_decay + pfs.wp.vcn_e_period {
+            Some(target * (1.0 - (days - pfs.wp.vcn_e_decay) / pfs.wp.vcn_e_period))
+        } else {
+            None
+        }
+    }
+
     fn new_immunity(&self, pfs: &ParamsForStep) -> Option<f64> {
+        if !self.vaccine.doses.is_empty() {
+            return self.scheduled_immunity(pfs);
+        }
         let days_vaccinated = self.days_vaccinated(pfs);
         let span = self.vaccine.interval();
         if days_vaccinated < span {
@@ -252,6 +320,9 @@ impl VaccinationParam {
                     + pfs.wp.vcn_1st_effc)
                     .r(),
             )
+        } else if let Some(curve) = &pfs.rp.vcn_wane {
+            let level = curve.level(days_vaccinated - pfs.wp.vcn_e_delay - span);
+            (level >= immunity::MIN_LEVEL).then(|| pfs.wp.vcn_max_effc.r() * level)
         } else if days_vaccinated < pfs.wp.vcn_e_delay + span + pfs.wp.vcn_e_decay {
             Some(pfs.wp.vcn_max_effc.r())
         } else if days_vaccinated < pfs.wp.vcn_e_decay + span + pfs.wp.vcn_e_period {
@@ -276,3 +347,32 @@ impl VaccinationParam {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Care, InfectionParam};
+    use crate::world::{
+        agent::DaysTo,
+        commons::{FiniteTypePool, RuntimeParams},
+    };
+
+    #[test]
+    fn test_days_to_recov() {
+        let rp = RuntimeParams {
+            therapy_effc: 50.0.into(),
+            ovf_rcv_dly: 50.0.into(),
+            ..Default::default()
+        };
+        let ip = InfectionParam::new(0.0, rp.variant_pool.get(0));
+        let days_to = DaysTo {
+            recover: 10.0,
+            onset: 0.0,
+            die: 0.0,
+            expire_immunity: 0.0,
+        };
+        assert_eq!(ip.get_days_to_recov(Care::None, &days_to, &rp), 10.0);
+        assert_eq!(ip.get_days_to_recov(Care::Bed, &days_to, &rp), 5.0);
+        // patients denied a bed recover later
+        assert_eq!(ip.get_days_to_recov(Care::Waiting, &days_to, &rp), 15.0);
+    }
+}
@@ -0,0 +1,88 @@
+use rayon::iter::ParallelIterator;
+
+use super::{param, Agent, Susceptibility};
+use crate::world::{commons::ParamsForStep, infection::Infector};
+
+/// Health states of the agents read by the interactions between them in a step,
+/// laid out in arrays indexed by agent ID so that the interactions take no lock.
+/// It is taken before the interactions, whose results are applied to the agents after all of them.
+///
+/// Only the states behind the lock of [`AgentHealth`](super::AgentHealth) are copied.
+/// Positions and velocities are owned by the cell of each agent and read without any lock,
+/// so they stay in the agents rather than in arrays of their own.
+#[derive(Default)]
+pub struct Snapshot {
+    /// infectors with the index of their variant
+    infectors: Vec<Option<(Infector, usize)>>,
+    susceptibilities: Vec<Option<Susceptibility>>,
+}
+
+impl Snapshot {
+    /// Takes the states of `agents`, sized by the largest ID among them.
+    pub fn take<'a, I>(&mut self, agents: I)
+    where
+        I: ParallelIterator<Item = &'a Agent>,
+    {
+        let states = agents
+            .map(|agent| {
+                let health = agent.health.read();
+                (agent.id, health.infector(), health.susceptibility())
+            })
+            .collect::<Vec<_>>();
+        let n = states.iter().map(|(id, _, _)| id + 1).max().unwrap_or(0);
+        self.infectors.clear();
+        self.infectors.resize(n, None);
+        self.susceptibilities.clear();
+        self.susceptibilities.resize(n, None);
+        for (id, infector, susceptibility) in states {
+            self.infectors[id] = infector;
+            self.susceptibilities[id] = susceptibility;
+        }
+    }
+
+    /// Returns the immune factor of agent `a` and the index of the variant
+    /// if agent `b` at distance `d` infects it.
+    pub fn infected_by(
+        &self,
+        a: usize,
+        b: usize,
+        d: f64,
+        pfs: &ParamsForStep,
+    ) -> Option<(f64, usize)> {
+        let (infector, variant) = self.infectors[b].as_ref()?;
+        let immunity = self.susceptibilities[a]?.immune_factor(*variant, pfs);
+        param::check_infection(infector, immunity, d, pfs).then_some((immunity, *variant))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::iter::IntoParallelRefIterator;
+
+    use super::Snapshot;
+    use crate::world::{
+        agent::Agent,
+        commons::{FiniteTypePool, RuntimeParams},
+    };
+
+    #[test]
+    fn test_take() {
+        let rp = RuntimeParams::default();
+        // IDs are not bound by the initial population
+        let agents = [3, 500]
+            .into_iter()
+            .map(|id| {
+                let mut agent = Agent::new();
+                agent.id = id;
+                agent
+            })
+            .collect::<Vec<_>>();
+        agents[1].health.write().infect(0.0, rp.variant_pool.get(0));
+        let mut snapshot = Snapshot::default();
+        snapshot.take(agents.par_iter());
+        assert_eq!(snapshot.infectors.len(), 501);
+        assert!(snapshot.infectors[500].is_some());
+        assert!(snapshot.susceptibilities[3].is_some());
+        assert!(snapshot.infectors[3].is_none());
+    }
+}
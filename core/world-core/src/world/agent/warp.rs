@@ -1,23 +1,34 @@
+use std::collections::BTreeMap;
+
+use math::Point;
+
 use super::{
     super::{
         commons::ParamsForStep,
-        testing::{TestQueue, Testee},
+        contact::TracedContacts,
+        testing::{TestQueue, TestReason, Testee},
     },
     cemetery::Cemetery,
     field::Field,
+    home_quarantine::HomeQuarantine,
     hospital::Hospital,
     Agent, Location, LocationLabel, WarpMode, WarpParam,
 };
-use crate::util::DrainWith;
+use crate::{
+    stat::{BorderEvent, Stat},
+    util::DrainWith,
+};
 
 #[derive(Default)]
 struct WarpStepInfo {
-    contacted_testees: Option<Vec<Testee>>,
+    traced: Option<TracedContacts>,
 }
 
 pub struct WarpAgent {
     agent: Agent,
     param: WarpParam,
+    /// the point where the warp started
+    from: Point,
 }
 
 impl LocationLabel for WarpAgent {
@@ -26,17 +37,20 @@ impl LocationLabel for WarpAgent {
 
 impl WarpAgent {
     fn new(agent: Agent, param: WarpParam) -> Self {
+        let from = agent.body.pt;
         Self {
             agent: Self::label(agent),
             param,
+            from,
         }
     }
 
     fn step(&mut self, pfs: &ParamsForStep) -> (WarpStepInfo, bool) {
         let mut wsi = WarpStepInfo::default();
         if let WarpMode::Inside = self.param.mode {
-            if let Some((w, testees)) = self.agent.check_quarantine(pfs) {
-                wsi.contacted_testees = Some(testees);
+            let pfs = &pfs.in_region(pfs.wp.region_at(&self.agent.body.pt));
+            if let Some((w, traced)) = self.agent.check_quarantine(pfs) {
+                wsi.traced = Some(traced);
                 self.param = w;
             }
         }
@@ -46,48 +60,239 @@ impl WarpAgent {
     }
 }
 
-pub struct Warps(Vec<WarpAgent>);
+pub struct Warps {
+    agents: Vec<WarpAgent>,
+    /// number of agents who have entered each region today
+    entries: BTreeMap<usize, u32>,
+}
 
 impl Warps {
     pub fn new(capacity: usize) -> Self {
-        Self(Vec::with_capacity(capacity))
+        Self {
+            agents: Vec::with_capacity(capacity),
+            entries: BTreeMap::new(),
+        }
     }
 
     pub fn clear(&mut self, agents: &mut Vec<Agent>) {
-        for wa in self.0.drain(..) {
+        for wa in self.agents.drain(..) {
             agents.push(wa.agent);
         }
+        self.entries.clear();
     }
 
     pub fn add(&mut self, agent: Agent, param: WarpParam) {
-        self.0.push(WarpAgent::new(agent, param));
+        self.agents.push(WarpAgent::new(agent, param));
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn step(
         &mut self,
         field: &mut Field,
         hospital: &mut Hospital,
+        home_quarantine: &mut HomeQuarantine,
         cemetery: &mut Cemetery,
         test_queue: &mut TestQueue,
+        stat: &mut Stat,
         pfs: &ParamsForStep,
     ) {
-        let tmp = self.0.drain_with_mut(|a| a.step(pfs));
+        if pfs.rp.local_step == 0 {
+            self.entries.clear();
+        }
+        let tmp = self.agents.drain_with_mut(|a| a.step(pfs));
         for (wsi, opt) in tmp.into_iter() {
-            if let Some(testees) = wsi.contacted_testees {
-                test_queue.extend(testees);
+            if let Some(traced) = wsi.traced {
+                stat.add_tracing_count(&traced.count);
+                test_queue.extend(traced.testees);
             }
             if let Some(wa) = opt {
                 let WarpAgent {
-                    agent,
+                    mut agent,
                     param: WarpParam { mode, goal },
+                    from,
                 } = wa;
                 match mode {
                     WarpMode::Back => field.add(agent, pfs.wp.into_grid_index(&goal)),
-                    WarpMode::Inside => field.add(agent, pfs.wp.into_grid_index(&goal)),
+                    WarpMode::Inside => {
+                        // under the control of the region entered from another one
+                        let control = pfs
+                            .wp
+                            .region_at(&goal)
+                            .filter(|to| pfs.wp.region_at(&from) != Some(*to))
+                            .and_then(|to| Some((to, pfs.rp.brd_ctrl.get(&to)?)));
+                        let Some((to, bc)) = control else {
+                            field.add(agent, pfs.wp.into_grid_index(&goal));
+                            continue;
+                        };
+                        let entries = self.entries.entry(to).or_default();
+                        if bc.quota.is_some_and(|q| *entries >= q) {
+                            stat.border_count[&BorderEvent::Refused] += 1;
+                            // turned back to where it left
+                            agent.body.pt = from;
+                            field.add(agent, pfs.wp.into_grid_index(&from));
+                            continue;
+                        }
+                        *entries += 1;
+                        stat.border_count[&BorderEvent::Entered] += 1;
+                        if bc.test && agent.testing.read().is_reservable(pfs) {
+                            agent.testing.write().reserve();
+                            test_queue.push(Testee::new(
+                                (&agent).into(),
+                                TestReason::OnArrival,
+                                pfs.rp.step,
+                            ));
+                            stat.border_count[&BorderEvent::Tested] += 1;
+                        }
+                        if bc.qrn_days > 0.0 {
+                            let until = pfs.rp.step + (bc.qrn_days * pfs.wp.steps_per_day()) as u32;
+                            stat.border_count[&BorderEvent::Quarantined] += 1;
+                            home_quarantine.add(agent, until);
+                        } else {
+                            field.add(agent, pfs.wp.into_grid_index(&goal));
+                        }
+                    }
                     WarpMode::Hospital(back_to) => hospital.add(agent, back_to),
+                    WarpMode::HomeQuarantine(until) => home_quarantine.add(agent, until),
                     WarpMode::Cemetery => cemetery.add(agent),
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use math::Point;
+
+    use super::{
+        super::{
+            cemetery::Cemetery, field::Field, home_quarantine::HomeQuarantine, hospital::Hospital,
+            Agent, WarpParam,
+        },
+        Warps,
+    };
+    use crate::{
+        stat::{BorderEvent, Stat},
+        world::{
+            commons::{BorderControl, ParamsForStep, RuntimeParams, WorldParams},
+            geometry::Region,
+            testing::TestQueue,
+        },
+    };
+
+    /// Agents travelling from the first region to the second one.
+    struct Travel {
+        wp: WorldParams,
+        rp: RuntimeParams,
+        warps: Warps,
+        field: Field,
+        hospital: Hospital,
+        home_quarantine: HomeQuarantine,
+        cemetery: Cemetery,
+        test_queue: TestQueue,
+        stat: Stat,
+    }
+
+    impl Travel {
+        const FROM: Point = Point { x: 99.0, y: 50.0 };
+        const GOAL: Point = Point { x: 131.0, y: 50.0 };
+
+        fn new(bc: BorderControl) -> Self {
+            let mut wp = WorldParams::new(
+                2,
+                360,
+                18,
+                16,
+                0.0.into(),
+                0.0.into(),
+                0.0.into(),
+                0.0.into(),
+                None,
+                0.0.into(),
+                0.0,
+                0.0.into(),
+                0.0.into(),
+                0.0.into(),
+                0.0.into(),
+                0.0.into(),
+                0.0,
+                0.0,
+                0.0,
+                0.0.into(),
+            );
+            wp.regions = (0..2)
+                .map(|i| Region {
+                    min: Point::new(i as f64 * 130.0, 0.0),
+                    max: Point::new(i as f64 * 130.0 + 100.0, 360.0),
+                    population: 1,
+                    infected: 0.0.into(),
+                })
+                .collect();
+            let mut rp = RuntimeParams::default();
+            rp.brd_ctrl.insert(1, bc);
+            let field = Field::new(&wp);
+            Self {
+                wp,
+                rp,
+                warps: Warps::new(4),
+                field,
+                hospital: Hospital::new(0),
+                home_quarantine: HomeQuarantine::new(4),
+                cemetery: Cemetery::new(0),
+                test_queue: TestQueue::new(),
+                stat: Stat::default(),
+            }
+        }
+
+        /// Lets `n` agents travel starting at `local_step` until all of them arrive.
+        fn travel(&mut self, n: usize, local_step: u32) {
+            for _ in 0..n {
+                let mut agent = Agent::new();
+                agent.body.pt = Self::FROM;
+                self.warps.add(agent, WarpParam::inside(Self::GOAL));
+            }
+            self.rp.local_step = local_step;
+            // 4.5 a step
+            for _ in 0..8 {
+                self.warps.step(
+                    &mut self.field,
+                    &mut self.hospital,
+                    &mut self.home_quarantine,
+                    &mut self.cemetery,
+                    &mut self.test_queue,
+                    &mut self.stat,
+                    &ParamsForStep::new(&self.wp, &self.rp),
+                );
+                self.rp.local_step += 1;
+            }
+            assert!(self.warps.agents.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_border_control() {
+        let mut t = Travel::new(BorderControl {
+            quota: Some(2),
+            test: true,
+            qrn_days: 1.0,
+            ..Default::default()
+        });
+        t.travel(3, 1);
+        assert_eq!(t.stat.border_count[&BorderEvent::Entered], 2);
+        assert_eq!(t.stat.border_count[&BorderEvent::Refused], 1);
+        assert_eq!(t.stat.border_count[&BorderEvent::Tested], 2);
+        assert_eq!(t.stat.border_count[&BorderEvent::Quarantined], 2);
+        assert_eq!(t.home_quarantine.len(), 2);
+        // the refused one stays where it left
+        let mut agents = Vec::new();
+        t.field.clear(&mut agents);
+        assert_eq!(agents.len(), 1);
+        assert_eq!(agents[0].body.pt, Travel::FROM);
+
+        // the quota is renewed every day
+        t.travel(1, 1);
+        assert_eq!(t.stat.border_count[&BorderEvent::Refused], 2);
+        t.travel(1, 0);
+        assert_eq!(t.stat.border_count[&BorderEvent::Entered], 3);
+    }
+}
@@ -1,12 +1,18 @@
 use std::{collections::BTreeMap, ops::Deref, sync::Arc};
 
+use super::{
+    density::DensityMap,
+    geometry::{FrictionZone, Region, Wall},
+    immunity::WaningCurve,
+    infection::{Contact, InfectionModel},
+};
 use crate::util::random::DistInfo;
 
 use enum_map::macros::Enum;
 use math::{Percentage, Permille, Point};
 use table::TableIndex;
 
-use rand::Rng;
+use rand::{seq::SliceRandom, Rng};
 
 #[derive(Debug, Default)]
 pub struct RuntimeParams {
@@ -38,6 +44,12 @@ pub struct RuntimeParams {
     pub imn_max_dur_sv: Percentage,
     pub imn_max_effc: Percentage,
     pub imn_max_effc_sv: Percentage,
+    /// Waning of infection-derived immunity, which is constant until it expires if `None`
+    pub imn_wane: Option<WaningCurve>,
+    /// Waning of vaccine-derived immunity after the full effect, which decays linearly if `None`
+    pub vcn_wane: Option<WaningCurve>,
+    /// Reduction of the remaining susceptibility of agents both infected and vaccinated
+    pub imn_hybrid: Percentage,
 
     /// Distancing strength
     pub dst_st: f64,
@@ -52,6 +64,10 @@ pub struct RuntimeParams {
     pub gat_fr: f64,
     /// Gathering's random spot rate (%)
     pub gat_rnd_rt: Percentage,
+    /// Fixed gathering spots
+    pub gat_spots: Vec<GatheringSpot>,
+    /// Scheduled gatherings
+    pub gat_events: Vec<GatheringEvent>,
     /// gathering's size
     pub gat_sz: DistInfo<f64>,
     /// gathering's duration
@@ -79,8 +95,22 @@ pub struct RuntimeParams {
     pub tst_capa: Permille,
     /// Test delay limit (days)
     pub tst_dly_lim: f64,
-    //[todo] pub trc_ope: TracingOperation, // How to treat the contacts, tests or vaccination, or both
-    //[todo] pub trc_vcn_type: u32, // vaccine type for tracing vaccination
+    /// How to treat the contacts, tests, vaccination or quarantine
+    pub trc_ope: TracingOperation,
+    /// vaccine type for tracing vaccination
+    pub trc_vcn_type: usize,
+    /// Quarantine duration of traced contacts (days)
+    pub trc_qrn_dur: f64,
+    /// Rate of positive agents isolated at home instead of hospitalized
+    pub hm_qrn_rt: Percentage,
+    /// Home quarantine duration of positive agents (days)
+    pub hm_qrn_dur: f64,
+    /// Hospital capacity (beds per 1000 population), unlimited if zero
+    pub hsp_capa: Permille,
+    /// Increase of severity progression for patients denied a bed
+    pub ovf_svr: Percentage,
+    /// Increase of days to recovery for patients denied a bed
+    pub ovf_rcv_dly: Percentage,
     pub step: u32,
     pub local_step: u32,
     pub days_elapsed: u32,
@@ -90,10 +120,106 @@ pub struct RuntimeParams {
     pub variant_pool: VariantPool,
     pub vaccine_pool: VaccinePool,
     pub vx_stg: BTreeMap<usize, VaccinationStrategy>,
+    /// Minimum days since the last dose to be eligible for a booster
+    pub vcn_bst_days: f64,
+    /// Probabilities per day that an agent in a region travels to each of the other regions
+    pub rgn_od: Vec<Vec<f64>>,
+    /// Controls on entering each region from another one
+    pub brd_ctrl: BTreeMap<usize, BorderControl>,
+    /// Parameters of each region replacing those of the world for the agents in it
+    pub rgn_prm: BTreeMap<usize, RegionParams>,
+    /// Probability per day that an infected household member infects each of the others
+    pub hh_infec: Percentage,
+    /// Whether household members of a positive agent are quarantined for
+    /// [`trc_qrn_dur`](Self::trc_qrn_dur)
+    pub hh_qrn: bool,
+}
+
+bitflags::bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct TracingOperation: u8 {
+        const TEST = 1;
+        const VACCINATE = 1 << 1;
+        const QUARANTINE = 1 << 2;
+    }
+}
+
+impl Default for TracingOperation {
+    /// contacts are only tested as in the original SimEpidemic
+    fn default() -> Self {
+        Self::TEST
+    }
+}
+
+/// A fixed spot such as a school, a mall or a stadium where gatherings take place.
+#[derive(Debug, Clone)]
+pub struct GatheringSpot {
+    pub p: Point,
+    /// relative frequency of gatherings among the open spots
+    pub weight: f64,
+    pub open: bool,
+}
+
+impl GatheringSpot {
+    pub fn new(p: Point, weight: f64) -> Self {
+        Self {
+            p,
+            weight,
+            open: true,
+        }
+    }
+}
+
+/// A mass gathering scheduled at a fixed place and time such as a concert.
+#[derive(Debug, Clone)]
+pub struct GatheringEvent {
+    pub p: Point,
+    pub size: f64,
+    /// start time in days
+    pub start: f64,
+    /// duration in hours
+    pub duration: f64,
+    pub strength: f64,
+    /// probability of each agent around to take part in
+    pub participation: Percentage,
+    /// a cancelled event does not start or ends immediately
+    pub cancelled: bool,
+}
+
+/// Controls on agents entering a region from another one, and cases imported from the outside.
+#[derive(Debug, Clone, Default)]
+pub struct BorderControl {
+    /// maximum number of entries per day, which is unlimited if `None`
+    pub quota: Option<u32>,
+    /// whether entrants are tested on arrival
+    pub test: bool,
+    /// days of quarantine at the arrival point
+    pub qrn_days: f64,
+    /// number of susceptible agents in the region infected from the outside per day
+    pub imp_rate: f64,
+    /// index of the variant of imported cases
+    pub imp_variant: usize,
+}
+
+/// Runtime parameters of a region, each of which replaces that of the world if set.
+#[derive(Debug, Default)]
+pub struct RegionParams {
+    pub mob_freq: Option<DistInfo<Permille>>,
+    pub mob_dist: Option<DistInfo<Percentage>>,
+    pub back_hm_rt: Option<Percentage>,
+    /// Gathering's frequency, which thins out the gatherings held in the region
+    pub gat_fr: Option<f64>,
+    pub gat_freq: Option<DistInfo<Percentage>>,
+    pub dst_st: Option<f64>,
+    pub tst_sbj_asy: Option<Percentage>,
+    pub tst_sbj_sym: Option<Percentage>,
+    pub hm_qrn_rt: Option<Percentage>,
 }
 
 #[derive(Debug)]
 pub struct VaccinationStrategy {
+    /// index of the vaccine
+    pub vaccine: usize,
     pub perform_rate: Permille,
     pub regularity: Percentage,
     pub priority: VaccinePriority,
@@ -130,6 +256,11 @@ pub enum HealthType {
     Vaccinated,
 }
 
+/// preferred number of agents in a cell of the mesh
+pub const AGENTS_PER_CELL: f64 = 16.0;
+/// preferred number of cells for each thread
+pub const CELLS_PER_THREAD: f64 = 4.0;
+
 #[derive(Clone, Debug)]
 pub struct WorldParams {
     pub init_n_pop: u32,
@@ -141,9 +272,30 @@ pub struct WorldParams {
     pub q_asymptomatic: Percentage,
     pub q_symptomatic: Percentage,
     pub wrk_plc_mode: Option<WorkPlaceMode>,
-    //[todo] pub av_clstr_rate: Percentage, // Anti-Vax
-    //[todo] pub av_clstr_gran: Percentage, // Anti-Vax
-    //[todo] pub av_test_rate: Percentage, // Anti-Vax
+    /// Rate of agents commuting between home and workplace
+    pub cmt_rt: Percentage,
+    /// Number of workplace clusters in each region, or in the field without regions
+    pub wrk_plc_n: u32,
+    /// Radius of a workplace cluster or a home (% of field size)
+    pub wrk_plc_rad: Percentage,
+    /// Hours of a day to go to and to leave a workplace, over midnight if the former is later
+    pub wrk_hours: (f64, f64),
+    /// Barriers inside the field
+    pub walls: Vec<Wall>,
+    /// Areas with their own friction instead of [`RuntimeParams::friction`]
+    pub frc_zones: Vec<FrictionZone>,
+    /// Sub-populations, which are separated from each other by [`walls`](Self::walls)
+    pub regions: Vec<Region>,
+    /// Relative frequencies of households of one, two, ... members, no household if empty
+    pub hh_size: Vec<f64>,
+    /// Hypothesis of transmission
+    pub infec_model: Arc<dyn InfectionModel>,
+    /// Rate of vaccine refusers (Anti-Vax)
+    pub av_clstr_rate: Percentage,
+    /// Side length of a cluster of vaccine refusers (% of field size), not clustered if zero
+    pub av_clstr_gran: Percentage,
+    /// Testing propensity of vaccine refusers relative to the others
+    pub av_test_rate: Percentage,
     pub rcv_bias: Percentage,
     pub rcv_temp: f64,
     pub rcv_upper: Percentage,
@@ -158,7 +310,10 @@ pub struct WorldParams {
     pub vcn_sv_effc: Percentage,
     _init_n_pop: f64,
     _field_size: f64,
+    _field_height: f64,
     _mesh: f64,
+    _mesh_rows: usize,
+    _view_range: f64,
     _steps_per_day: f64,
     _days_per_step: f64,
     _res_rate: f64,
@@ -200,6 +355,18 @@ impl WorldParams {
             q_symptomatic,
             steps_per_day,
             wrk_plc_mode,
+            cmt_rt: Percentage::new(0.0),
+            wrk_plc_n: 10,
+            wrk_plc_rad: Percentage::new(5.0),
+            wrk_hours: (9.0, 17.0),
+            walls: Vec::new(),
+            frc_zones: Vec::new(),
+            regions: Vec::new(),
+            hh_size: Vec::new(),
+            infec_model: Arc::new(Contact),
+            av_clstr_rate: Percentage::new(0.0),
+            av_clstr_gran: Percentage::new(10.0),
+            av_test_rate: Percentage::new(100.0),
             vcn_effc_symp,
             vcn_sv_effc,
             vcn_e_delay,
@@ -209,7 +376,10 @@ impl WorldParams {
             vcn_e_decay,
             _init_n_pop: init_n_pop as f64,
             _field_size,
+            _field_height: _field_size,
             _mesh,
+            _mesh_rows: mesh,
+            _view_range: _field_size / _mesh,
             _steps_per_day,
             _days_per_step: 1.0 / _steps_per_day,
             _res_rate: _mesh / _field_size,
@@ -239,6 +409,63 @@ impl WorldParams {
         self._field_size
     }
 
+    /// Makes the field a rectangle of `field_size` by `field_height`
+    /// divided into square cells of the same size.
+    pub fn set_field_height(&mut self, field_height: usize) {
+        self._field_height = field_height as f64;
+        self._mesh_rows = ((self._field_height * self._res_rate).ceil() as usize).max(1);
+    }
+
+    /// Divides the width of the field into `mesh` cells, keeping the view range.
+    pub fn set_mesh(&mut self, mesh: usize) {
+        self.mesh = mesh;
+        self._mesh = mesh as f64;
+        self._res_rate = self._mesh / self._field_size;
+        self._mesh_rows = ((self._field_height * self._res_rate).ceil() as usize).max(1);
+    }
+
+    /// Returns the number of cells along the width of the field which puts about
+    /// [`AGENTS_PER_CELL`] agents into a cell and [`CELLS_PER_THREAD`] cells for each of `threads`,
+    /// without making cells narrower than a unit.
+    pub fn auto_mesh(&self, threads: usize) -> usize {
+        let n_cells = (self._init_n_pop / AGENTS_PER_CELL).max(threads as f64 * CELLS_PER_THREAD);
+        let mesh = (n_cells * self._field_size / self._field_height)
+            .sqrt()
+            .ceil();
+        mesh.min(self._field_size.floor()).max(1.0) as usize
+    }
+
+    /// Returns the side length of a cell.
+    #[inline]
+    pub fn cell_size(&self) -> f64 {
+        self._field_size / self._mesh
+    }
+
+    /// Returns the height of the field, which is [`field_size`](Self::field_size) unless set.
+    #[inline]
+    pub fn field_height(&self) -> f64 {
+        self._field_height
+    }
+
+    /// Returns the number of rows of cells, which is [`mesh`](Self::mesh) unless the height is set.
+    #[inline]
+    pub fn mesh_rows(&self) -> usize {
+        self._mesh_rows
+    }
+
+    /// Returns `true` if the segment from `p` to `q` crosses any wall.
+    pub fn is_walled(&self, p: &Point, q: &Point) -> bool {
+        self.walls.iter().any(|w| w.blocks(p, q))
+    }
+
+    /// Returns the friction at `p`.
+    pub fn friction_at(&self, p: &Point, rp: &RuntimeParams) -> Percentage {
+        self.frc_zones
+            .iter()
+            .find(|z| z.contains(p))
+            .map_or(rp.friction, |z| z.friction)
+    }
+
     #[inline]
     pub fn mesh(&self) -> f64 {
         self._mesh
@@ -252,23 +479,91 @@ impl WorldParams {
     #[inline]
     pub fn into_grid_index(&self, p: &Point) -> TableIndex {
         TableIndex::new(
-            quantize(p.y, self.res_rate(), self.mesh),
+            quantize(p.y, self.res_rate(), self._mesh_rows),
             quantize(p.x, self.res_rate(), self.mesh),
         )
     }
 
+    /// Returns the range agents interact in, which is the initial cell size.
+    /// Agents meet each other in as many rings of cells around theirs as the range reaches.
     #[inline]
     pub fn view_range(&self) -> f64 {
-        self._field_size / self._mesh
+        self._view_range
     }
 
     pub fn random_point(&self) -> Point {
         Point::new(
             rand::thread_rng().gen::<f64>() * self.field_size(),
-            rand::thread_rng().gen::<f64>() * self.field_size(),
+            rand::thread_rng().gen::<f64>() * self.field_height(),
         )
     }
 
+    /// Returns a random point distributed according to [`wrk_plc_mode`](Self::wrk_plc_mode)
+    /// in a region chosen in proportion to the population, or in the field if no region.
+    pub fn random_place(&self) -> Point {
+        let region = self
+            .regions
+            .iter()
+            .enumerate()
+            .collect::<Vec<_>>()
+            .choose_weighted(&mut rand::thread_rng(), |(_, r)| r.population)
+            .ok()
+            .map(|(i, _)| *i);
+        self.random_place_in(region)
+    }
+
+    /// Returns a random point distributed according to [`wrk_plc_mode`](Self::wrk_plc_mode)
+    /// in the region, or in the field if [`None`].
+    pub fn random_place_in(&self, region: Option<usize>) -> Point {
+        let p = match &self.wrk_plc_mode {
+            None | Some(WorkPlaceMode::Uniform) => self.random_point(),
+            Some(WorkPlaceMode::Centered) => self.centered_point(),
+            Some(WorkPlaceMode::PopDistImg(map)) => {
+                map.sample(self.field_size(), self.field_height())
+            }
+        };
+        match region {
+            None => p,
+            Some(i) => self.regions[i].point_at(&Point::new(
+                p.x / self.field_size(),
+                p.y / self.field_height(),
+            )),
+        }
+    }
+
+    /// Returns the index of the region containing `p`.
+    pub fn region_at(&self, p: &Point) -> Option<usize> {
+        self.regions.iter().position(|r| r.contains(p))
+    }
+
+    /// Returns the numbers of agents, initially infected and recovered in each region,
+    /// or in the field if no region.
+    pub fn initial_groups(&self) -> Vec<(usize, usize, usize)> {
+        let group = |n_pop: usize, infected: Percentage| {
+            let n_infected = ((n_pop as f64 * infected.r()) as usize).min(n_pop);
+            let n_recovered =
+                ((n_pop as f64 * self.recovered.r()) as usize).min(n_pop - n_infected);
+            (n_pop, n_infected, n_recovered)
+        };
+        if self.regions.is_empty() {
+            vec![group(self.init_n_pop as usize, self.infected)]
+        } else {
+            self.regions
+                .iter()
+                .map(|r| group(r.population as usize, r.infected))
+                .collect()
+        }
+    }
+
+    /// Returns the local steps of a day to go to and to leave a workplace,
+    /// which are the same for work around the clock from 0 to 24 o'clock.
+    pub fn work_steps(&self) -> (u32, u32) {
+        let (go, leave) = self.wrk_hours;
+        let f =
+            |h: f64| ((h / 24.0).clamp(0.0, 1.0) * self._steps_per_day) as u32 % self.steps_per_day;
+        (f(go), f(leave))
+    }
+
     pub fn centered_point(&self) -> Point {
         let mut p = Point::new(
             rand::thread_rng().gen::<f64>(),
@@ -276,8 +571,8 @@ impl WorldParams {
         );
         p.apply_mut(|c| *c = *c * 2.0 - 1.0);
         let v = p.centered_bias();
-        p.apply_mut(|c| *c = (*c * v + 1.0) * 0.5 * self.field_size());
-        p
+        p.apply_mut(|c| *c = (*c * v + 1.0) * 0.5);
+        Point::new(p.x * self.field_size(), p.y * self.field_height())
     }
 }
 
@@ -299,7 +594,13 @@ impl CenteredBias for Point {
 pub enum WorkPlaceMode {
     Uniform,
     Centered,
-    //[todo] PopDistImg,
+    /// distributed according to a population density map
+    PopDistImg(Arc<DensityMap>),
+}
+
+pub enum Commute {
+    ToWork,
+    ToHome,
 }
 
 #[derive(Debug)]
@@ -386,20 +687,49 @@ impl FiniteTypePool for VariantPool {
     }
 }
 
+/// A dose in the primary schedule of a vaccine.
+#[derive(Debug, Clone)]
+pub struct Dose {
+    /// days after the previous dose
+    pub interval: f64,
+    /// efficacy reached by the dose
+    pub efficacy: Percentage,
+}
+
 #[derive(Debug)]
 pub struct VaccineInfo {
     pub interval: usize,
-    // pub efficacy: Vec<f64>,
+    /// primary schedule; two doses `interval` days apart with the efficacies of `WorldParams` if empty
+    pub doses: Vec<Dose>,
 }
 
 impl VaccineInfo {
     pub fn new(interval: usize) -> Self {
-        Self { interval }
+        Self {
+            interval,
+            doses: Vec::new(),
+        }
+    }
+
+    pub fn with_doses(doses: Vec<Dose>) -> Self {
+        Self {
+            interval: doses.get(1).map_or(0, |d| d.interval as usize),
+            doses,
+        }
     }
 
     pub fn interval(&self) -> f64 {
         self.interval as f64
     }
+
+    /// Returns the number of doses to complete the primary schedule.
+    pub fn n_primary_doses(&self) -> usize {
+        if self.doses.is_empty() {
+            2
+        } else {
+            self.doses.len()
+        }
+    }
 }
 
 pub type Vaccine = FiniteType<VaccineInfo>;
@@ -426,6 +756,14 @@ impl VaccinePool {
             efficacy: Vec::from(efficacy.map(Vec::from)),
         }
     }
+
+    /// `efficacy[i][j]` is the efficacy of the `i`-th vaccine against the `j`-th variant.
+    pub fn from_vec(pool: Vec<VaccineInfo>, efficacy: Vec<Vec<f64>>) -> Self {
+        Self {
+            pool: pool.into_iter().map(Arc::new).collect(),
+            efficacy,
+        }
+    }
 }
 
 impl FiniteTypePool for VaccinePool {
@@ -439,11 +777,75 @@ impl FiniteTypePool for VaccinePool {
 pub struct ParamsForStep<'a> {
     pub wp: &'a WorldParams,
     pub rp: &'a RuntimeParams,
+    /// parameters of the region the agent is in
+    region: Option<&'a RegionParams>,
 }
 
 impl<'a> ParamsForStep<'a> {
     pub fn new(wp: &'a WorldParams, rp: &'a RuntimeParams) -> Self {
-        ParamsForStep { rp, wp }
+        ParamsForStep {
+            rp,
+            wp,
+            region: None,
+        }
+    }
+
+    /// Returns the parameters for agents in the region, or those of the world if [`None`].
+    pub fn in_region(&self, region: Option<usize>) -> Self {
+        ParamsForStep {
+            region: region.and_then(|i| self.rp.rgn_prm.get(&i)),
+            ..*self
+        }
+    }
+
+    pub fn mob_freq(&self) -> &'a DistInfo<Permille> {
+        self.region
+            .and_then(|r| r.mob_freq.as_ref())
+            .unwrap_or(&self.rp.mob_freq)
+    }
+
+    pub fn mob_dist(&self) -> &'a DistInfo<Percentage> {
+        self.region
+            .and_then(|r| r.mob_dist.as_ref())
+            .unwrap_or(&self.rp.mob_dist)
+    }
+
+    pub fn back_hm_rt(&self) -> Percentage {
+        self.region
+            .and_then(|r| r.back_hm_rt)
+            .unwrap_or(self.rp.back_hm_rt)
+    }
+
+    pub fn gat_fr(&self) -> f64 {
+        self.region.and_then(|r| r.gat_fr).unwrap_or(self.rp.gat_fr)
+    }
+
+    pub fn gat_freq(&self) -> &'a DistInfo<Percentage> {
+        self.region
+            .and_then(|r| r.gat_freq.as_ref())
+            .unwrap_or(&self.rp.gat_freq)
+    }
+
+    pub fn dst_st(&self) -> f64 {
+        self.region.and_then(|r| r.dst_st).unwrap_or(self.rp.dst_st)
+    }
+
+    pub fn tst_sbj_asy(&self) -> Percentage {
+        self.region
+            .and_then(|r| r.tst_sbj_asy)
+            .unwrap_or(self.rp.tst_sbj_asy)
+    }
+
+    pub fn tst_sbj_sym(&self) -> Percentage {
+        self.region
+            .and_then(|r| r.tst_sbj_sym)
+            .unwrap_or(self.rp.tst_sbj_sym)
+    }
+
+    pub fn hm_qrn_rt(&self) -> Percentage {
+        self.region
+            .and_then(|r| r.hm_qrn_rt)
+            .unwrap_or(self.rp.hm_qrn_rt)
     }
 
     #[inline]
@@ -451,6 +853,35 @@ impl<'a> ParamsForStep<'a> {
         self.wp.wrk_plc_mode.is_some() && Self::is_daytime(self.wp, self.rp)
     }
 
+    /// Returns `true` while commuters should stay at their workplace,
+    /// which lasts over midnight if they go to work later than they leave
+    /// and all day if they go and leave at the same step.
+    pub fn is_work_time(&self) -> bool {
+        let (go, leave) = self.wp.work_steps();
+        let s = self.rp.step % self.wp.steps_per_day;
+        if go == leave {
+            true
+        } else if go < leave {
+            go <= s && s < leave
+        } else {
+            go <= s || s < leave
+        }
+    }
+
+    /// Returns the direction to commute if it is time to do so,
+    /// which is never home for work around the clock.
+    pub fn commute(&self) -> Option<Commute> {
+        let (go, leave) = self.wp.work_steps();
+        let s = self.rp.step % self.wp.steps_per_day;
+        if s == go {
+            Some(Commute::ToWork)
+        } else if s == leave {
+            Some(Commute::ToHome)
+        } else {
+            None
+        }
+    }
+
     fn is_daytime(wp: &WorldParams, rp: &RuntimeParams) -> bool {
         if wp.steps_per_day < 3 {
             rp.step % 2 == 0
@@ -472,3 +903,90 @@ pub fn quantize(p: f64, res_rate: f64, n: usize) -> usize {
 pub fn dequantize(i: usize, res_rate: f64) -> f64 {
     (i as f64) / res_rate
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Commute, ParamsForStep, RuntimeParams, WorldParams};
+
+    /// Returns the parameters of a square field of 360 divided into 18 cells with 16 steps a day.
+    fn world_params(init_n_pop: u32) -> WorldParams {
+        WorldParams::new(
+            init_n_pop,
+            360,
+            18,
+            16,
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            None,
+            0.0.into(),
+            0.0,
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            0.0.into(),
+            0.0,
+            0.0,
+            0.0,
+            0.0.into(),
+        )
+    }
+
+    /// Returns the steps of a day at work and those to go to and to leave work.
+    fn work_schedule(wrk_hours: (f64, f64)) -> (Vec<u32>, Option<u32>, Option<u32>) {
+        let mut wp = world_params(1);
+        wp.wrk_hours = wrk_hours;
+        let mut rp = RuntimeParams::default();
+        let (mut at_work, mut go, mut leave) = (Vec::new(), None, None);
+        for step in 16..32 {
+            rp.step = step;
+            let pfs = ParamsForStep::new(&wp, &rp);
+            let s = step % 16;
+            if pfs.is_work_time() {
+                at_work.push(s);
+            }
+            match pfs.commute() {
+                Some(Commute::ToWork) => go = Some(s),
+                Some(Commute::ToHome) => leave = Some(s),
+                None => {}
+            }
+        }
+        (at_work, go, leave)
+    }
+
+    #[test]
+    fn test_work_time() {
+        // 16 steps a day
+        assert_eq!(
+            work_schedule((9.0, 17.0)),
+            (vec![6, 7, 8, 9, 10], Some(6), Some(11))
+        );
+        assert_eq!(
+            work_schedule((22.0, 6.0)),
+            (vec![0, 1, 2, 3, 14, 15], Some(14), Some(4))
+        );
+        assert_eq!(
+            work_schedule((9.0, 24.0)),
+            ((6..16).collect(), Some(6), Some(0))
+        );
+        assert_eq!(
+            work_schedule((0.0, 24.0)),
+            ((0..16).collect(), Some(0), None)
+        );
+    }
+
+    #[test]
+    fn test_mesh() {
+        // 6,250 cells of 16 agents
+        let mut wp = world_params(100_000);
+        assert_eq!(wp.auto_mesh(1), 80);
+        wp.set_mesh(wp.auto_mesh(1));
+        assert_eq!(wp.cell_size(), 4.5);
+        // cells of 20 at first
+        assert_eq!(wp.view_range(), 20.0);
+        // cells of a unit at the finest
+        assert_eq!(world_params(10_000_000).auto_mesh(1), 360);
+    }
+}
@@ -1,16 +1,26 @@
 use super::{
     agent::AgentRef,
-    commons::ParamsForStep,
+    commons::{FiniteTypePool, ParamsForStep, TracingOperation},
     testing::{TestReason, Testee},
 };
+use crate::stat::TracingType;
 
 use std::collections::VecDeque;
 
+use enum_map::EnumMap;
+
 struct ContactInfo {
     agent: AgentRef,
     time_stamp: u32,
 }
 
+/// contacts picked up by tracing and what has been done for them
+#[derive(Default)]
+pub struct TracedContacts {
+    pub testees: Vec<Testee>,
+    pub count: EnumMap<TracingType, u32>,
+}
+
 /// a vector guarantees the ascending order of `time_stamp`
 #[derive(Default)]
 pub struct Contacts(VecDeque<ContactInfo>);
@@ -27,24 +37,43 @@ impl Contacts {
         }
     }
 
-    pub fn drain_testees(&mut self, pfs: &ParamsForStep) -> Vec<Testee> {
+    pub fn drain_traced(&mut self, pfs: &ParamsForStep) -> TracedContacts {
         let retention_steps = pfs.wp.steps_per_day * Self::RETENTION_PERIOD;
-        self.0
-            .drain(..)
-            .filter_map(|ci| {
-                if pfs.rp.step - ci.time_stamp >= retention_steps {
-                    return None;
-                }
-                // let a = ci.agent.read();
-                // drop(a);
-                if !ci.agent.testing.read().is_reservable(pfs)
-                    || !ci.agent.location.read().in_field()
-                {
-                    return None;
-                }
+        let trc_ope = pfs.rp.trc_ope;
+        let mut traced = TracedContacts::default();
+        for ci in self.0.drain(..) {
+            if pfs.rp.step - ci.time_stamp >= retention_steps {
+                continue;
+            }
+            if !ci.agent.location.read().in_field() {
+                continue;
+            }
+            if trc_ope.contains(TracingOperation::VACCINATE)
+                && ci.agent.try_give_vaccine_ticket(
+                    pfs.rp.vaccine_pool.get(pfs.rp.trc_vcn_type),
+                    false,
+                    pfs,
+                )
+            {
+                traced.count[&TracingType::Vaccinated] += 1;
+            }
+            if trc_ope.contains(TracingOperation::QUARANTINE)
+                && ci.agent.testing.write().order_quarantine()
+            {
+                traced.count[&TracingType::Quarantined] += 1;
+                // quarantined contacts are not tested additionally
+                continue;
+            }
+            if trc_ope.contains(TracingOperation::TEST)
+                && ci.agent.testing.read().is_reservable(pfs)
+            {
                 ci.agent.testing.write().reserve();
-                Some(Testee::new(ci.agent, TestReason::AsContact, pfs.rp.step))
-            })
-            .collect()
+                traced.count[&TracingType::Tested] += 1;
+                traced
+                    .testees
+                    .push(Testee::new(ci.agent, TestReason::AsContact, pfs.rp.step));
+            }
+        }
+        traced
     }
 }
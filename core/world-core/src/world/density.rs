@@ -0,0 +1,162 @@
+use std::io::Cursor;
+
+use anyhow::{bail, ensure};
+use math::Point;
+use rand::Rng;
+
+/// A grid of relative population densities over the field.
+/// The first row corresponds to the cells of the smallest y.
+#[derive(Debug)]
+pub struct DensityMap {
+    width: usize,
+    height: usize,
+    values: Vec<f64>,
+    /// cumulative sums of `values` in row-major order
+    cumulative: Vec<f64>,
+}
+
+impl DensityMap {
+    pub fn new(width: usize, height: usize, values: Vec<f64>) -> anyhow::Result<Self> {
+        ensure!(width > 0 && height > 0, "density map is empty");
+        ensure!(
+            values.len() == width * height,
+            "density map has {} values for {width}x{height} cells",
+            values.len()
+        );
+        ensure!(
+            values.iter().all(|v| v.is_finite() && *v >= 0.0),
+            "density must be a non-negative number"
+        );
+        let cumulative = values
+            .iter()
+            .scan(0.0, |acc, v| {
+                *acc += v;
+                Some(*acc)
+            })
+            .collect::<Vec<_>>();
+        ensure!(
+            *cumulative.last().unwrap() > 0.0,
+            "density map has no populated cell"
+        );
+        Ok(Self {
+            width,
+            height,
+            values,
+            cumulative,
+        })
+    }
+
+    /// Reads comma separated densities, one line per row.
+    pub fn from_csv(src: &str) -> anyhow::Result<Self> {
+        let mut width = None;
+        let mut values = Vec::new();
+        let mut height = 0;
+        for (i, line) in src.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let row = line
+                .split(',')
+                .map(|v| v.trim().parse::<f64>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| anyhow::anyhow!("line {}: {e}", i + 1))?;
+            match width {
+                None => width = Some(row.len()),
+                Some(w) if w != row.len() => {
+                    bail!("line {}: expected {w} columns, found {}", i + 1, row.len())
+                }
+                _ => {}
+            }
+            values.extend(row);
+            height += 1;
+        }
+        Self::new(width.unwrap_or(0), height, values)
+    }
+
+    /// Reads a PNG image whose brighter pixels mean denser population.
+    /// Colors are converted to gray levels and the alpha channel is ignored.
+    pub fn from_png(bytes: &[u8]) -> anyhow::Result<Self> {
+        let mut decoder = png::Decoder::new(Cursor::new(bytes));
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf)?;
+        let (width, height) = (info.width as usize, info.height as usize);
+        let n_ch = info.color_type.samples();
+        let values = buf[..info.buffer_size()]
+            .chunks_exact(n_ch)
+            .map(|px| {
+                let gray = match n_ch {
+                    1 | 2 => px[0] as f64,
+                    _ => (px[0] as f64 + px[1] as f64 + px[2] as f64) / 3.0,
+                };
+                gray / 255.0
+            })
+            .collect::<Vec<_>>();
+        // image rows go from top to bottom
+        let values = values
+            .chunks_exact(width)
+            .rev()
+            .flatten()
+            .copied()
+            .collect();
+        Self::new(width, height, values)
+    }
+
+    /// Returns a random point in the field with the probability proportional to the density.
+    pub fn sample(&self, width: f64, height: f64) -> Point {
+        let rng = &mut rand::thread_rng();
+        let total = *self.cumulative.last().unwrap();
+        let r = rng.gen::<f64>() * total;
+        let i = self
+            .cumulative
+            .partition_point(|c| *c <= r)
+            .min(self.values.len() - 1);
+        let (row, col) = (i / self.width, i % self.width);
+        Point::new(
+            (col as f64 + rng.gen::<f64>()) / self.width as f64 * width,
+            (row as f64 + rng.gen::<f64>()) / self.height as f64 * height,
+        )
+    }
+
+    /// Returns the density of the cell containing `p`.
+    pub fn density_at(&self, p: &Point, width: f64, height: f64) -> f64 {
+        let f = |v: f64, size: f64, n: usize| ((v / size * n as f64) as usize).min(n - 1);
+        self.values[f(p.y, height, self.height) * self.width + f(p.x, width, self.width)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DensityMap;
+
+    #[test]
+    fn test_csv() {
+        let map = DensityMap::from_csv("0, 1\n0, 0\n").unwrap();
+        for _ in 0..100 {
+            let p = map.sample(10.0, 10.0);
+            assert!((5.0..10.0).contains(&p.x) && (0.0..5.0).contains(&p.y));
+            assert_eq!(map.density_at(&p, 10.0, 10.0), 1.0);
+        }
+        assert!(DensityMap::from_csv("1, 2\n3\n").is_err());
+        assert!(DensityMap::from_csv("0, 0\n").is_err());
+    }
+
+    #[test]
+    fn test_png() {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut bytes, 1, 2);
+            encoder.set_color(png::ColorType::Grayscale);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+            // white on the top row
+            writer.write_image_data(&[255, 0]).unwrap();
+        }
+        let map = DensityMap::from_png(&bytes).unwrap();
+        for _ in 0..100 {
+            assert!(map.sample(10.0, 10.0).y >= 5.0);
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use math::{Percentage, Point};
+
+fn cross(o: &Point, p: &Point, q: &Point) -> f64 {
+    (p.x - o.x) * (q.y - o.y) - (p.y - o.y) * (q.x - o.x)
+}
+
+/// A line segment which agents bounce off and infection does not pass through.
+#[derive(Debug, Clone)]
+pub struct Wall {
+    pub a: Point,
+    pub b: Point,
+}
+
+impl Wall {
+    pub fn new(a: Point, b: Point) -> Self {
+        Self { a, b }
+    }
+
+    /// Returns `true` if the segment from `p` to `q` crosses the wall.
+    /// A point on the line of the wall is regarded as on its negative side,
+    /// so that nothing slips through by stopping on the wall.
+    pub fn blocks(&self, p: &Point, q: &Point) -> bool {
+        let side = |o: &Point, u: &Point, v: &Point| cross(o, u, v) > 0.0;
+        side(&self.a, &self.b, p) != side(&self.a, &self.b, q)
+            && side(p, q, &self.a) != side(p, q, &self.b)
+    }
+
+    /// Returns the mirror image of `p` across the line of the wall.
+    pub fn reflect(&self, p: &Point) -> Point {
+        self.a + self.reflect_vec(&(p - &self.a))
+    }
+
+    /// Returns the mirror image of the vector `v` across the direction of the wall.
+    pub fn reflect_vec(&self, v: &Point) -> Point {
+        let d = self.b - self.a;
+        let n = d.x * d.x + d.y * d.y;
+        if n == 0.0 {
+            return *v;
+        }
+        let t = (v.x * d.x + v.y * d.y) / n;
+        d * (2.0 * t) - *v
+    }
+}
+
+/// A rectangular area where agents move with a different friction.
+#[derive(Debug, Clone)]
+pub struct FrictionZone {
+    pub min: Point,
+    pub max: Point,
+    pub friction: Percentage,
+}
+
+impl FrictionZone {
+    pub fn contains(&self, p: &Point) -> bool {
+        (self.min.x..self.max.x).contains(&p.x) && (self.min.y..self.max.y).contains(&p.y)
+    }
+}
+
+/// A rectangular sub-population bounded by walls, which agents leave only by travelling.
+#[derive(Debug, Clone)]
+pub struct Region {
+    pub min: Point,
+    pub max: Point,
+    pub population: u32,
+    /// initially infected rate
+    pub infected: Percentage,
+}
+
+impl Region {
+    pub fn contains(&self, p: &Point) -> bool {
+        (self.min.x..self.max.x).contains(&p.x) && (self.min.y..self.max.y).contains(&p.y)
+    }
+
+    /// Returns the point at relative coordinates `u` in `[0, 1]` of the region.
+    pub fn point_at(&self, u: &Point) -> Point {
+        Point::new(
+            self.min.x + (self.max.x - self.min.x) * u.x,
+            self.min.y + (self.max.y - self.min.y) * u.y,
+        )
+    }
+
+    /// Returns the nearest point inside the region.
+    pub fn clamp(&self, p: &Point) -> Point {
+        Point::new(
+            p.x.clamp(self.min.x, self.max.x.next_down()),
+            p.y.clamp(self.min.y, self.max.y.next_down()),
+        )
+    }
+
+    /// Returns the walls along the boundary.
+    pub fn walls(&self) -> [Wall; 4] {
+        let (p0, p1) = (self.min, Point::new(self.max.x, self.min.y));
+        let (p2, p3) = (self.max, Point::new(self.min.x, self.max.y));
+        [
+            Wall::new(p0, p1),
+            Wall::new(p1, p2),
+            Wall::new(p2, p3),
+            Wall::new(p3, p0),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use math::Point;
+
+    use super::Wall;
+
+    #[test]
+    fn test_wall() {
+        let w = Wall::new(Point::new(5.0, 0.0), Point::new(5.0, 10.0));
+        assert!(w.blocks(&Point::new(4.0, 5.0), &Point::new(6.0, 5.0)));
+        assert!(!w.blocks(&Point::new(4.0, 5.0), &Point::new(4.5, 9.0)));
+        assert!(!w.blocks(&Point::new(4.0, 11.0), &Point::new(6.0, 11.0)));
+        // stopping on the wall and going on
+        let on = Point::new(5.0, 5.0);
+        assert_ne!(
+            w.blocks(&Point::new(4.0, 5.0), &on),
+            w.blocks(&on, &Point::new(6.0, 5.0))
+        );
+        assert_eq!(w.reflect(&Point::new(6.0, 5.0)), Point::new(4.0, 5.0));
+        assert_eq!(w.reflect_vec(&Point::new(1.0, 1.0)), Point::new(-1.0, 1.0));
+    }
+}
@@ -0,0 +1,67 @@
+/// Immunity is regarded as lost when its level relative to the peak falls below this.
+pub const MIN_LEVEL: f64 = 0.01;
+
+/// How acquired immunity wanes relative to its peak over days.
+#[derive(Debug, Clone)]
+pub enum WaningCurve {
+    Exponential {
+        half_life: f64,
+    },
+    /// `(days, level)` points sorted by days, linearly interpolated and constant outside
+    PiecewiseLinear(Vec<(f64, f64)>),
+    /// levels at every `interval` days from zero, linearly interpolated and constant after the last
+    Table {
+        interval: f64,
+        levels: Vec<f64>,
+    },
+}
+
+impl WaningCurve {
+    /// Returns the level in `[0, 1]` after `days` from the peak.
+    pub fn level(&self, days: f64) -> f64 {
+        let level = match self {
+            WaningCurve::Exponential { half_life } => 0.5f64.powf(days / half_life),
+            WaningCurve::PiecewiseLinear(points) => {
+                let i = points.partition_point(|(d, _)| *d <= days);
+                match (i.checked_sub(1).map(|j| points[j]), points.get(i).copied()) {
+                    (None, None) => 1.0,
+                    (None, Some((_, l))) | (Some((_, l)), None) => l,
+                    (Some((d0, l0)), Some((d1, l1))) => l0 + (l1 - l0) * (days - d0) / (d1 - d0),
+                }
+            }
+            WaningCurve::Table { interval, levels } => {
+                let x = days.max(0.0) / interval;
+                let i = x.floor() as usize;
+                match (levels.get(i), levels.get(i + 1)) {
+                    (Some(l0), Some(l1)) => l0 + (l1 - l0) * x.fract(),
+                    _ => levels.last().copied().unwrap_or(1.0),
+                }
+            }
+        };
+        level.clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WaningCurve;
+
+    #[test]
+    fn test_level() {
+        let c = WaningCurve::Exponential { half_life: 10.0 };
+        assert_eq!(c.level(0.0), 1.0);
+        assert!((c.level(20.0) - 0.25).abs() < 1e-12);
+
+        let c = WaningCurve::PiecewiseLinear(vec![(10.0, 1.0), (30.0, 0.0)]);
+        assert_eq!(c.level(0.0), 1.0);
+        assert_eq!(c.level(20.0), 0.5);
+        assert_eq!(c.level(40.0), 0.0);
+
+        let c = WaningCurve::Table {
+            interval: 10.0,
+            levels: vec![1.0, 0.5, 0.1],
+        };
+        assert_eq!(c.level(5.0), 0.75);
+        assert_eq!(c.level(100.0), 0.1);
+    }
+}
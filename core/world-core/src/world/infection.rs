@@ -0,0 +1,185 @@
+use std::fmt::Debug;
+
+use super::commons::ParamsForStep;
+
+pub(crate) fn exacerbation(reproductivity: f64) -> f64 {
+    reproductivity.powf(1.0 / 3.0)
+}
+
+/// An infected agent as seen by an infection model.
+#[derive(Debug, Clone)]
+pub struct Infector {
+    pub days_infected: f64,
+    pub days_to_onset: f64,
+    /// reproductivity of the virus variant
+    pub reproductivity: f64,
+}
+
+/// A hypothesis of how the virus is transmitted.
+/// All the probabilities are per day and are reduced by the immunity of the infectee.
+pub trait InfectionModel: Debug + Send + Sync {
+    /// Returns the probability that the infector infects an agent at distance `d`.
+    fn contact_prob(&self, infector: &Infector, d: f64, pfs: &ParamsForStep) -> f64;
+
+    /// Returns `true` if the virus is accumulated in the air of each cell.
+    fn is_airborne(&self) -> bool {
+        false
+    }
+
+    /// Returns the amount of virus the infector emits into the air of its cell.
+    fn emission(&self, _infector: &Infector, _pfs: &ParamsForStep) -> f64 {
+        0.0
+    }
+
+    /// Returns the rate of airborne virus remaining after a day.
+    fn retention(&self) -> f64 {
+        0.0
+    }
+
+    /// Returns the probability to be infected in the air with the amount of virus
+    /// in a square of the view range.
+    fn exposure_prob(&self, _amount: f64, _pfs: &ParamsForStep) -> f64 {
+        0.0
+    }
+}
+
+fn infec_prob(reproductivity: f64, pfs: &ParamsForStep) -> f64 {
+    if reproductivity < 1.0 {
+        pfs.rp.infec.r() * reproductivity
+    } else {
+        1.0 - (1.0 - pfs.rp.infec.r()) / reproductivity
+    }
+}
+
+fn distance_factor(reproductivity: f64, d: f64, pfs: &ParamsForStep) -> f64 {
+    let infec_d_max = pfs.rp.infec_dst * reproductivity.sqrt();
+    if d > infec_d_max {
+        0.0
+    } else {
+        1f64.min(((infec_d_max - d) / 2.0).powf(2.0))
+    }
+}
+
+/// Infectiousness rising linearly from `contag_delay` to `contag_peak` or the onset.
+fn time_factor(infector: &Infector, pfs: &ParamsForStep) -> f64 {
+    let exacerbate = exacerbation(infector.reproductivity);
+    let contag_delay = pfs.rp.contag_delay / exacerbate;
+    let contag_peak = pfs.rp.contag_peak / exacerbate;
+    if infector.days_infected <= contag_delay {
+        return 0.0;
+    }
+    1f64.min(
+        (infector.days_infected - contag_delay)
+            / (contag_peak.min(infector.days_to_onset) - contag_delay),
+    )
+}
+
+/// Transmission by close contacts with the infectiousness ramped up to the peak.
+#[derive(Debug, Default)]
+pub struct Contact;
+
+impl InfectionModel for Contact {
+    fn contact_prob(&self, infector: &Infector, d: f64, pfs: &ParamsForStep) -> f64 {
+        let virus_x = infector.reproductivity;
+        infec_prob(virus_x, pfs) * time_factor(infector, pfs) * distance_factor(virus_x, d, pfs)
+    }
+}
+
+/// Transmission by close contacts with the infectiousness following a viral load curve
+/// in the shape of a gamma distribution, normalized to one at its mode.
+#[derive(Debug)]
+pub struct ViralLoad {
+    pub shape: f64,
+    /// in days
+    pub scale: f64,
+}
+
+impl Default for ViralLoad {
+    fn default() -> Self {
+        Self {
+            shape: 3.0,
+            scale: 1.0,
+        }
+    }
+}
+
+impl ViralLoad {
+    fn infectiousness(&self, days_infected: f64) -> f64 {
+        if days_infected <= 0.0 {
+            return 0.0;
+        }
+        if self.shape <= 1.0 {
+            return (-days_infected / self.scale).exp();
+        }
+        let mode = (self.shape - 1.0) * self.scale;
+        (days_infected / mode).powf(self.shape - 1.0) * (-(days_infected - mode) / self.scale).exp()
+    }
+}
+
+impl InfectionModel for ViralLoad {
+    fn contact_prob(&self, infector: &Infector, d: f64, pfs: &ParamsForStep) -> f64 {
+        let virus_x = infector.reproductivity;
+        infec_prob(virus_x, pfs)
+            * self.infectiousness(infector.days_infected * exacerbation(virus_x))
+            * distance_factor(virus_x, d, pfs)
+    }
+}
+
+/// Transmission through virus emitted into the air of a cell,
+/// which is accumulated over time and decays with its half-life.
+/// Agents do not infect each other by contacts.
+#[derive(Debug)]
+pub struct Aerosol {
+    /// amount of virus emitted by an infector at its peak per day
+    pub emission: f64,
+    /// in hours
+    pub half_life: f64,
+    /// infection probability per day and amount of virus
+    pub dose_response: f64,
+}
+
+impl Default for Aerosol {
+    fn default() -> Self {
+        Self {
+            emission: 1.0,
+            half_life: 1.0,
+            dose_response: 0.5,
+        }
+    }
+}
+
+impl InfectionModel for Aerosol {
+    fn contact_prob(&self, _infector: &Infector, _d: f64, _pfs: &ParamsForStep) -> f64 {
+        0.0
+    }
+
+    fn is_airborne(&self) -> bool {
+        true
+    }
+
+    fn emission(&self, infector: &Infector, pfs: &ParamsForStep) -> f64 {
+        self.emission * infec_prob(infector.reproductivity, pfs) * time_factor(infector, pfs)
+    }
+
+    fn retention(&self) -> f64 {
+        0.5f64.powf(24.0 / self.half_life)
+    }
+
+    fn exposure_prob(&self, amount: f64, _pfs: &ParamsForStep) -> f64 {
+        1.0 - (-self.dose_response * amount).exp()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ViralLoad;
+
+    #[test]
+    fn test_viral_load() {
+        let vl = ViralLoad::default();
+        assert_eq!(vl.infectiousness(0.0), 0.0);
+        assert!((vl.infectiousness(2.0) - 1.0).abs() < 1e-12);
+        assert!(vl.infectiousness(1.0) < 1.0);
+        assert!(vl.infectiousness(3.0) < 1.0);
+    }
+}
@@ -0,0 +1,99 @@
+use std::time::{Duration, Instant};
+
+use enum_map::{macros::Enum, EnumMap};
+
+/// A phase of a step of the world.
+#[derive(Enum, Clone, Copy, Debug, strum::Display)]
+pub enum Phase {
+    TestQueue,
+    Gatherings,
+    Vaccination,
+    Households,
+    Imports,
+    Interact,
+    Field,
+    Hospital,
+    HomeQuarantine,
+    Warps,
+    Stat,
+    Scenario,
+}
+
+/// Time spent in each phase of the steps, which is measured only if enabled.
+#[derive(Default)]
+pub struct Profile {
+    enabled: bool,
+    n_steps: u32,
+    times: EnumMap<Phase, Duration>,
+}
+
+impl Profile {
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.n_steps = 0;
+        self.times = EnumMap::default();
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Starts measuring a step, which does nothing if disabled.
+    pub fn start(&mut self) -> Laps<'_> {
+        if !self.enabled {
+            return Laps(None);
+        }
+        self.n_steps += 1;
+        Laps(Some((self, Instant::now())))
+    }
+
+    /// Returns the mean seconds spent in each phase per step, which is empty if disabled.
+    pub fn mean_secs(&self) -> Vec<(Phase, f64)> {
+        if !self.enabled || self.n_steps == 0 {
+            return Vec::new();
+        }
+        self.times
+            .iter()
+            .map(|(phase, t)| (phase, t.as_secs_f64() / self.n_steps as f64))
+            .collect()
+    }
+}
+
+/// Measurement of the phases of a step in order.
+pub struct Laps<'a>(Option<(&'a mut Profile, Instant)>);
+
+impl Laps<'_> {
+    /// Adds the time since the previous phase ended to `phase`.
+    pub fn lap(&mut self, phase: Phase) {
+        if let Some((profile, prev)) = &mut self.0 {
+            let now = Instant::now();
+            profile.times[&phase] += now - *prev;
+            *prev = now;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Phase, Profile};
+
+    #[test]
+    fn test_profile() {
+        let mut profile = Profile::default();
+        profile.start().lap(Phase::Interact);
+        assert!(profile.mean_secs().is_empty());
+
+        profile.set_enabled(true);
+        for _ in 0..2 {
+            let mut laps = profile.start();
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            laps.lap(Phase::Interact);
+            laps.lap(Phase::Field);
+        }
+        let secs = profile.mean_secs();
+        assert_eq!(secs.len(), 12);
+        assert!(secs[Phase::Interact as usize].1 >= 0.01);
+        assert!(secs[Phase::Field as usize].1 < 0.01);
+    }
+}
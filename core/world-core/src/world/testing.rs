@@ -1,6 +1,9 @@
 use crate::util::random;
 
-use super::{agent::AgentRef, commons::ParamsForStep};
+use super::{
+    agent::{AgentRef, Location},
+    commons::ParamsForStep,
+};
 use enum_map::{macros::Enum, EnumMap};
 
 use std::collections::VecDeque;
@@ -12,6 +15,7 @@ pub enum TestReason {
     AsSymptom,
     AsContact,
     AsSuspected,
+    OnArrival,
     //[todo] TestPositiveRate,
 }
 
@@ -111,7 +115,13 @@ impl TestQueue {
                 break;
             }
             let t = self.0.pop_front().unwrap();
-            if t.time_stamp > oldest && t.agent.location.read().in_field() {
+            // entrants are tested even in the arrival quarantine
+            let present = match *t.agent.location.read() {
+                Location::Field => true,
+                Location::HomeQuarantine => t.reason == TestReason::OnArrival,
+                _ => false,
+            };
+            if t.time_stamp > oldest && present {
                 max_tests -= 1;
                 let (reason, result) = t.conduct(pfs);
                 count_reason[&reason] += 1;
@@ -1,11 +1,11 @@
-use std::str::FromStr;
+use std::{fmt, str::FromStr};
 
 use nom::{
     branch::alt,
     bytes::complete::tag,
     character::complete::{space0, space1},
     combinator::{eof, value},
-    error::{Error, ParseError},
+    error::{Error, ErrorKind, ParseError},
     multi::fold_many0,
     sequence::{delimited, pair, preceded, terminated},
     Finish, IResult, Parser,
@@ -26,16 +26,45 @@ where
     }
 }
 
+/// Failure to parse a predicate, located where the parser stopped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PredicateError {
+    /// column of the input counted from one
+    pub column: usize,
+    /// input left unparsed
+    pub rest: String,
+    pub kind: ErrorKind,
+}
+
+impl fmt::Display for PredicateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.rest.is_empty() {
+            write!(f, "unexpected end at column {}", self.column)
+        } else {
+            write!(f, "unexpected `{}` at column {}", self.rest, self.column)
+        }
+    }
+}
+
+impl std::error::Error for PredicateError {}
+
 impl<Field> FromStr for Predicate<Field>
 where
     Field: FieldCombinator,
 {
-    type Err = Error<String>;
+    type Err = PredicateError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match parse_expr(s).finish() {
             Ok((_, e)) => Ok(Self(e)),
-            Err(e) => Err(Error::new(e.input.to_string(), e.code)),
+            Err(e) => {
+                let rest = e.input.trim_start();
+                Err(PredicateError {
+                    column: s[..(s.len() - rest.len())].chars().count() + 1,
+                    rest: rest.to_string(),
+                    kind: e.code,
+                })
+            }
         }
     }
 }
@@ -173,7 +202,7 @@ mod tests {
         branch::alt, character::complete::u32, combinator::map, error::Error, IResult, Parser,
     };
 
-    use crate::{binary_relation, EvalField, FieldCombinator, Predicate};
+    use crate::{binary_relation, EvalField, FieldCombinator, Predicate, PredicateError};
 
     #[derive(Debug, PartialEq, PartialOrd)]
     pub enum CondField {
@@ -240,4 +269,18 @@ mod tests {
             env.days <= 10 && env.days == 5 || env.days < 100 && env.days > 4
         );
     }
+
+    #[test]
+    fn test_error_column() {
+        let column = |s: &str| {
+            s.parse::<Predicate<CondField>>()
+                .map(|_| ())
+                .map_err(|PredicateError { column, .. }| column)
+        };
+        assert_eq!(column("dayz == 1"), Err(1));
+        assert_eq!(column("days == 1 AND dayz > 2"), Err(11));
+        assert_eq!(column("days == 1 )"), Err(11));
+        assert_eq!(column("(days == 1"), Err(11));
+        assert_eq!(column("days == 1"), Ok(()));
+    }
 }
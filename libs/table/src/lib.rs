@@ -1,6 +1,10 @@
 mod iter;
 
 use self::iter::*;
+use rayon::{
+    iter::{IntoParallelRefMutIterator, ParallelIterator},
+    slice::ParallelSliceMut,
+};
 use std::{
     fmt::Debug,
     ops::{Index, IndexMut},
@@ -88,6 +92,59 @@ impl<T> Table<T> {
     pub fn iter_mut(&mut self) -> serial::IterMut<'_, Item<TableIndex, T>, (&TableIndex, &mut T)> {
         serial::IterMut::new(&mut self.container, self.column)
     }
+
+    /// Calls `f` in parallel with each pair of cells the second of which is `row` rows below
+    /// and `column` columns right of the first. The offset must not be backward in the same row.
+    pub fn par_for_each_pair_mut<F>(&mut self, row: usize, column: isize, f: F)
+    where
+        T: Send,
+        F: Fn((&TableIndex, &mut T), (&TableIndex, &mut T)) + Sync,
+    {
+        assert!(
+            row > 0 || column > 0,
+            "offset ({row}, {column}) is not forward"
+        );
+        // the cells of a block of `n` rows or columns are paired with those of the next block,
+        // and the pairs of even and odd blocks are visited in turn
+        let pair_rows = |a: &mut [Item<TableIndex, T>], b: &mut [Item<TableIndex, T>]| {
+            let shift = column.unsigned_abs();
+            let (a, b) = if column >= 0 {
+                let n = b.len();
+                (a, &mut b[shift.min(n)..])
+            } else {
+                let n = a.len();
+                (&mut a[shift.min(n)..], b)
+            };
+            for (x, y) in a.iter_mut().zip(b.iter_mut()) {
+                f(x.into(), y.into());
+            }
+        };
+        if row > 0 {
+            for start in [0, row] {
+                let rows = &mut self.container[start.min(self.row)..];
+                rows.par_chunks_mut(row * 2).for_each(|rows| {
+                    let (a, b) = rows.split_at_mut(row.min(rows.len()));
+                    for (a, b) in a.iter_mut().zip(b.iter_mut()) {
+                        pair_rows(a, b);
+                    }
+                });
+            }
+        } else {
+            let column = column as usize;
+            for start in [0, column] {
+                self.container.par_iter_mut().for_each(|cells| {
+                    let n = cells.len();
+                    let cells = &mut cells[start.min(n)..];
+                    for cells in cells.chunks_mut(column * 2) {
+                        let (a, b) = cells.split_at_mut(column.min(cells.len()));
+                        for (x, y) in a.iter_mut().zip(b.iter_mut()) {
+                            f(x.into(), y.into());
+                        }
+                    }
+                });
+            }
+        }
+    }
 }
 
 impl<'a, Idx, T> From<&'a mut Item<Idx, T>> for (&'a Idx, &'a mut T) {
@@ -500,6 +557,42 @@ mod tests {
         par_iter_mut_all_direction::<7, 7>();
     }
 
+    fn par_pairs_check<const R: usize, const C: usize>() {
+        println!("({R}, {C})");
+        let table = &mut Table::new(R, C, || 0);
+        for row in 0..3 {
+            for column in -3..=3isize {
+                if row == 0 && column <= 0 {
+                    continue;
+                }
+                let pairs = std::sync::Mutex::new(Vec::new());
+                table.par_for_each_pair_mut(row, column, |(idx0, v0), (idx1, v1)| {
+                    *v0 += 1;
+                    *v1 += 1;
+                    pairs.lock().unwrap().push((idx0.clone(), idx1.clone()));
+                });
+                let mut pairs = pairs.into_inner().unwrap();
+                for (idx0, idx1) in &pairs {
+                    assert!(idx0.row + row == idx1.row);
+                    assert!(idx0.column as isize + column == idx1.column as isize);
+                }
+                pairs.sort_by_key(|(idx, _)| (idx.row, idx.column));
+                pairs.dedup();
+                assert!(
+                    pairs.len() == R.saturating_sub(row) * C.saturating_sub(column.unsigned_abs())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn table_test_par_pairs() {
+        par_pairs_check::<7, 6>();
+        par_pairs_check::<6, 6>();
+        par_pairs_check::<6, 7>();
+        par_pairs_check::<1, 2>();
+    }
+
     #[test]
     fn table_test_par() {
         par_iter_all_direction::<7, 6>();
@@ -1,4 +1,4 @@
-use poem_openapi::Object;
+use poem_openapi::{Enum, Object};
 
 #[derive(Object, Clone, Debug, serde::Deserialize, serde::Serialize, Default)]
 #[oai(rename_all = "camelCase")]
@@ -6,6 +6,250 @@ pub struct WorldParams {
     #[oai(validator(minimum(value = "1", exclusive = false)))]
     pub population_size: u32,
     pub infected: f64,
+    /// commuting between home and workplace; agents have no home if omitted
+    pub commuting: Option<CommutingParams>,
+    /// population density used to place homes and workplaces
+    pub density_map: Option<DensityMap>,
+    /// clusters of vaccine refusers; nobody refuses if omitted
+    pub anti_vax: Option<AntiVaxParams>,
+    /// hypothesis of transmission; the contact model if omitted
+    pub infection_model: Option<InfectionModelParams>,
+    /// shape of the field and obstacles in it; a square of 360 without obstacles if omitted
+    pub geometry: Option<GeometryParams>,
+    /// sub-populations laid out from left to right with a gap of one cell,
+    /// which replace the population size, the initially infected rate and the size of the field,
+    /// and may each have their own mobility, gatherings and interventions
+    #[oai(default)]
+    #[serde(default)]
+    pub regions: Vec<RegionParams>,
+    /// households sharing a home; agents live alone if omitted
+    pub households: Option<HouseholdParams>,
+    /// number of cells along the width of the field, at most the width;
+    /// derived from the population density and the number of threads if omitted
+    #[oai(validator(minimum(value = "1", exclusive = false)))]
+    pub mesh: Option<u32>,
+}
+
+#[derive(Object, Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub struct HouseholdParams {
+    /// relative frequencies of households of one, two, ... members
+    #[oai(validator(min_items = 1))]
+    pub size_distribution: Vec<f64>,
+    /// percentage per day that an infected member infects each of the others
+    #[oai(validator(minimum(value = "0"), maximum(value = "100")))]
+    pub transmission: f64,
+    /// whether the other members of a positive agent are quarantined
+    #[oai(default)]
+    #[serde(default)]
+    pub quarantine: bool,
+}
+
+/// A sub-population with its own size and initial infections. The agents in it follow its
+/// mobility, gatherings and interventions, each value of which is that of the world if omitted.
+#[derive(Object, Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub struct RegionParams {
+    #[oai(validator(minimum(value = "1", exclusive = false)))]
+    pub width: u32,
+    #[oai(validator(minimum(value = "1", exclusive = false)))]
+    pub height: u32,
+    pub population: u32,
+    #[oai(validator(minimum(value = "0"), maximum(value = "100")))]
+    pub infected: f64,
+    pub mobility: Option<RegionMobilityParams>,
+    pub gatherings: Option<RegionGatheringParams>,
+    pub interventions: Option<RegionInterventionParams>,
+}
+
+/// minimum, most frequent and maximum values of a distribution
+#[derive(Object, Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub struct DistParams {
+    pub min: f64,
+    pub mode: f64,
+    pub max: f64,
+}
+
+#[derive(Object, Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub struct RegionMobilityParams {
+    /// permille of the agents moving to a random place per day
+    pub frequency: Option<DistParams>,
+    /// distance of the move in percent of the width of the field
+    pub distance: Option<DistParams>,
+    /// percentage per day of the agents away from home going back in the daytime
+    #[oai(validator(minimum(value = "0"), maximum(value = "100")))]
+    pub back_home_rate: Option<f64>,
+}
+
+#[derive(Object, Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub struct RegionGatheringParams {
+    /// number of random gatherings per day in an area of 100,000
+    #[oai(validator(minimum(value = "0")))]
+    pub frequency: Option<f64>,
+    /// percentage of the agents around taking part in a random gathering
+    pub participation: Option<DistParams>,
+}
+
+#[derive(Object, Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub struct RegionInterventionParams {
+    /// strength of the distancing of agents who keep their distance
+    #[oai(validator(minimum(value = "0")))]
+    pub distancing_strength: Option<f64>,
+    /// percentage per day of the asymptomatic agents tested
+    #[oai(validator(minimum(value = "0"), maximum(value = "100")))]
+    pub test_asymptomatic: Option<f64>,
+    /// percentage per day of the symptomatic agents tested
+    #[oai(validator(minimum(value = "0"), maximum(value = "100")))]
+    pub test_symptomatic: Option<f64>,
+    /// percentage of the positive agents isolated at home instead of hospitalized
+    #[oai(validator(minimum(value = "0"), maximum(value = "100")))]
+    pub home_quarantine_rate: Option<f64>,
+}
+
+#[derive(Object, Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub struct GeometryParams {
+    #[oai(validator(minimum(value = "1", exclusive = false)))]
+    pub width: u32,
+    #[oai(validator(minimum(value = "1", exclusive = false)))]
+    pub height: u32,
+    /// barriers which agents bounce off and infection does not pass through
+    #[oai(default)]
+    #[serde(default)]
+    pub walls: Vec<WallParams>,
+    /// rectangular areas with their own friction
+    #[oai(default)]
+    #[serde(default)]
+    pub friction_zones: Vec<FrictionZoneParams>,
+}
+
+/// line segment from `(x1, y1)` to `(x2, y2)`
+#[derive(Object, Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub struct WallParams {
+    pub x1: f64,
+    pub y1: f64,
+    pub x2: f64,
+    pub y2: f64,
+}
+
+/// rectangle from `(x, y)` to `(x + width, y + height)`
+#[derive(Object, Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub struct FrictionZoneParams {
+    pub x: f64,
+    pub y: f64,
+    #[oai(validator(minimum(value = "0")))]
+    pub width: f64,
+    #[oai(validator(minimum(value = "0")))]
+    pub height: f64,
+    /// percentage of velocity lost per day instead of the global friction
+    #[oai(validator(minimum(value = "0"), maximum(value = "100")))]
+    pub friction: f64,
+}
+
+#[derive(Enum, Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub enum InfectionModelKind {
+    /// close contacts with infectiousness ramped up to the peak
+    Contact,
+    /// close contacts with gamma-distributed infectiousness
+    ViralLoad,
+    /// exposure to virus accumulated in the air of each cell
+    Aerosol,
+}
+
+#[derive(Object, Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub struct InfectionModelParams {
+    pub kind: InfectionModelKind,
+    /// parameters of the viral load model; defaults if omitted
+    pub viral_load: Option<ViralLoadParams>,
+    /// parameters of the aerosol model; defaults if omitted
+    pub aerosol: Option<AerosolParams>,
+}
+
+#[derive(Object, Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub struct ViralLoadParams {
+    /// shape of the gamma distribution
+    #[oai(validator(minimum(value = "0", exclusive = true)))]
+    pub shape: f64,
+    /// scale of the gamma distribution in days
+    #[oai(validator(minimum(value = "0", exclusive = true)))]
+    pub scale: f64,
+}
+
+#[derive(Object, Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub struct AerosolParams {
+    /// amount of virus emitted by an infected agent at its peak per day
+    #[oai(validator(minimum(value = "0")))]
+    pub emission: f64,
+    /// half-life of airborne virus in hours
+    #[oai(validator(minimum(value = "0", exclusive = true)))]
+    pub half_life: f64,
+    /// infection probability per day and amount of virus
+    #[oai(validator(minimum(value = "0")))]
+    pub dose_response: f64,
+}
+
+#[derive(Object, Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub struct AntiVaxParams {
+    /// percentage of vaccine refusers
+    #[oai(validator(minimum(value = "0"), maximum(value = "100")))]
+    pub rate: f64,
+    /// side length of a cluster in percentage of the field size; not clustered if zero
+    #[oai(validator(minimum(value = "0"), maximum(value = "100")))]
+    pub granularity: f64,
+    /// testing propensity of refusers in percentage of the others
+    #[oai(validator(minimum(value = "0")))]
+    pub test_rate: f64,
+}
+
+#[derive(Enum, Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub enum DensityMapFormat {
+    /// comma separated densities, one line per row from the bottom of the field
+    Csv,
+    /// base64 encoded grayscale image whose brighter pixels mean denser population
+    Png,
+}
+
+#[derive(Object, Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub struct DensityMap {
+    pub format: DensityMapFormat,
+    pub data: String,
+}
+
+#[derive(Object, Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub struct CommutingParams {
+    /// percentage of agents commuting between home and workplace
+    #[oai(validator(minimum(value = "0"), maximum(value = "100")))]
+    pub rate: f64,
+    /// number of workplace clusters in each region, where its residents work
+    #[oai(validator(minimum(value = "1")))]
+    pub workplaces: u32,
+    /// radius of a workplace cluster in percentage of the field size
+    #[oai(validator(minimum(value = "0")))]
+    pub radius: f64,
+    /// hour of a day to go to work
+    #[oai(validator(minimum(value = "0"), maximum(value = "24")))]
+    pub start_hour: f64,
+    /// hour of a day to leave work, which is on the next day if earlier than `start_hour`;
+    /// work from 0 to 24 o'clock lasts around the clock
+    #[oai(validator(minimum(value = "0"), maximum(value = "24")))]
+    pub end_hour: f64,
+    /// homes and workplaces are concentrated around the center of the field
+    #[oai(default)]
+    pub centered: bool,
 }
 
 #[derive(Object, Clone, Debug, serde::Deserialize, serde::Serialize)]
@@ -15,9 +259,313 @@ pub struct JobParam {
     pub stop_at: u32,
     pub world_params: WorldParams,
     pub scenario: Vec<Operation>,
-    // vaccines
+    /// contact tracing policy; contacts are only tested if omitted
+    pub tracing: Option<TracingParams>,
+    /// isolation of positive agents at home; all of them are hospitalized if omitted
+    pub home_quarantine: Option<HomeQuarantineParams>,
+    /// hospital capacity; unlimited if omitted
+    pub hospital: Option<HospitalParams>,
+    /// waning and hybrid immunity; immunity is kept until it expires if omitted
+    pub immunity: Option<ImmunityParams>,
+    /// vaccines and their strategies; nobody is vaccinated except traced contacts if omitted
+    pub vaccination: Option<VaccinationParams>,
+    /// fixed gathering spots; gatherings only take place at random spots if omitted
+    pub gathering_spots: Option<GatheringSpotsParams>,
+    /// scheduled mass gatherings, indexed from zero in the order given
+    #[oai(default)]
+    #[serde(default)]
+    pub gathering_events: Vec<GatheringEvent>,
+    /// travel between regions and border controls; nobody travels if omitted
+    pub travel: Option<TravelParams>,
+    /// whether the mean time spent in each phase of a step is reported in the status
+    #[oai(default)]
+    #[serde(default)]
+    pub profiling: bool,
     // variants
-    // gatherings
+}
+
+#[derive(Object, Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub struct TravelParams {
+    /// percentages of the agents in the region of each row travelling to the region of each
+    /// column per day, at most 100 in total per row without the diagonal; nobody travels if empty
+    #[oai(default)]
+    #[serde(default)]
+    pub od_matrix: Vec<Vec<f64>>,
+    #[oai(default)]
+    #[serde(default)]
+    pub border_controls: Vec<BorderControlParams>,
+}
+
+/// controls on agents entering a region from another one
+#[derive(Object, Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub struct BorderControlParams {
+    /// index of the region
+    pub region: usize,
+    /// maximum number of entries per day; unlimited if omitted
+    pub quota: Option<u32>,
+    #[oai(default)]
+    #[serde(default)]
+    pub test_on_arrival: bool,
+    /// days of quarantine at the arrival point
+    #[oai(default, validator(minimum(value = "0")))]
+    #[serde(default)]
+    pub quarantine_days: f64,
+    /// number of cases imported from the outside per day
+    #[oai(default, validator(minimum(value = "0")))]
+    #[serde(default)]
+    pub imported_cases: f64,
+    /// index of the variant of imported cases
+    #[oai(default)]
+    #[serde(default)]
+    pub imported_variant: usize,
+}
+
+#[derive(Enum, Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub enum TracingOperation {
+    Test,
+    Vaccinate,
+    Quarantine,
+}
+
+#[derive(Object, Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub struct TracingParams {
+    /// what to do for traced contacts
+    pub operations: Vec<TracingOperation>,
+    /// index of the vaccine given to traced contacts
+    #[oai(default)]
+    pub vaccine_type: usize,
+    /// quarantine duration of traced contacts in days
+    #[oai(validator(minimum(value = "0", exclusive = false)))]
+    pub quarantine_days: f64,
+}
+
+#[derive(Object, Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub struct HomeQuarantineParams {
+    /// percentage of positive agents isolated at home instead of hospitalized
+    #[oai(validator(minimum(value = "0"), maximum(value = "100")))]
+    pub rate: f64,
+    /// home quarantine duration in days
+    #[oai(validator(minimum(value = "0", exclusive = false)))]
+    pub days: f64,
+}
+
+#[derive(Object, Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub struct HospitalParams {
+    /// beds per 1000 population
+    #[oai(validator(minimum(value = "0", exclusive = true)))]
+    pub capacity: f64,
+    /// percentage increase of severity progression for patients denied a bed
+    #[oai(default)]
+    pub overflow_severity: f64,
+    /// percentage increase of days to recovery for patients denied a bed
+    #[oai(default)]
+    pub overflow_recovery_delay: f64,
+}
+
+#[derive(Object, Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub struct VaccinationParams {
+    /// vaccine types indexed from zero in the order given
+    pub vaccines: Vec<VaccineParams>,
+    /// strategies indexed from zero in the order given, which a scenario refers to
+    pub strategies: Vec<VaccinationStrategyParams>,
+    /// minimum days since the last dose to be eligible for a booster
+    #[oai(validator(minimum(value = "0")))]
+    pub booster_min_days: f64,
+}
+
+#[derive(Object, Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub struct VaccineParams {
+    /// primary schedule
+    #[oai(validator(min_items = 1))]
+    pub doses: Vec<DoseParams>,
+    /// percentage of the efficacy of the doses retained against each variant in the order of the
+    /// variant pool; fully retained against every variant if empty
+    #[oai(default)]
+    #[serde(default)]
+    pub variant_efficacy: Vec<f64>,
+}
+
+#[derive(Object, Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub struct DoseParams {
+    /// days after the previous dose; ignored for the first dose
+    #[oai(default, validator(minimum(value = "0")))]
+    #[serde(default)]
+    pub interval: f64,
+    /// percentage efficacy reached by the dose
+    #[oai(validator(minimum(value = "0"), maximum(value = "100")))]
+    pub efficacy: f64,
+}
+
+#[derive(Enum, Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub enum VaccinePriority {
+    Random,
+    Older,
+    Central,
+    PopulationDensity,
+    /// only agents eligible for a booster
+    Booster,
+}
+
+#[derive(Object, Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub struct VaccinationStrategyParams {
+    /// index of the vaccine
+    pub vaccine: usize,
+    /// doses per 1000 population per day
+    #[oai(validator(minimum(value = "0")))]
+    pub perform_rate: f64,
+    /// percentage of agents vaccinated in the order of the priority
+    #[oai(
+        default = "default_regularity",
+        validator(minimum(value = "0"), maximum(value = "100"))
+    )]
+    #[serde(default = "default_regularity")]
+    pub regularity: f64,
+    pub priority: VaccinePriority,
+}
+
+fn default_regularity() -> f64 {
+    100.0
+}
+
+#[derive(Object, Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub struct ImmunityParams {
+    /// waning of immunity acquired by infection
+    pub infection_waning: Option<WaningCurve>,
+    /// waning of immunity acquired by vaccination after its full effect
+    pub vaccine_waning: Option<WaningCurve>,
+    /// percentage reduction of the remaining susceptibility of agents both infected and vaccinated
+    #[oai(default, validator(minimum(value = "0"), maximum(value = "100")))]
+    #[serde(default)]
+    pub hybrid_boost: f64,
+}
+
+#[derive(Enum, Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub enum WaningCurveKind {
+    /// halved every `halfLife` days
+    Exponential,
+    /// linearly interpolated between `points`
+    PiecewiseLinear,
+    /// `levels` at every `interval` days
+    Table,
+}
+
+#[derive(Object, Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub struct WaningCurve {
+    pub kind: WaningCurveKind,
+    pub half_life: Option<f64>,
+    #[oai(default)]
+    #[serde(default)]
+    pub points: Vec<WaningPoint>,
+    pub interval: Option<f64>,
+    /// levels relative to the peak
+    #[oai(default)]
+    #[serde(default)]
+    pub levels: Vec<f64>,
+}
+
+#[derive(Object, Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub struct WaningPoint {
+    pub days: f64,
+    /// level relative to the peak
+    #[oai(validator(minimum(value = "0"), maximum(value = "1")))]
+    pub level: f64,
+}
+
+#[derive(Object, Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub struct GatheringSpotsParams {
+    /// spots at given coordinates, indexed from zero in the order given
+    #[oai(default)]
+    pub spots: Vec<GatheringSpot>,
+    /// number of additional spots placed at random, indexed after `spots`
+    #[oai(default)]
+    pub random_count: u32,
+    /// placement of the additional spots
+    #[oai(default)]
+    pub random_placement: SpotPlacement,
+    /// weight of each additional spot
+    #[oai(default = "default_spot_weight", validator(minimum(value = "0")))]
+    pub random_weight: f64,
+    /// percentage of gatherings at random spots instead of fixed ones
+    #[oai(validator(minimum(value = "0"), maximum(value = "100")))]
+    pub random_spot_rate: f64,
+}
+
+#[derive(Object, Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub struct GatheringSpot {
+    pub x: f64,
+    pub y: f64,
+    /// relative frequency of gatherings among the open spots
+    #[oai(default = "default_spot_weight", validator(minimum(value = "0")))]
+    pub weight: f64,
+    /// closed spots can be opened by the scenario
+    #[oai(default = "default_spot_open")]
+    pub open: bool,
+}
+
+fn default_spot_weight() -> f64 {
+    1.0
+}
+
+fn default_spot_open() -> bool {
+    true
+}
+
+#[derive(
+    Enum, Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize,
+)]
+#[oai(rename_all = "camelCase")]
+pub enum SpotPlacement {
+    /// uniformly over the field
+    #[default]
+    Uniform,
+    /// following the placement of the population
+    Population,
+}
+
+#[derive(Object, Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub struct GatheringEvent {
+    pub x: f64,
+    pub y: f64,
+    #[oai(validator(minimum(value = "0", exclusive = true)))]
+    pub radius: f64,
+    /// day to start from zero
+    pub day: u32,
+    /// hour of the day to start
+    #[oai(default, validator(minimum(value = "0"), maximum(value = "24")))]
+    #[serde(default)]
+    pub hour: f64,
+    /// duration in hours
+    #[oai(validator(minimum(value = "0", exclusive = true)))]
+    pub duration: f64,
+    /// strength to attract the participants
+    #[oai(default = "default_event_strength")]
+    #[serde(default = "default_event_strength")]
+    pub strength: f64,
+    /// percentage of agents around who take part in
+    #[oai(validator(minimum(value = "0"), maximum(value = "100")))]
+    pub participation: f64,
+}
+
+fn default_event_strength() -> f64 {
+    80.0
 }
 
 #[derive(Object, Clone, Debug, serde::Deserialize, serde::Serialize)]
@@ -27,6 +575,36 @@ pub struct Operation {
     pub assignments: serde_json::Value,
 }
 
+/// An invalid part of a job.
+#[derive(Object, Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[oai(rename_all = "camelCase")]
+pub struct ParamError {
+    /// location such as `scenario[0].condition`; the whole job if omitted
+    pub path: Option<String>,
+    /// column of a condition where it fails to be parsed, counted from one
+    pub column: Option<u32>,
+    pub message: String,
+}
+
+impl ParamError {
+    pub fn new<M: std::fmt::Display>(path: Option<String>, message: M) -> Self {
+        Self {
+            path,
+            column: None,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for ParamError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "{path}: {}", self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use poem_openapi::types::ParseFromJSON;
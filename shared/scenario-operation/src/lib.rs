@@ -2,7 +2,7 @@ use std::str::FromStr;
 
 use nom::{branch::alt, character::complete::u32, combinator::map, error::Error, IResult, Parser};
 
-pub use predicate::EvalField;
+pub use predicate::{EvalField, PredicateError};
 use predicate::{binary_relation, FieldCombinator, Predicate};
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -150,17 +150,41 @@ impl_primitive_interpolate!(u32);
 impl_primitive_interpolate!(f32);
 impl_primitive_interpolate!(f64);
 
+/// switches to the new value at the last step
+impl Interpolate<bool> for bool {
+    type Target = bool;
+    fn interpolate<C: FromIterator<Self::Target>>(from: &Self, to: &bool, n: &u32) -> C {
+        (1..=*n).map(|i| if i < *n { *from } else { *to }).collect()
+    }
+}
+
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
 pub enum VaccinationStrategy {
     PerformRate(f64),
 }
 
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GatheringSpot {
+    Open(bool),
+    Weight(f64),
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GatheringEvent {
+    Cancelled(bool),
+    Participation(f64),
+}
+
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
 pub enum MyField {
     GatheringFrequency(f64),
     Vaccination(Container<usize, VaccinationStrategy>),
+    GatheringSpot(Container<usize, GatheringSpot>),
+    GatheringEvent(Container<usize, GatheringEvent>),
 }
 
 #[derive(Debug)]
@@ -0,0 +1,252 @@
+use world_if::api::job;
+
+/// gathering frequency of the world unless the scenario changes it
+const GATHERING_FREQUENCY: f64 = 50.0;
+/// samples needed to fit a model
+const MIN_SAMPLES: usize = 8;
+/// weight of the default model when fitting
+const RIDGE: f64 = 1e-3;
+
+const N_FEATURES: usize = 4;
+
+/// Features of a job which its running time depends on.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Cost {
+    pub population_size: u32,
+    /// number of cells of the mesh, zero if unknown
+    pub n_cells: f64,
+    /// largest frequency of gatherings during the run
+    pub gathering_frequency: f64,
+    /// steps to run, zero if unknown
+    pub n_steps: u64,
+}
+
+impl Cost {
+    /// Returns the cost of a job whose field is divided into `n_cells` cells
+    /// and whose world runs `steps_per_day` steps a day.
+    pub fn new(param: &job::JobParam, n_cells: u32, steps_per_day: u32) -> Self {
+        let mut cost = Self::of_world(&param.world_params, n_cells);
+        cost.n_steps = param.stop_at as u64 * steps_per_day as u64;
+        cost.gathering_frequency = param
+            .scenario
+            .iter()
+            .filter_map(|op| max_gathering_frequency(&op.assignments))
+            .fold(cost.gathering_frequency, f64::max);
+        cost
+    }
+
+    /// Returns the cost of a step of a world whose field is divided into `n_cells` cells,
+    /// with the gathering frequency unchanged and the steps unknown.
+    pub fn of_world(param: &job::WorldParams, n_cells: u32) -> Self {
+        let population_size = if param.regions.is_empty() {
+            param.population_size
+        } else {
+            param.regions.iter().map(|r| r.population).sum()
+        };
+        Self {
+            population_size,
+            n_cells: n_cells as f64,
+            gathering_frequency: GATHERING_FREQUENCY,
+            n_steps: 0,
+        }
+    }
+
+    /// Returns `[1, k, k * agents per cell, k * gatherings / 100]` where `k` is the population in thousands.
+    fn features(&self) -> [f64; N_FEATURES] {
+        let n = self.population_size as f64;
+        let k = n / 1000.0;
+        [
+            1.0,
+            k,
+            k * n / self.n_cells.max(1.0),
+            k * self.gathering_frequency / 100.0,
+        ]
+    }
+}
+
+/// Returns the largest gathering frequency assigned in the scenario.
+fn max_gathering_frequency(value: &serde_json::Value) -> Option<f64> {
+    match value {
+        serde_json::Value::Object(map) => map
+            .iter()
+            .filter_map(|(k, v)| match (k.as_str(), v) {
+                ("gatheringFrequency", v) => v.as_f64(),
+                (_, v) => max_gathering_frequency(v),
+            })
+            .reduce(f64::max),
+        serde_json::Value::Array(vs) => vs
+            .iter()
+            .filter_map(max_gathering_frequency)
+            .reduce(f64::max),
+        _ => None,
+    }
+}
+
+/// Linear model of the seconds a step of a job takes on the features of its cost.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct CostModel {
+    pub coefs: [f64; N_FEATURES],
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        Self {
+            coefs: [1e-4, 5e-4, 5e-5, 1e-4],
+        }
+    }
+}
+
+impl CostModel {
+    /// Returns the estimated seconds per step.
+    pub fn step_secs(&self, cost: &Cost) -> f64 {
+        let x = cost.features();
+        (0..N_FEATURES)
+            .map(|i| self.coefs[i] * x[i])
+            .sum::<f64>()
+            .max(f64::EPSILON)
+    }
+
+    /// Returns the estimated seconds to run all the steps.
+    pub fn secs(&self, cost: &Cost) -> f64 {
+        self.step_secs(cost) * cost.n_steps as f64
+    }
+
+    /// Fits a model to measured seconds per step by least squares pulled toward the default,
+    /// which is returned as is if there are too few samples.
+    pub fn fit(samples: &[(Cost, f64)]) -> Self {
+        let default = Self::default();
+        if samples.len() < MIN_SAMPLES {
+            return default;
+        }
+        // normal equations (X^T X + λI) c = X^T y + λ c_0
+        let mut a = [[0.0; N_FEATURES]; N_FEATURES];
+        let mut b = [0.0; N_FEATURES];
+        for (cost, secs) in samples {
+            let x = cost.features();
+            for i in 0..N_FEATURES {
+                for j in 0..N_FEATURES {
+                    a[i][j] += x[i] * x[j];
+                }
+                b[i] += x[i] * secs;
+            }
+        }
+        for i in 0..N_FEATURES {
+            a[i][i] += RIDGE;
+            b[i] += RIDGE * default.coefs[i];
+        }
+        match solve(a, b) {
+            Some(coefs) => Self {
+                coefs: coefs.map(|c| c.max(0.0)),
+            },
+            None => default,
+        }
+    }
+}
+
+/// Solves `a x = b` by Gaussian elimination with partial pivoting.
+fn solve(
+    mut a: [[f64; N_FEATURES]; N_FEATURES],
+    mut b: [f64; N_FEATURES],
+) -> Option<[f64; N_FEATURES]> {
+    for i in 0..N_FEATURES {
+        let p = (i..N_FEATURES).max_by(|&j, &k| a[j][i].abs().total_cmp(&a[k][i].abs()))?;
+        if a[p][i].abs() < f64::EPSILON {
+            return None;
+        }
+        a.swap(i, p);
+        b.swap(i, p);
+        let pivot = a[i];
+        for j in (i + 1)..N_FEATURES {
+            let f = a[j][i] / pivot[i];
+            for (x, y) in a[j][i..].iter_mut().zip(&pivot[i..]) {
+                *x -= f * y;
+            }
+            b[j] -= f * b[i];
+        }
+    }
+    let mut x = [0.0; N_FEATURES];
+    for i in (0..N_FEATURES).rev() {
+        let s = ((i + 1)..N_FEATURES).map(|k| a[i][k] * x[k]).sum::<f64>();
+        x[i] = (b[i] - s) / a[i][i];
+    }
+    Some(x)
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct ResourceMeasure {
+    pub max_cost: Cost,
+    pub max_resource: u32,
+    /// model calibrated by the worker
+    pub model: CostModel,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ResourceSizeError {
+    #[error("cost exceeds the maximum resource")]
+    ExceedMaxResource,
+}
+
+impl ResourceMeasure {
+    pub fn new(max_cost: Cost, max_resource: u32, model: CostModel) -> Self {
+        Self {
+            max_cost,
+            max_resource,
+            model,
+        }
+    }
+
+    /// Returns the number of resources for a job in proportion to its estimated seconds per step.
+    pub fn measure(&self, cost: &Cost) -> Result<u32, ResourceSizeError> {
+        let step_secs = self.model.step_secs(cost);
+        let max_step_secs = self.model.step_secs(&self.max_cost);
+        if cost.population_size > self.max_cost.population_size || step_secs > max_step_secs {
+            return Err(ResourceSizeError::ExceedMaxResource);
+        }
+        let k = self.max_resource as f64 * step_secs / max_step_secs;
+        Ok((k.floor() as u32).max(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cost, CostModel};
+
+    fn cost(population_size: u32, n_cells: f64, gathering_frequency: f64) -> Cost {
+        Cost {
+            population_size,
+            n_cells,
+            gathering_frequency,
+            n_steps: 1,
+        }
+    }
+
+    #[test]
+    fn test_fit() {
+        let truth = CostModel {
+            coefs: [2e-4, 1e-3, 2e-5, 3e-4],
+        };
+        let samples = [1000, 5000, 10000, 20000]
+            .into_iter()
+            .flat_map(|n| {
+                [
+                    (n, 324.0, 50.0),
+                    (n, n as f64 / 16.0, 100.0),
+                    (n, 900.0, 0.0),
+                ]
+            })
+            .map(|(n, c, g)| {
+                let cost = cost(n, c, g);
+                let secs = truth.step_secs(&cost);
+                (cost, secs)
+            })
+            .collect::<Vec<_>>();
+        let model = CostModel::fit(&samples);
+        for (cost, secs) in &samples {
+            assert!((model.step_secs(cost) - secs).abs() / secs < 0.05);
+        }
+        assert_eq!(
+            CostModel::fit(&samples[..2]).coefs,
+            CostModel::default().coefs
+        );
+    }
+}
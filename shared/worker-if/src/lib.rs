@@ -1,13 +1,20 @@
+mod cost;
+mod load;
+
 use std::fmt::Display;
 
 use ::world_if::api::job;
 
+pub use cost::{Cost, CostModel, ResourceMeasure, ResourceSizeError};
+pub use load::{Capacity, Load, WorkerInfo};
+
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub enum Request {
-    Execute(String, job::JobParam),
+    Execute(String, Box<job::JobParam>),
     Terminate(String),
     ReadStatistics(String),
     RemoveStatistics(Vec<String>),
+    ReadLog(String),
 }
 
 impl Display for Request {
@@ -17,6 +24,36 @@ impl Display for Request {
             Request::Terminate(_) => write!(f, "terminate"),
             Request::ReadStatistics(_) => write!(f, "read statistics"),
             Request::RemoveStatistics(_) => write!(f, "remove statistics"),
+            Request::ReadLog(_) => write!(f, "read log"),
+        }
+    }
+}
+
+/// How the world of a task has ended.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TaskExit {
+    pub code: Option<i32>,
+    /// signal which killed the process
+    pub signal: Option<i32>,
+    /// error or panic of a world run in process
+    pub error: Option<String>,
+}
+
+impl TaskExit {
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+}
+
+impl Display for TaskExit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self { error: Some(e), .. } => write!(f, "error: {e}"),
+            Self {
+                signal: Some(s), ..
+            } => write!(f, "signal {s}"),
+            Self { code: Some(c), .. } => write!(f, "exit code {c}"),
+            _ => write!(f, "unknown exit"),
         }
     }
 }
@@ -44,61 +81,6 @@ impl<T, E: std::error::Error> From<Result<T, E>> for Response<T> {
     }
 }
 
-#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
-pub struct Cost(u64);
-
-impl From<&job::JobParam> for Cost {
-    fn from(value: &job::JobParam) -> Self {
-        (&value.world_params).into()
-    }
-}
-
-impl From<&job::WorldParams> for Cost {
-    fn from(value: &job::WorldParams) -> Self {
-        Self((value.population_size as u64).pow(2))
-    }
-}
-
-impl From<job::WorldParams> for Cost {
-    fn from(value: job::WorldParams) -> Self {
-        Self((value.population_size as u64).pow(2))
-    }
-}
-
-#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
-pub struct ResourceMeasure {
-    pub max_cost: Cost,
-    pub max_resource: u32,
-}
-
-#[derive(thiserror::Error, Debug)]
-pub enum ResourceSizeError {
-    #[error("cost exceeds the maximum resource")]
-    ExceedMaxResource,
-}
-
-impl ResourceMeasure {
-    pub fn new(max_param: job::WorldParams, max_resource: u32) -> Self {
-        Self {
-            max_cost: max_param.into(),
-            max_resource,
-        }
-    }
-
-    pub fn measure(&self, cost: &Cost) -> Result<u32, ResourceSizeError> {
-        if cost.0 > self.max_cost.0 {
-            return Err(ResourceSizeError::ExceedMaxResource);
-        }
-
-        let k = self.max_resource as u64 * cost.0;
-        if k <= self.max_cost.0 {
-            Ok(1)
-        } else {
-            Ok((k / self.max_cost.0) as u32)
-        }
-    }
-}
-
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub enum ResponseOk {
     Item,
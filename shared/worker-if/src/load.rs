@@ -0,0 +1,36 @@
+use crate::ResourceMeasure;
+
+/// Hardware of a worker and how it is shared by the tasks.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Capacity {
+    pub n_cpus: u32,
+    /// bytes of physical memory
+    pub total_memory: u64,
+    pub max_concurrent_tasks: u32,
+    /// threads of the pool each task runs its steps on
+    pub threads_per_task: u32,
+}
+
+/// Sent by a worker first when it is connected.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct WorkerInfo {
+    pub measure: ResourceMeasure,
+    pub capacity: Capacity,
+}
+
+/// Utilisation of a worker, which is reported periodically.
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct Load {
+    pub running_tasks: u32,
+    /// load average over the last minute
+    pub load_average: f64,
+    /// bytes of memory available without swapping
+    pub available_memory: u64,
+}
+
+impl Load {
+    /// Returns the load average per CPU, which exceeds one if the worker is oversubscribed.
+    pub fn cpu_usage(&self, capacity: &Capacity) -> f64 {
+        self.load_average / capacity.n_cpus.max(1) as f64
+    }
+}
@@ -1,10 +1,10 @@
-use std::fmt;
+use std::{fmt, sync::mpsc};
 
 pub use api;
 use chrono::serde::ts_seconds;
 pub use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
 use ipc_channel::ipc::{IpcBytesReceiver, IpcBytesSender};
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Serialize};
 
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 pub enum Request {
@@ -78,6 +78,12 @@ pub struct WorldStatus {
     custom: String,
     #[serde(with = "ts_seconds")]
     time_stamp: chrono::DateTime<chrono::Utc>,
+    /// mean seconds spent in each phase of a step, empty unless profiling
+    phase_secs: Vec<(String, f64)>,
+    /// mean seconds the world has spent in a step, zero before the first one
+    step_secs: f64,
+    /// number of cells the field of the world is divided into
+    n_cells: u32,
 }
 
 impl WorldStatus {
@@ -87,8 +93,37 @@ impl WorldStatus {
             state,
             custom,
             time_stamp: chrono::Utc::now(),
+            phase_secs: Vec::new(),
+            step_secs: 0.0,
+            n_cells: 0,
         }
     }
+
+    #[inline]
+    pub fn step(&self) -> u32 {
+        self.step
+    }
+
+    pub fn with_phase_secs(mut self, phase_secs: Vec<(String, f64)>) -> Self {
+        self.phase_secs = phase_secs;
+        self
+    }
+
+    pub fn with_step_secs(mut self, step_secs: f64, n_cells: u32) -> Self {
+        self.step_secs = step_secs;
+        self.n_cells = n_cells;
+        self
+    }
+
+    #[inline]
+    pub fn step_secs(&self) -> f64 {
+        self.step_secs
+    }
+
+    #[inline]
+    pub fn n_cells(&self) -> u32 {
+        self.n_cells
+    }
 }
 
 impl fmt::Display for WorldStatus {
@@ -111,21 +146,101 @@ impl IpcBiConnection {
     pub fn new(tx: IpcBytesSender, rx: IpcBytesReceiver) -> Self {
         Self { tx, rx }
     }
+}
+
+/// Transport of serialized requests and responses between a worker and a world.
+pub trait BiConnection: Send {
+    fn send_bytes(&self, data: Vec<u8>) -> anyhow::Result<()>;
+    fn recv_bytes(&self) -> anyhow::Result<Vec<u8>>;
+    fn try_recv_bytes(&self) -> anyhow::Result<Option<Vec<u8>>>;
+}
+
+/// Typed messages over a [`BiConnection`].
+pub trait BiConnectionExt: BiConnection {
+    fn send<T: Serialize>(&self, data: &T) -> anyhow::Result<()> {
+        self.send_bytes(bincode::serialize(data)?)
+    }
+
+    fn recv<T: DeserializeOwned>(&self) -> anyhow::Result<T> {
+        Ok(bincode::deserialize(&self.recv_bytes()?)?)
+    }
+
+    fn try_recv<T: DeserializeOwned>(&self) -> anyhow::Result<Option<T>> {
+        match self.try_recv_bytes()? {
+            Some(bytes) => Ok(Some(bincode::deserialize(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+}
 
-    pub fn send<T: Serialize>(&self, data: &T) -> anyhow::Result<()> {
-        self.tx.send(&bincode::serialize(data)?)?;
+impl<C: BiConnection + ?Sized> BiConnectionExt for C {}
+
+impl BiConnection for IpcBiConnection {
+    fn send_bytes(&self, data: Vec<u8>) -> anyhow::Result<()> {
+        self.tx.send(&data)?;
         Ok(())
     }
 
-    pub fn recv<T: for<'de> Deserialize<'de>>(&self) -> anyhow::Result<T> {
-        Ok(bincode::deserialize(&self.rx.recv()?)?)
+    fn recv_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(self.rx.recv()?)
     }
 
-    pub fn try_recv<T: for<'de> Deserialize<'de>>(&self) -> anyhow::Result<Option<T>> {
+    fn try_recv_bytes(&self) -> anyhow::Result<Option<Vec<u8>>> {
         match self.rx.try_recv() {
-            Ok(bytes) => Ok(Some(bincode::deserialize(&bytes)?)),
+            Ok(bytes) => Ok(Some(bytes)),
             Err(ipc::TryRecvError::Empty) => Ok(None),
             Err(ipc::TryRecvError::IpcError(e)) => Err(e.into()),
         }
     }
 }
+
+/// Connection within a process, which carries the same messages as [`IpcBiConnection`].
+pub struct LocalBiConnection {
+    tx: mpsc::Sender<Vec<u8>>,
+    rx: mpsc::Receiver<Vec<u8>>,
+}
+
+impl LocalBiConnection {
+    /// Returns both ends of a connection.
+    pub fn pair() -> (Self, Self) {
+        let (tx0, rx0) = mpsc::channel();
+        let (tx1, rx1) = mpsc::channel();
+        (Self { tx: tx0, rx: rx1 }, Self { tx: tx1, rx: rx0 })
+    }
+}
+
+impl BiConnection for LocalBiConnection {
+    fn send_bytes(&self, data: Vec<u8>) -> anyhow::Result<()> {
+        self.tx.send(data)?;
+        Ok(())
+    }
+
+    fn recv_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(self.rx.recv()?)
+    }
+
+    fn try_recv_bytes(&self) -> anyhow::Result<Option<Vec<u8>>> {
+        match self.rx.try_recv() {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(mpsc::TryRecvError::Empty) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Stream of the statuses of a world.
+pub trait StatusSender: Send {
+    fn send_status(&self, status: WorldStatus) -> anyhow::Result<()>;
+}
+
+impl StatusSender for IpcSender<WorldStatus> {
+    fn send_status(&self, status: WorldStatus) -> anyhow::Result<()> {
+        Ok(self.send(status)?)
+    }
+}
+
+impl StatusSender for mpsc::Sender<WorldStatus> {
+    fn send_status(&self, status: WorldStatus) -> anyhow::Result<()> {
+        Ok(self.send(status)?)
+    }
+}
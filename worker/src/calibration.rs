@@ -0,0 +1,47 @@
+use std::{fs, path::PathBuf};
+
+use worker_if::{Cost, CostModel};
+
+/// samples kept, the oldest are dropped first
+const MAX_SAMPLES: usize = 256;
+
+/// Seconds per step measured in the tasks run by this worker,
+/// which are saved in a file to calibrate the cost model across restarts.
+pub struct Calibration {
+    path: PathBuf,
+    samples: Vec<(Cost, f64)>,
+}
+
+impl Calibration {
+    /// Loads the samples saved in `path`, starting from none if it cannot be read.
+    pub fn load(path: PathBuf) -> Self {
+        let samples = match fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|e| {
+                tracing::warn!("ignored calibration in {} due to {e}", path.display());
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        Self { path, samples }
+    }
+
+    pub fn model(&self) -> CostModel {
+        CostModel::fit(&self.samples)
+    }
+
+    pub fn record(&mut self, cost: Cost, step_secs: f64) {
+        self.samples.push((cost, step_secs));
+        if self.samples.len() > MAX_SAMPLES {
+            self.samples.drain(..(self.samples.len() - MAX_SAMPLES));
+        }
+        let res = serde_json::to_vec(&self.samples)
+            .map_err(anyhow::Error::from)
+            .and_then(|data| Ok(fs::write(&self.path, data)?));
+        if let Err(e) = res {
+            tracing::warn!(
+                "could not save calibration to {} due to {e}",
+                self.path.display()
+            );
+        }
+    }
+}
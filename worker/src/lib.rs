@@ -1,11 +1,27 @@
-use std::{collections::BTreeMap, fs::File, io, path::PathBuf, process, sync::Arc};
+mod calibration;
+pub mod system;
+
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom, Write},
+    os::unix::process::{CommandExt, ExitStatusExt},
+    path::PathBuf,
+    process,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        mpsc, Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
 
 use arrow2::io::{
     csv::write::{self, SerializeOptions},
     ipc::read,
 };
 use futures_util::SinkExt;
-use ipc_channel::ipc::IpcOneShotServer;
+use ipc_channel::ipc::{IpcOneShotServer, IpcSender};
 use parking_lot::Mutex;
 use quinn::Connection;
 use serde::{Deserialize, Serialize};
@@ -14,22 +30,31 @@ use tokio_util::codec::{FramedWrite, LengthDelimitedCodec};
 
 use worker_if::{
     self,
-    world_if::{self, api::job, IpcBiConnection},
-    ResourceMeasure, ResourceSizeError,
+    world_if::{
+        self, api::job, BiConnection, BiConnectionExt, IpcBiConnection, LocalBiConnection,
+        WorldStatus,
+    },
+    Capacity, Cost, CostModel, Load, ResourceMeasure, ResourceSizeError, TaskExit, WorkerInfo,
 };
 
+use calibration::Calibration;
+
 #[derive(Debug, thiserror::Error)]
 pub enum ResponseError {
     #[error("Parameter cost exceeded the maximum resource")]
     ParamSizeExceeded(#[from] ResourceSizeError),
     #[error("Failed to execute process")]
     FailedToExecute(#[from] IpcServerConnectionError),
+    #[error("Failed to spawn a thread: {0}")]
+    FailedToSpawnThread(io::Error),
     #[error("Ipc error has occured: {0}")]
     IpcError(#[from] anyhow::Error),
     #[error("Some error has occured in the child process: {0}")]
     FailedInProcess(#[from] world_if::Error),
     #[error("No id found")]
     NoIdFound,
+    #[error("Already running the maximum number of tasks")]
+    TooManyTasks,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -46,19 +71,26 @@ pub async fn run(
     max_population_size: u32,
     max_resource: u32,
 ) -> anyhow::Result<()> {
+    let max_param = job::WorldParams {
+        population_size: max_population_size,
+        ..Default::default()
+    };
+    let max_cost = Cost::of_world(
+        &max_param,
+        world::n_cells(&max_param, manager.capacity.threads_per_task as usize)?,
+    );
     let mut send = connection.open_uni().await?;
     protocol::quic::write_data(
         &mut send,
-        &ResourceMeasure::new(
-            job::WorldParams {
-                population_size: max_population_size,
-                ..Default::default()
-            },
-            max_resource,
-        ),
+        &WorkerInfo {
+            measure: ResourceMeasure::new(max_cost, max_resource, manager.cost_model()),
+            capacity: manager.capacity.clone(),
+        },
     )
     .await?;
 
+    let report = tokio::spawn(report_load(manager.clone(), connection.clone()));
+
     while let Ok((mut send, mut recv)) = connection.accept_bi().await {
         let manager = manager.clone();
         tokio::spawn(async move {
@@ -67,8 +99,8 @@ pub async fn run(
             match req {
                 worker_if::Request::Execute(id, param) => {
                     let mut stream = FramedWrite::new(send, LengthDelimitedCodec::new());
-                    let (res, child) = match manager.execute(id, &param).await {
-                        Ok(child) => (worker_if::Response::<()>::from_ok(()), Some(child)),
+                    let (res, task) = match manager.execute(id.clone(), &param).await {
+                        Ok(task) => (worker_if::Response::<()>::from_ok(()), Some(task)),
                         Err(e) => (worker_if::Response::<()>::from_err(e), None),
                     };
                     stream
@@ -76,11 +108,15 @@ pub async fn run(
                         .await
                         .unwrap();
 
-                    if let Some(child) = child {
+                    if let Some(task) = task {
                         tokio::spawn(async move {
-                            let status = child.wait().unwrap();
+                            let exit = tokio::task::spawn_blocking(move || task.wait())
+                                .await
+                                .unwrap();
+                            manager.finish_task();
+                            manager.record_exit(&id, &exit);
                             stream
-                                .send(bincode::serialize(&status.success()).unwrap().into())
+                                .send(bincode::serialize(&exit).unwrap().into())
                                 .await
                                 .unwrap();
                         });
@@ -103,6 +139,7 @@ pub async fn run(
                         .into_iter()
                         .filter(|id| {
                             let path = manager.stat_dir_path.join(&id).with_extension("arrow");
+                            let _ = std::fs::remove_file(manager.log_path(id));
                             match std::fs::remove_file(path) {
                                 Ok(_) => {
                                     tracing::info!("removed {id}.arrow");
@@ -119,17 +156,119 @@ pub async fn run(
                         .await
                         .unwrap();
                 }
+                worker_if::Request::ReadLog(id) => {
+                    let res = worker_if::Response::from(manager.read_log(&id));
+                    let mut stream = FramedWrite::new(send, LengthDelimitedCodec::new());
+                    stream
+                        .send(bincode::serialize(&res).unwrap().into())
+                        .await
+                        .unwrap();
+                }
             };
         });
     }
+    report.abort();
     Ok(())
 }
 
+/// interval between load reports
+const LOAD_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Reports the load of this worker periodically until the connection is closed.
+async fn report_load(manager: Arc<WorldManager>, connection: Connection) {
+    let mut interval = tokio::time::interval(LOAD_INTERVAL);
+    loop {
+        interval.tick().await;
+        let res = async {
+            let mut send = connection.open_uni().await?;
+            protocol::quic::write_data(&mut send, &manager.load()).await?;
+            anyhow::Ok(())
+        };
+        if let Err(e) = res.await {
+            tracing::warn!("stopped reporting load due to {e}");
+            break;
+        }
+    }
+}
+
+/// How the worlds of the tasks are run.
+pub enum Execution {
+    /// in a `world` process spawned for each task
+    Process { world_path: String, limits: Limits },
+    /// in a thread of this worker with its own thread pool for each task
+    InProcess,
+}
+
+/// Resource limits of each `world` process, which are unlimited if `None`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Limits {
+    /// bytes of virtual memory
+    pub memory: Option<u64>,
+    /// seconds of CPU time
+    pub cpu_time: Option<u64>,
+}
+
+/// interval to check if a world process has exited before connecting
+const ACCEPT_POLL: Duration = Duration::from_millis(100);
+
+/// bytes read from the end of a log at most
+const MAX_LOG_SIZE: u64 = 1 << 20;
+
+/// Receives the next status of a world, or `None` if it has ended.
+type StatusReceiver = Box<dyn Fn() -> Option<WorldStatus> + Send>;
+
+/// A world running for a task.
+enum TaskHandle {
+    Process(SharedChild),
+    Thread(JoinHandle<anyhow::Result<()>>),
+}
+
+impl TaskHandle {
+    /// Waits for the world to end.
+    fn wait(self) -> TaskExit {
+        match self {
+            TaskHandle::Process(child) => match child.wait() {
+                Ok(status) => TaskExit {
+                    code: status.code(),
+                    signal: status.signal(),
+                    error: None,
+                },
+                Err(e) => TaskExit {
+                    code: None,
+                    signal: None,
+                    error: Some(e.to_string()),
+                },
+            },
+            TaskHandle::Thread(handle) => {
+                let error = match handle.join() {
+                    Ok(Ok(())) => None,
+                    Ok(Err(e)) => Some(e.to_string()),
+                    Err(panic) => Some(match panic.downcast::<String>() {
+                        Ok(msg) => format!("panicked at {msg}"),
+                        Err(panic) => match panic.downcast::<&str>() {
+                            Ok(msg) => format!("panicked at {msg}"),
+                            Err(_) => "panicked".to_string(),
+                        },
+                    }),
+                };
+                TaskExit {
+                    code: error.is_none().then_some(0),
+                    signal: None,
+                    error,
+                }
+            }
+        }
+    }
+}
+
 pub struct WorldManager {
-    world_path: String,
+    execution: Execution,
     stat_dir: String,
     stat_dir_path: PathBuf,
-    table: Mutex<BTreeMap<String, IpcBiConnection>>,
+    table: Mutex<BTreeMap<String, Box<dyn BiConnection>>>,
+    calibration: Arc<Mutex<Calibration>>,
+    capacity: Capacity,
+    running_tasks: AtomicU32,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -138,48 +277,162 @@ pub enum IpcServerConnectionError {
     IpcServer(io::Error),
     #[error("Failed to spawn child: {0}")]
     ChildProcess(io::Error),
+    #[error("Failed to create a log file: {0}")]
+    LogFile(io::Error),
+    #[error("Child exited before connecting: {0}")]
+    Exited(process::ExitStatus),
     #[error("Failed to connect by IO: {0}")]
     FailedToConnect(bincode::Error),
 }
 
-fn request(bicon: &IpcBiConnection, req: world_if::Request) -> anyhow::Result<world_if::Response> {
+fn request(bicon: &dyn BiConnection, req: world_if::Request) -> anyhow::Result<world_if::Response> {
     bicon.send(&req)?;
     Ok(bicon.recv()?)
 }
 
 impl WorldManager {
-    pub fn new(world_path: String, stat_dir: String, stat_dir_path: PathBuf) -> Arc<Self> {
+    pub fn new(
+        execution: Execution,
+        stat_dir: String,
+        stat_dir_path: PathBuf,
+        calibration_path: PathBuf,
+        capacity: Capacity,
+    ) -> Arc<Self> {
         Arc::new(Self {
-            world_path,
+            execution,
             stat_dir,
             stat_dir_path,
             table: Default::default(),
+            calibration: Arc::new(Mutex::new(Calibration::load(calibration_path))),
+            capacity,
+            running_tasks: AtomicU32::new(0),
         })
     }
 
+    /// Returns the current load, whose system figures are zero if they cannot be read.
+    pub fn load(&self) -> Load {
+        Load {
+            running_tasks: self.running_tasks.load(Ordering::Acquire),
+            load_average: system::load_average().unwrap_or_default(),
+            available_memory: system::memory().map_or(0, |(_, available)| available),
+        }
+    }
+
+    fn finish_task(&self) {
+        self.running_tasks.fetch_sub(1, Ordering::AcqRel);
+    }
+
+    fn log_path(&self, world_id: &str) -> PathBuf {
+        self.stat_dir_path.join(world_id).with_extension("log")
+    }
+
+    /// Appends how the world has ended to its log.
+    fn record_exit(&self, world_id: &str, exit: &TaskExit) {
+        if exit.success() {
+            tracing::info!("{world_id} ended with {exit}");
+        } else {
+            tracing::error!("{world_id} ended with {exit}");
+        }
+        let res = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.log_path(world_id))
+            .and_then(|mut file| writeln!(file, "world ended with {exit}"));
+        if let Err(e) = res {
+            tracing::warn!("could not write the log of {world_id} due to {e}");
+        }
+    }
+
+    /// Returns the end of the log of a task.
+    fn read_log(&self, world_id: &str) -> io::Result<Vec<u8>> {
+        let mut file = File::open(self.log_path(world_id))?;
+        let len = file.metadata()?.len();
+        file.seek(SeekFrom::Start(len.saturating_sub(MAX_LOG_SIZE)))?;
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Returns the cost model calibrated by the tasks run so far.
+    pub fn cost_model(&self) -> CostModel {
+        self.calibration.lock().model()
+    }
+
+    /// Spawns a world for a task unless the maximum number of tasks are already running.
     async fn execute(
         &self,
         world_id: String,
         param: &world_if::api::job::JobParam,
-    ) -> Result<SharedChild, ResponseError> {
-        let ((bicon, stream), child) = self.connect_ipc_server::<(
-            IpcBiConnection,
-            world_if::IpcReceiver<world_if::WorldStatus>,
-        )>(&world_id)?;
+    ) -> Result<TaskHandle, ResponseError> {
+        let max = self.capacity.max_concurrent_tasks;
+        self.running_tasks
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                (n < max).then_some(n + 1)
+            })
+            .map_err(|_| ResponseError::TooManyTasks)?;
+        let res = self.spawn_world(world_id, param).await;
+        if res.is_err() {
+            self.finish_task();
+        }
+        res
+    }
+
+    async fn spawn_world(
+        &self,
+        world_id: String,
+        param: &world_if::api::job::JobParam,
+    ) -> Result<TaskHandle, ResponseError> {
+        let (bicon, stream, task): (Box<dyn BiConnection>, StatusReceiver, _) =
+            match &self.execution {
+                Execution::Process { world_path, limits } => {
+                    let ((bicon, stream), child) = self.connect_ipc_server::<(
+                        IpcBiConnection,
+                        world_if::IpcReceiver<WorldStatus>,
+                    )>(
+                        world_path, limits, &world_id
+                    )?;
+                    (
+                        Box::new(bicon),
+                        Box::new(move || stream.recv().ok()),
+                        TaskHandle::Process(child),
+                    )
+                }
+                Execution::InProcess => {
+                    let (bicon, world_bicon) = LocalBiConnection::pair();
+                    let (tx, rx) = mpsc::channel();
+                    let handle = self
+                        .spawn_thread(&world_id, world_bicon, tx)
+                        .map_err(ResponseError::FailedToSpawnThread)?;
+                    (
+                        Box::new(bicon),
+                        Box::new(move || rx.recv().ok()),
+                        TaskHandle::Thread(handle),
+                    )
+                }
+            };
         let mut table = self.table.lock();
         bicon.send(param)?;
         let bicon = table.entry(world_id.clone()).or_insert(bicon);
 
-        tokio::spawn(async move {
+        let calibration = self.calibration.clone();
+        // the mesh is taken from the world once it has run
+        let mut cost = Cost::new(param, 0, world::STEPS_PER_DAY);
+        // the statuses are received by blocking until the world ends
+        tokio::task::spawn_blocking(move || {
             let mut status_hist = Vec::new();
-            while let Ok(status) = stream.recv() {
+            while let Some(status) = stream() {
                 status_hist.push(status);
             }
             tracing::debug!("status: {:?}", status_hist.last());
+            // the world reports the time spent in its steps, excluding the time it waits
+            if let Some(status) = status_hist.last().filter(|s| s.step_secs() > 0.0) {
+                cost.n_cells = status.n_cells() as f64;
+                calibration.lock().record(cost, status.step_secs());
+            }
         });
 
-        match request(&bicon, world_if::Request::Execute)? {
-            world_if::Response::Ok(_) => Ok(child),
+        match request(bicon.as_ref(), world_if::Request::Execute)? {
+            world_if::Response::Ok(_) => Ok(task),
             world_if::Response::Err(e) => Err(e.into()),
         }
     }
@@ -187,9 +440,9 @@ impl WorldManager {
     async fn terminate(&self, world_id: String) -> Result<(), ResponseError> {
         let table = self.table.lock();
         let Some(bicon) = table.get(&world_id) else {
-            return Err(ResponseError::NoIdFound)
+            return Err(ResponseError::NoIdFound);
         };
-        match request(bicon, world_if::Request::Terminate)? {
+        match request(bicon.as_ref(), world_if::Request::Terminate)? {
             world_if::Response::Ok(_) => Ok(()),
             world_if::Response::Err(e) => Err(e.into()),
         }
@@ -217,13 +470,39 @@ impl WorldManager {
         Ok(buf)
     }
 
-    fn connect_ipc_server<T: for<'de> Deserialize<'de> + Serialize>(
+    /// Spawns a thread running a world on a thread pool of `threads_per_task` threads.
+    fn spawn_thread(
+        &self,
+        world_id: &str,
+        bicon: LocalBiConnection,
+        stream: mpsc::Sender<WorldStatus>,
+    ) -> io::Result<JoinHandle<anyhow::Result<()>>> {
+        let id = world_id.to_string();
+        let stat_dir = self.stat_dir.clone();
+        let threads = self.capacity.threads_per_task as usize;
+        thread::Builder::new()
+            .name(format!("world_{world_id}"))
+            .spawn(move || {
+                let pool = rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build()?;
+                pool.install(|| world::WorldSpawner::new(id, bicon, stream, stat_dir)?.listen())
+            })
+    }
+
+    /// Spawns a `world` process whose output is written to its log.
+    fn connect_ipc_server<T: for<'de> Deserialize<'de> + Serialize + Send + 'static>(
         &self,
+        world_path: &str,
+        limits: &Limits,
         world_id: &str,
     ) -> Result<(T, SharedChild), IpcServerConnectionError> {
+        let log =
+            File::create(self.log_path(world_id)).map_err(IpcServerConnectionError::LogFile)?;
+        let log_err = log.try_clone().map_err(IpcServerConnectionError::LogFile)?;
         let (server, server_name) =
             IpcOneShotServer::<T>::new().map_err(IpcServerConnectionError::IpcServer)?;
-        let mut command = process::Command::new(&self.world_path);
+        let mut command = process::Command::new(world_path);
         command.args([
             "--world-id",
             world_id,
@@ -231,13 +510,40 @@ impl WorldManager {
             &server_name,
             "--stat-dir",
             &self.stat_dir,
+            "--threads",
+            &self.capacity.threads_per_task.to_string(),
         ]);
+        command.stdout(log).stderr(log_err);
+        let limits = *limits;
+        // SAFETY: setrlimit is async-signal-safe and nothing is allocated
+        unsafe {
+            command.pre_exec(move || system::set_limits(&limits));
+        }
         let child = shared_child::SharedChild::spawn(&mut command)
             .map_err(IpcServerConnectionError::ChildProcess)?;
-        let (_, value) = server
-            .accept()
-            .map_err(IpcServerConnectionError::FailedToConnect)?;
-        Ok((value, child))
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || tx.send(server.accept()));
+        loop {
+            match rx.recv_timeout(ACCEPT_POLL) {
+                Ok(res) => {
+                    let (_, value) = res.map_err(IpcServerConnectionError::FailedToConnect)?;
+                    return Ok((value, child));
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    let status = child
+                        .try_wait()
+                        .map_err(IpcServerConnectionError::ChildProcess)?;
+                    if let Some(status) = status {
+                        // unblocks the accepting thread by a message which fails to be deserialized
+                        if let Ok(tx) = IpcSender::<()>::connect(server_name) {
+                            let _ = tx.send(());
+                        }
+                        return Err(IpcServerConnectionError::Exited(status));
+                    }
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => unreachable!(),
+            }
+        }
     }
 }
 
@@ -246,6 +552,50 @@ mod tests {
     use std::{fs::File, path::Path};
 
     use arrow2::io::ipc::read;
+    use worker_if::{world_if::api::job, Capacity};
+
+    use super::{Execution, WorldManager};
+
+    #[tokio::test]
+    async fn test_execute_in_process() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join("worker_test_execute_in_process");
+        std::fs::create_dir_all(&dir)?;
+        let manager = WorldManager::new(
+            Execution::InProcess,
+            dir.to_str().unwrap().to_string(),
+            dir.clone(),
+            dir.join("calibration.json"),
+            Capacity {
+                n_cpus: 2,
+                total_memory: 0,
+                max_concurrent_tasks: 1,
+                threads_per_task: 2,
+            },
+        );
+        let param = job::JobParam {
+            stop_at: 1,
+            world_params: job::WorldParams {
+                population_size: 100,
+                infected: 10.0,
+                ..Default::default()
+            },
+            scenario: Vec::new(),
+            tracing: None,
+            home_quarantine: None,
+            hospital: None,
+            immunity: None,
+            vaccination: None,
+            gathering_spots: None,
+            gathering_events: Vec::new(),
+            travel: None,
+            profiling: false,
+        };
+        let task = manager.execute("in_process".to_string(), &param).await?;
+        assert!(manager.execute("second".to_string(), &param).await.is_err());
+        assert!(task.wait().success());
+        assert!(dir.join("in_process.arrow").exists());
+        Ok(())
+    }
 
     #[test]
     fn test_read_arrow() -> anyhow::Result<()> {
@@ -1,4 +1,10 @@
-use std::{error::Error, net::SocketAddr, path::Path, sync::Arc, time::Duration};
+use std::{
+    error::Error,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
 use clap::Parser;
 use quinn::{crypto, Endpoint, ServerConfig, TransportConfig, VarInt};
@@ -14,8 +20,14 @@ struct Config {
     cert_path: String,
     /// path of private key file
     pkey_path: String,
-    /// world binary path
-    world_path: String,
+    /// world binary path, which is unused if `in_process`
+    #[serde(default)]
+    world_path: Option<String>,
+    /// whether worlds run in threads of the worker instead of `world` processes,
+    /// in which case their output goes to the log of the worker
+    /// and `<id>.log` in `stat_dir` only records how each task ended
+    #[serde(default)]
+    in_process: bool,
     /// address to listen
     addr: SocketAddr,
     /// max population size
@@ -24,32 +36,87 @@ struct Config {
     max_resource: u32,
     /// directory where statistics are saved
     stat_dir: String,
+    /// file where measured step times are saved, `calibration.json` in `stat_dir` if omitted
+    #[serde(default)]
+    calibration_path: Option<String>,
+    /// threads of each task, the smaller of 4 and the number of CPUs if omitted
+    #[serde(default)]
+    threads_per_task: Option<u32>,
+    /// tasks run at once, as many as the CPUs are shared by if omitted
+    #[serde(default)]
+    max_concurrent_tasks: Option<u32>,
+    /// megabytes of virtual memory of each world process, unlimited if omitted
+    #[serde(default)]
+    memory_limit_mb: Option<u64>,
+    /// seconds of CPU time of each world process, unlimited if omitted
+    #[serde(default)]
+    cpu_time_limit_secs: Option<u64>,
 }
 
+/// threads of each task unless configured
+const THREADS_PER_TASK: u32 = 4;
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
     let Args { config_path } = Args::parse();
+    tracing_subscriber::fmt::init();
     let Config {
         cert_path,
         pkey_path,
         world_path,
+        in_process,
         addr,
         max_population_size,
         max_resource,
         stat_dir,
+        calibration_path,
+        threads_per_task,
+        max_concurrent_tasks,
+        memory_limit_mb,
+        cpu_time_limit_secs,
     } = toml::from_str(&std::fs::read_to_string(&config_path)?)?;
     let stat_dir_path = Path::new(&stat_dir).to_path_buf();
     assert!(stat_dir_path.exists(), "{} does not exist.", stat_dir);
-    assert!(
-        Path::new(&world_path).exists(),
-        "{} does not exist.",
-        world_path
-    );
-
-    tracing_subscriber::fmt::init();
+    let limits = worker::Limits {
+        memory: memory_limit_mb.map(|mb| mb << 20),
+        cpu_time: cpu_time_limit_secs,
+    };
+    let execution = if in_process {
+        if limits.memory.is_some() || limits.cpu_time.is_some() {
+            tracing::warn!("limits are ignored in process.");
+        }
+        worker::Execution::InProcess
+    } else {
+        let world_path = world_path.expect("world_path is required unless in_process.");
+        assert!(
+            Path::new(&world_path).exists(),
+            "{} does not exist.",
+            world_path
+        );
+        worker::Execution::Process { world_path, limits }
+    };
 
     let endpoint = Endpoint::server(get_server_config(cert_path, pkey_path)?, addr)?;
-    let manager = worker::WorldManager::new(world_path, stat_dir, stat_dir_path);
+    let calibration_path =
+        calibration_path.map_or_else(|| stat_dir_path.join("calibration.json"), PathBuf::from);
+    let n_cpus = worker::system::n_cpus();
+    let threads_per_task = threads_per_task
+        .unwrap_or(THREADS_PER_TASK.min(n_cpus))
+        .max(1);
+    let capacity = worker_if::Capacity {
+        n_cpus,
+        total_memory: worker::system::memory().map_or(0, |(total, _)| total),
+        max_concurrent_tasks: max_concurrent_tasks.unwrap_or((n_cpus / threads_per_task).max(1)),
+        threads_per_task,
+    };
+    tracing::info!(?capacity);
+    let manager = worker::WorldManager::new(
+        execution,
+        stat_dir,
+        stat_dir_path,
+        calibration_path,
+        capacity,
+    );
     while let Some(connecting) = endpoint.accept().await {
         let connection = connecting.await.unwrap();
         let ip = connection.remote_address().to_string();
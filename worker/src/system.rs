@@ -0,0 +1,69 @@
+use std::{fs, io, thread};
+
+use crate::Limits;
+
+/// Returns the number of CPUs this process can run on.
+pub fn n_cpus() -> u32 {
+    thread::available_parallelism().map_or(1, |n| n.get() as u32)
+}
+
+/// Returns the total and the available bytes of memory read from `/proc/meminfo`.
+pub fn memory() -> Option<(u64, u64)> {
+    parse_meminfo(&fs::read_to_string("/proc/meminfo").ok()?)
+}
+
+/// Returns the load average over the last minute read from `/proc/loadavg`.
+pub fn load_average() -> Option<f64> {
+    fs::read_to_string("/proc/loadavg")
+        .ok()?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Sets the limits of the current process, which is called in a child before exec.
+pub fn set_limits(limits: &Limits) -> io::Result<()> {
+    for (resource, limit) in [
+        (libc::RLIMIT_AS, limits.memory),
+        (libc::RLIMIT_CPU, limits.cpu_time),
+    ] {
+        let Some(limit) = limit else { continue };
+        let rlim = libc::rlimit {
+            rlim_cur: limit as libc::rlim_t,
+            rlim_max: limit as libc::rlim_t,
+        };
+        if unsafe { libc::setrlimit(resource, &rlim) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+fn parse_meminfo(meminfo: &str) -> Option<(u64, u64)> {
+    let kib = |key: &str| -> Option<u64> {
+        let line = meminfo.lines().find(|l| l.starts_with(key))?;
+        line[key.len()..]
+            .trim()
+            .trim_end_matches("kB")
+            .trim()
+            .parse()
+            .ok()
+    };
+    Some((kib("MemTotal:")? * 1024, kib("MemAvailable:")? * 1024))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_meminfo;
+
+    #[test]
+    fn test_parse_meminfo() {
+        let meminfo = "MemTotal:       16318440 kB\nMemFree:         1023544 kB\nMemAvailable:    9876540 kB\n";
+        assert_eq!(
+            parse_meminfo(meminfo),
+            Some((16318440 * 1024, 9876540 * 1024))
+        );
+        assert_eq!(parse_meminfo("MemTotal: 1 kB\n"), None);
+    }
+}
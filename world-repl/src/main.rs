@@ -13,6 +13,10 @@ fn main() {
         infected,
     } = Args::parse();
 
-    world_repl::run(new_runtime_params(), new_world_params(init_n_pop, infected));
+    let rp = new_runtime_params();
+    let mut wp = new_world_params(init_n_pop, infected);
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    wp.set_mesh(wp.auto_mesh(threads));
+    world_repl::run(rp, wp);
     println!("stopped");
 }
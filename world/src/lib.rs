@@ -1,20 +1,33 @@
-use ipc_channel::ipc::IpcSender;
-use scenario_operation::Operation;
+use scenario_operation::{Assignment, MyField, Operation};
 use world_if::{
-    api, IpcBiConnection, Request, Response, ResponseError, ResponseOk, WorldState, WorldStatus,
+    api, BiConnection, BiConnectionExt, Request, Response, ResponseError, ResponseOk, StatusSender,
+    WorldState, WorldStatus,
 };
 
 use world_core::{
     scenario::Scenario,
     util::{self, random::DistInfo},
     world::{
-        commons::{RuntimeParams, WorldParams},
+        commons::{
+            BorderControl, Dose, GatheringEvent, GatheringSpot, RegionParams, RuntimeParams,
+            TracingOperation, VaccinationStrategy, VaccineInfo, VaccinePool, VaccinePriority,
+            WorkPlaceMode, WorldParams,
+        },
+        density::DensityMap,
+        geometry::{FrictionZone, Region, Wall},
+        immunity::WaningCurve,
+        infection::{Aerosol, Contact, InfectionModel, ViralLoad},
         World,
     },
 };
 
+use anyhow::{bail, ensure};
+use base64::{engine::general_purpose, Engine};
+use math::Point;
+
 use std::{
     io,
+    sync::Arc,
     thread::{self, JoinHandle},
 };
 
@@ -22,36 +35,43 @@ use std::{
 struct WorldStepInfo {
     prev_time: f64,
     steps_per_sec: f64,
+    /// seconds spent in the steps run so far
+    busy_secs: f64,
+    n_steps: u32,
+    /// mean seconds spent in each phase of a step, empty unless profiling
+    phase_secs: Vec<(String, f64)>,
 }
 
-pub struct WorldSpawner {
+pub struct WorldSpawner<C, S> {
     world: World,
     stop_at: u32,
     info: WorldStepInfo,
-    bicon: IpcBiConnection,
-    stream: IpcSender<WorldStatus>,
+    bicon: C,
+    stream: S,
     stat_dir: String,
 }
 
-impl WorldSpawner {
-    pub fn new(
-        id: String,
-        bicon: IpcBiConnection,
-        stream: IpcSender<WorldStatus>,
-        stat_dir: String,
-    ) -> anyhow::Result<Self> {
-        let param: api::job::JobParam = bicon.recv().unwrap();
-        let world = World::new(
+impl<C, S> WorldSpawner<C, S>
+where
+    C: BiConnection + 'static,
+    S: StatusSender + 'static,
+{
+    pub fn new(id: String, bicon: C, stream: S, stat_dir: String) -> anyhow::Result<Self> {
+        let param: api::job::JobParam = bicon.recv()?;
+        let (world_params, runtime_params, scenario) = new_params(&param).map_err(|errors| {
+            anyhow::anyhow!(errors
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("; "))
+        })?;
+        let mut world = World::new(
             id,
-            new_runtime_params(),
-            new_world_params(&param.world_params),
-            Scenario::new(param.scenario, |s| {
-                Operation::new(
-                    s.condition.parse().unwrap(),
-                    serde_json::from_value(s.assignments).unwrap(),
-                )
-            }),
+            runtime_params,
+            world_params,
+            Scenario::new(scenario, |op| op),
         );
+        world.set_profiling(param.profiling);
         let spawner = Self {
             world,
             stop_at: param.stop_at,
@@ -70,7 +90,8 @@ impl WorldSpawner {
             .spawn(move || self.listen())
     }
 
-    fn listen(mut self) -> anyhow::Result<()> {
+    /// Runs the world on the current thread until it is executed to the end or fails.
+    pub fn listen(mut self) -> anyhow::Result<()> {
         loop {
             match self.bicon.recv()? {
                 Request::Execute => {
@@ -91,12 +112,7 @@ impl WorldSpawner {
 
     #[inline]
     fn send_status(&self, state: WorldState) -> anyhow::Result<()> {
-        self.stream.send(WorldStatus::new(
-            self.world.runtime_params.step,
-            state,
-            String::new(),
-        ))?;
-        Ok(())
+        self.send_status_with(state, String::new())
     }
 
     #[inline]
@@ -124,14 +140,26 @@ impl WorldSpawner {
 
     #[inline]
     fn step(&mut self, step_to_end: u32) -> anyhow::Result<bool> {
+        let start = util::get_uptime();
         self.world.step();
         let new_time = util::get_uptime();
+        self.info.busy_secs += new_time - start;
+        self.info.n_steps += 1;
         let time_passed = new_time - self.info.prev_time;
         if time_passed < 1.0 {
             self.info.steps_per_sec +=
                 ((1.0 / time_passed).min(30.0) - self.info.steps_per_sec) * 0.2;
         }
         self.info.prev_time = new_time;
+        if self.world.profile().is_enabled() {
+            self.info.phase_secs = self
+                .world
+                .profile()
+                .mean_secs()
+                .into_iter()
+                .map(|(phase, secs)| (phase.to_string(), secs))
+                .collect();
+        }
 
         let (state, cont) = if self.world.is_ended() {
             (WorldState::Ended, false)
@@ -151,26 +179,221 @@ impl WorldSpawner {
 
     #[inline]
     fn send_status_with(&self, state: WorldState, custom: String) -> anyhow::Result<()> {
-        self.stream.send(WorldStatus::new(
-            self.world.runtime_params.step,
-            state,
-            custom,
-        ))?;
-        Ok(())
+        let wp = &self.world.world_params;
+        let step_secs = if self.info.n_steps > 0 {
+            self.info.busy_secs / self.info.n_steps as f64
+        } else {
+            0.0
+        };
+        self.stream.send_status(
+            WorldStatus::new(self.world.runtime_params.step, state, custom)
+                .with_phase_secs(self.info.phase_secs.clone())
+                .with_step_secs(step_secs, (wp.mesh * wp.mesh_rows()) as u32),
+        )
+    }
+}
+
+/// Returns the invalid parts of a job, which is empty if a world can be built from it.
+pub fn validate(param: &api::job::JobParam) -> Vec<api::job::ParamError> {
+    new_params(param).err().unwrap_or_default()
+}
+
+/// Returns the number of cells the field of a world built from `param` is divided into,
+/// which depends on the `threads` it runs on if the mesh is derived from the population.
+pub fn n_cells(param: &api::job::WorldParams, threads: usize) -> anyhow::Result<u32> {
+    let mut errors = Vec::new();
+    let mut wp = new_world_params(param, &mut errors);
+    if let Some(e) = errors.first() {
+        bail!("{e}");
+    }
+    set_mesh(&mut wp, param, threads)?;
+    Ok((wp.mesh * wp.mesh_rows()) as u32)
+}
+
+/// Builds the parameters and the scenario of a world from a job, reporting every invalid part.
+fn new_params(
+    param: &api::job::JobParam,
+) -> Result<(WorldParams, RuntimeParams, Vec<Operation>), Vec<api::job::ParamError>> {
+    let mut errors = Vec::new();
+    let mut world_params = new_world_params(&param.world_params, &mut errors);
+    let mesh = set_mesh(
+        &mut world_params,
+        &param.world_params,
+        rayon::current_num_threads(),
+    );
+    ok_at(mesh, "worldParams.mesh", &mut errors);
+    let runtime_params = new_runtime_params(param, &world_params, &mut errors);
+    let scenario = new_scenario(&param.scenario, &runtime_params).unwrap_or_else(|e| {
+        errors.extend(e);
+        Vec::new()
+    });
+    if errors.is_empty() {
+        Ok((world_params, runtime_params, scenario))
+    } else {
+        Err(errors)
     }
 }
 
-fn new_world_params(param: &api::job::WorldParams) -> WorldParams {
-    WorldParams::new(
-        param.population_size,
-        360,
-        18,
-        16,
+/// Returns the value of `res`, or records its error at `path` and returns the default.
+fn ok_at<T: Default>(
+    res: anyhow::Result<T>,
+    path: impl Into<String>,
+    errors: &mut Vec<api::job::ParamError>,
+) -> T {
+    res.unwrap_or_else(|e| {
+        errors.push(api::job::ParamError::new(Some(path.into()), e));
+        T::default()
+    })
+}
+
+/// Parses the operations of a scenario, reporting every invalid condition and assignment.
+fn new_scenario(
+    ops: &[api::job::Operation],
+    rp: &RuntimeParams,
+) -> Result<Vec<Operation>, Vec<api::job::ParamError>> {
+    let mut errors = Vec::new();
+    let mut scenario = Vec::new();
+    for (i, op) in ops.iter().enumerate() {
+        let condition = match op.condition.parse() {
+            Ok(condition) => Some(condition),
+            Err(e @ scenario_operation::PredicateError { column, .. }) => {
+                errors.push(api::job::ParamError {
+                    column: Some(column as u32),
+                    ..api::job::ParamError::new(Some(format!("scenario[{i}].condition")), e)
+                });
+                None
+            }
+        };
+        let serde_json::Value::Array(values) = &op.assignments else {
+            errors.push(api::job::ParamError::new(
+                Some(format!("scenario[{i}].assignments")),
+                "assignments must be an array",
+            ));
+            continue;
+        };
+        let mut assignments = Vec::new();
+        for (j, value) in values.iter().enumerate() {
+            let res = serde_json::from_value::<Assignment<MyField>>(value.clone())
+                .map_err(anyhow::Error::from)
+                .and_then(|a| check_assignment(&a, rp).map(|_| a));
+            match res {
+                Ok(a) => assignments.push(a),
+                Err(e) => errors.push(api::job::ParamError::new(
+                    Some(format!("scenario[{i}].assignments[{j}]")),
+                    e,
+                )),
+            }
+        }
+        if let Some(condition) = condition {
+            scenario.push(Operation::new(condition, assignments));
+        }
+    }
+    if errors.is_empty() {
+        Ok(scenario)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Checks that an assignment refers to a strategy, a spot or an event which exists.
+fn check_assignment(a: &Assignment<MyField>, rp: &RuntimeParams) -> anyhow::Result<()> {
+    let field = match a {
+        Assignment::Value(field) => field,
+        Assignment::Interpolate(field, n) => {
+            ensure!(*n > 0, "interpolation needs at least one step");
+            field
+        }
+    };
+    match field {
+        MyField::GatheringFrequency(_) => {}
+        MyField::Vaccination(c) => ensure!(
+            rp.vx_stg.contains_key(&c.index),
+            "no vaccination strategy {}",
+            c.index
+        ),
+        MyField::GatheringSpot(c) => {
+            ensure!(
+                c.index < rp.gat_spots.len(),
+                "no gathering spot {}",
+                c.index
+            );
+            if let scenario_operation::GatheringSpot::Weight(w) = c.value {
+                ensure!(w >= 0.0, "weight of a gathering spot must not be negative");
+            }
+        }
+        MyField::GatheringEvent(c) => ensure!(
+            c.index < rp.gat_events.len(),
+            "no gathering event {}",
+            c.index
+        ),
+    }
+    Ok(())
+}
+
+/// side length of the default square field
+const FIELD_SIZE: u32 = 360;
+/// range agents interact in, which is also the gap between regions
+const CELL_SIZE: u32 = 20;
+/// steps of a world per day
+pub const STEPS_PER_DAY: u32 = 16;
+
+/// Records an error at the path of a parameter unless the condition holds, like [`ensure!`],
+/// and returns whether it holds.
+macro_rules! ensure_at {
+    ($errors:expr, $cond:expr, $path:expr, $($arg:tt)+) => {{
+        let holds: bool = $cond;
+        if !holds {
+            $errors.push(api::job::ParamError::new(
+                Some($path.to_string()),
+                format!($($arg)+),
+            ));
+        }
+        holds
+    }};
+}
+
+fn new_world_params(
+    param: &api::job::WorldParams,
+    errors: &mut Vec<api::job::ParamError>,
+) -> WorldParams {
+    let wrk_plc_mode = match (&param.density_map, &param.commuting) {
+        (Some(dm), _) => {
+            let map = new_density_map(dm).map(|m| Some(WorkPlaceMode::PopDistImg(Arc::new(m))));
+            ok_at(map, "worldParams.densityMap", errors)
+        }
+        (None, Some(c)) if c.centered => Some(WorkPlaceMode::Centered),
+        (None, Some(_)) => Some(WorkPlaceMode::Uniform),
+        (None, None) => None,
+    };
+    let (width, height) = if param.regions.is_empty() {
+        param
+            .geometry
+            .as_ref()
+            .map_or((FIELD_SIZE, FIELD_SIZE), |g| (g.width, g.height))
+    } else {
+        let widths = param
+            .regions
+            .iter()
+            .map(|r| r.width + CELL_SIZE)
+            .sum::<u32>();
+        let height = param.regions.iter().map(|r| r.height).max().unwrap();
+        (widths - CELL_SIZE, height)
+    };
+    let population_size = if param.regions.is_empty() {
+        param.population_size
+    } else {
+        param.regions.iter().map(|r| r.population).sum()
+    };
+    let mut wp = WorldParams::new(
+        population_size,
+        width as usize,
+        (width / CELL_SIZE).max(1) as usize,
+        STEPS_PER_DAY,
         param.infected.into(),
         0.0.into(),
         20.0.into(),
         50.0.into(),
-        None,
+        wrk_plc_mode,
         150.0.into(),
         50.0,
         500.0.into(),
@@ -182,10 +405,205 @@ fn new_world_params(param: &api::job::WorldParams) -> WorldParams {
         7.0,
         120.0,
         90.0.into(),
-    )
+    );
+    wp.set_field_height(height as usize);
+    if let Some(g) = &param.geometry {
+        wp.walls = g
+            .walls
+            .iter()
+            .map(|w| Wall::new(Point::new(w.x1, w.y1), Point::new(w.x2, w.y2)))
+            .collect();
+        wp.frc_zones = g
+            .friction_zones
+            .iter()
+            .map(|z| FrictionZone {
+                min: Point::new(z.x, z.y),
+                max: Point::new(z.x + z.width, z.y + z.height),
+                friction: z.friction.into(),
+            })
+            .collect();
+    }
+    let mut x = 0.0;
+    for r in &param.regions {
+        let region = Region {
+            min: Point::new(x, 0.0),
+            max: Point::new(x + r.width as f64, r.height as f64),
+            population: r.population,
+            infected: r.infected.into(),
+        };
+        wp.walls.extend(region.walls());
+        wp.regions.push(region);
+        x += (r.width + CELL_SIZE) as f64;
+    }
+    if let Some(hh) = &param.households {
+        ensure_at!(
+            errors,
+            hh.size_distribution.iter().all(|f| *f >= 0.0)
+                && hh.size_distribution.iter().any(|f| *f > 0.0),
+            "worldParams.households.sizeDistribution",
+            "household size distribution must be non-negative and not all zero"
+        );
+        wp.hh_size = hh.size_distribution.clone();
+    }
+    if let Some(c) = &param.commuting {
+        wp.cmt_rt = c.rate.into();
+        wp.wrk_plc_n = c.workplaces;
+        wp.wrk_plc_rad = c.radius.into();
+        wp.wrk_hours = (c.start_hour, c.end_hour);
+        let (go, leave) = wp.work_steps();
+        ensure_at!(
+            errors,
+            go != leave || c.end_hour - c.start_hour == 24.0,
+            "worldParams.commuting",
+            "work from {} to {} o'clock starts and ends at the same step",
+            c.start_hour,
+            c.end_hour
+        );
+    }
+    if let Some(im) = &param.infection_model {
+        wp.infec_model = new_infection_model(im);
+    }
+    if let Some(av) = &param.anti_vax {
+        wp.av_clstr_rate = av.rate.into();
+        wp.av_clstr_gran = av.granularity.into();
+        wp.av_test_rate = av.test_rate.into();
+    }
+    wp
+}
+
+fn new_waning_curve(param: &api::job::WaningCurve) -> anyhow::Result<WaningCurve> {
+    let curve = match param.kind {
+        api::job::WaningCurveKind::Exponential => {
+            let Some(half_life) = param.half_life.filter(|h| *h > 0.0) else {
+                bail!("exponential waning needs a positive half-life");
+            };
+            WaningCurve::Exponential { half_life }
+        }
+        api::job::WaningCurveKind::PiecewiseLinear => {
+            ensure!(
+                !param.points.is_empty(),
+                "piecewise linear waning needs points"
+            );
+            ensure!(
+                param.points.windows(2).all(|w| w[0].days < w[1].days),
+                "points of waning must be sorted by days"
+            );
+            WaningCurve::PiecewiseLinear(param.points.iter().map(|p| (p.days, p.level)).collect())
+        }
+        api::job::WaningCurveKind::Table => {
+            let Some(interval) = param.interval.filter(|i| *i > 0.0) else {
+                bail!("table waning needs a positive interval");
+            };
+            ensure!(!param.levels.is_empty(), "table waning needs levels");
+            WaningCurve::Table {
+                interval,
+                levels: param.levels.clone(),
+            }
+        }
+    };
+    Ok(curve)
+}
+
+/// Returns the distribution of values from zero to `upper`.
+fn new_dist_info<T: From<f64>>(
+    param: &api::job::DistParams,
+    upper: f64,
+) -> anyhow::Result<DistInfo<T>> {
+    ensure!(
+        0.0 <= param.min
+            && param.min <= param.mode
+            && param.mode <= param.max
+            && param.max <= upper,
+        "distribution must satisfy 0 <= min <= mode <= max <= {upper}"
+    );
+    Ok(DistInfo::new(
+        param.min.into(),
+        param.mode.into(),
+        param.max.into(),
+    ))
 }
 
-fn new_runtime_params() -> RuntimeParams {
+/// Returns the parameters of the region replacing those of the world.
+fn new_region_params(
+    param: &api::job::RegionParams,
+    i: usize,
+    errors: &mut Vec<api::job::ParamError>,
+) -> RegionParams {
+    let at = |path: &str| format!("worldParams.regions[{i}].{path}");
+    let mut region = RegionParams::default();
+    if let Some(mob) = &param.mobility {
+        let freq = mob.frequency.as_ref().map(|d| new_dist_info(d, 1000.0));
+        region.mob_freq = ok_at(freq.transpose(), at("mobility.frequency"), errors);
+        let dist = mob.distance.as_ref().map(|d| new_dist_info(d, 100.0));
+        region.mob_dist = ok_at(dist.transpose(), at("mobility.distance"), errors);
+        region.back_hm_rt = mob.back_home_rate.map(Into::into);
+    }
+    if let Some(gat) = &param.gatherings {
+        region.gat_fr = gat.frequency;
+        let freq = gat.participation.as_ref().map(|d| new_dist_info(d, 100.0));
+        region.gat_freq = ok_at(freq.transpose(), at("gatherings.participation"), errors);
+    }
+    if let Some(itv) = &param.interventions {
+        region.dst_st = itv.distancing_strength;
+        region.tst_sbj_asy = itv.test_asymptomatic.map(Into::into);
+        region.tst_sbj_sym = itv.test_symptomatic.map(Into::into);
+        region.hm_qrn_rt = itv.home_quarantine_rate.map(Into::into);
+    }
+    region
+}
+
+fn new_infection_model(param: &api::job::InfectionModelParams) -> Arc<dyn InfectionModel> {
+    match param.kind {
+        api::job::InfectionModelKind::Contact => Arc::new(Contact),
+        api::job::InfectionModelKind::ViralLoad => match &param.viral_load {
+            Some(vl) => Arc::new(ViralLoad {
+                shape: vl.shape,
+                scale: vl.scale,
+            }),
+            None => Arc::new(ViralLoad::default()),
+        },
+        api::job::InfectionModelKind::Aerosol => match &param.aerosol {
+            Some(a) => Arc::new(Aerosol {
+                emission: a.emission,
+                half_life: a.half_life,
+                dose_response: a.dose_response,
+            }),
+            None => Arc::new(Aerosol::default()),
+        },
+    }
+}
+
+fn new_density_map(param: &api::job::DensityMap) -> anyhow::Result<DensityMap> {
+    match param.format {
+        api::job::DensityMapFormat::Csv => DensityMap::from_csv(&param.data),
+        api::job::DensityMapFormat::Png => {
+            DensityMap::from_png(&general_purpose::STANDARD.decode(&param.data)?)
+        }
+    }
+}
+
+/// Divides the field into cells given or derived for `threads`, which must be at least a unit wide.
+fn set_mesh(
+    wp: &mut WorldParams,
+    param: &api::job::WorldParams,
+    threads: usize,
+) -> anyhow::Result<()> {
+    wp.set_mesh(
+        param
+            .mesh
+            .map_or_else(|| wp.auto_mesh(threads), |m| m as usize),
+    );
+    ensure!(
+        wp.cell_size() >= 1.0,
+        "{} cells are more than the width of the field {}",
+        wp.mesh,
+        wp.field_size()
+    );
+    Ok(())
+}
+
+/// Returns the runtime parameters which no job changes.
+fn base_runtime_params() -> RuntimeParams {
     RuntimeParams {
         mass: 50.0.into(),
         friction: 80.0.into(),
@@ -232,3 +650,451 @@ fn new_runtime_params() -> RuntimeParams {
         ..Default::default()
     }
 }
+
+fn new_runtime_params(
+    param: &api::job::JobParam,
+    wp: &WorldParams,
+    errors: &mut Vec<api::job::ParamError>,
+) -> RuntimeParams {
+    let mut rp = base_runtime_params();
+    if let Some(tracing) = &param.tracing {
+        rp.trc_ope = tracing
+            .operations
+            .iter()
+            .map(|ope| match ope {
+                api::job::TracingOperation::Test => TracingOperation::TEST,
+                api::job::TracingOperation::Vaccinate => TracingOperation::VACCINATE,
+                api::job::TracingOperation::Quarantine => TracingOperation::QUARANTINE,
+            })
+            .collect();
+        rp.trc_vcn_type = tracing.vaccine_type;
+        rp.trc_qrn_dur = tracing.quarantine_days;
+    }
+    if let Some(hq) = &param.home_quarantine {
+        rp.hm_qrn_rt = hq.rate.into();
+        rp.hm_qrn_dur = hq.days;
+    }
+    if let Some(hsp) = &param.hospital {
+        rp.hsp_capa = hsp.capacity.into();
+        rp.ovf_svr = hsp.overflow_severity.into();
+        rp.ovf_rcv_dly = hsp.overflow_recovery_delay.into();
+    }
+    rp.gat_events = param
+        .gathering_events
+        .iter()
+        .map(|ev| GatheringEvent {
+            p: Point::new(ev.x, ev.y),
+            size: ev.radius,
+            start: ev.day as f64 + ev.hour / 24.0,
+            duration: ev.duration,
+            strength: ev.strength,
+            participation: ev.participation.into(),
+            cancelled: false,
+        })
+        .collect();
+    if let Some(vcn) = &param.vaccination {
+        let n_variants = rp.variant_pool.efficacy.len();
+        let mut efficacy = Vec::with_capacity(vcn.vaccines.len());
+        for (i, v) in vcn.vaccines.iter().enumerate() {
+            if v.variant_efficacy.is_empty() {
+                efficacy.push(vec![1.0; n_variants]);
+                continue;
+            }
+            ensure_at!(
+                errors,
+                v.variant_efficacy.len() == n_variants,
+                format!("vaccination.vaccines[{i}].variantEfficacy"),
+                "vaccine {i} needs an efficacy for each of {n_variants} variants"
+            );
+            ensure_at!(
+                errors,
+                v.variant_efficacy.iter().all(|e| (0.0..=100.0).contains(e)),
+                format!("vaccination.vaccines[{i}].variantEfficacy"),
+                "efficacies of vaccine {i} against variants must be percentages"
+            );
+            efficacy.push(v.variant_efficacy.iter().map(|e| e / 100.0).collect());
+        }
+        rp.vaccine_pool = VaccinePool::from_vec(
+            vcn.vaccines
+                .iter()
+                .map(|v| {
+                    VaccineInfo::with_doses(
+                        v.doses
+                            .iter()
+                            .map(|d| Dose {
+                                interval: d.interval,
+                                efficacy: d.efficacy.into(),
+                            })
+                            .collect(),
+                    )
+                })
+                .collect(),
+            efficacy,
+        );
+        for (i, s) in vcn.strategies.iter().enumerate() {
+            ensure_at!(
+                errors,
+                s.vaccine < vcn.vaccines.len(),
+                format!("vaccination.strategies[{i}].vaccine"),
+                "strategy refers to vaccine {} of {}",
+                s.vaccine,
+                vcn.vaccines.len()
+            );
+            let priority = match s.priority {
+                api::job::VaccinePriority::Random => VaccinePriority::Random,
+                api::job::VaccinePriority::Older => VaccinePriority::Older,
+                api::job::VaccinePriority::Central => VaccinePriority::Central,
+                api::job::VaccinePriority::PopulationDensity => VaccinePriority::PopulationDensity,
+                api::job::VaccinePriority::Booster => VaccinePriority::Booster,
+            };
+            rp.vx_stg.insert(
+                i,
+                VaccinationStrategy {
+                    vaccine: s.vaccine,
+                    perform_rate: s.perform_rate.into(),
+                    regularity: s.regularity.into(),
+                    priority,
+                },
+            );
+        }
+        rp.vcn_bst_days = vcn.booster_min_days;
+    }
+    if rp.trc_ope.contains(TracingOperation::VACCINATE) {
+        let n_vaccines = rp.vaccine_pool.efficacy.len();
+        ensure_at!(
+            errors,
+            rp.trc_vcn_type < n_vaccines,
+            "tracing.vaccineType",
+            "no vaccine {} for tracing vaccination",
+            rp.trc_vcn_type
+        );
+    }
+    if let Some(imn) = &param.immunity {
+        let wane = imn.infection_waning.as_ref().map(new_waning_curve);
+        rp.imn_wane = ok_at(wane.transpose(), "immunity.infectionWaning", errors);
+        let wane = imn.vaccine_waning.as_ref().map(new_waning_curve);
+        rp.vcn_wane = ok_at(wane.transpose(), "immunity.vaccineWaning", errors);
+        rp.imn_hybrid = imn.hybrid_boost.into();
+    }
+    for (i, r) in param.world_params.regions.iter().enumerate() {
+        rp.rgn_prm.insert(i, new_region_params(r, i, errors));
+    }
+    if let Some(hh) = &param.world_params.households {
+        rp.hh_infec = hh.transmission.into();
+        rp.hh_qrn = hh.quarantine;
+    }
+    if let Some(tr) = &param.travel {
+        let n = wp.regions.len();
+        ensure_at!(
+            errors,
+            tr.od_matrix.is_empty()
+                || (tr.od_matrix.len() == n && tr.od_matrix.iter().all(|row| row.len() == n)),
+            "travel.odMatrix",
+            "origin-destination matrix must be {n}x{n} for {n} regions"
+        );
+        ensure_at!(
+            errors,
+            tr.od_matrix
+                .iter()
+                .flatten()
+                .all(|v| (0.0..=100.0).contains(v)),
+            "travel.odMatrix",
+            "travel rates must be percentages"
+        );
+        ensure_at!(
+            errors,
+            tr.od_matrix.iter().enumerate().all(|(from, row)| {
+                row.iter()
+                    .enumerate()
+                    .filter(|&(to, _)| to != from)
+                    .map(|(_, v)| v)
+                    .sum::<f64>()
+                    <= 100.0
+            }),
+            "travel.odMatrix",
+            "travel rates from a region must not exceed 100% in total"
+        );
+        rp.rgn_od = tr
+            .od_matrix
+            .iter()
+            .map(|row| row.iter().map(|v| v / 100.0).collect())
+            .collect();
+        let n_variants = rp.variant_pool.efficacy.len();
+        for (i, bc) in tr.border_controls.iter().enumerate() {
+            ensure_at!(
+                errors,
+                bc.region < n,
+                format!("travel.borderControls[{i}].region"),
+                "no region {} for border control",
+                bc.region
+            );
+            ensure_at!(
+                errors,
+                bc.imported_variant < n_variants,
+                format!("travel.borderControls[{i}].importedVariant"),
+                "no variant {} for imported cases",
+                bc.imported_variant
+            );
+            rp.brd_ctrl.insert(
+                bc.region,
+                BorderControl {
+                    quota: bc.quota,
+                    test: bc.test_on_arrival,
+                    qrn_days: bc.quarantine_days,
+                    imp_rate: bc.imported_cases,
+                    imp_variant: bc.imported_variant,
+                },
+            );
+        }
+    }
+    if let Some(gs) = &param.gathering_spots {
+        for (i, spot) in gs.spots.iter().enumerate() {
+            ensure_at!(
+                errors,
+                spot.weight >= 0.0,
+                format!("gatheringSpots.spots[{i}].weight"),
+                "weight of gathering spot {i} must not be negative"
+            );
+            ensure_at!(
+                errors,
+                (0.0..=wp.field_size()).contains(&spot.x)
+                    && (0.0..=wp.field_height()).contains(&spot.y),
+                format!("gatheringSpots.spots[{i}]"),
+                "gathering spot {i} at ({}, {}) is outside the field",
+                spot.x,
+                spot.y
+            );
+        }
+        ensure_at!(
+            errors,
+            gs.random_weight >= 0.0,
+            "gatheringSpots.randomWeight",
+            "weight of random gathering spots must not be negative"
+        );
+        rp.gat_rnd_rt = gs.random_spot_rate.into();
+        rp.gat_spots = gs
+            .spots
+            .iter()
+            .map(|spot| GatheringSpot {
+                p: Point::new(spot.x, spot.y),
+                weight: spot.weight,
+                open: spot.open,
+            })
+            .collect();
+        rp.gat_spots.extend((0..gs.random_count).map(|_| {
+            let p = match gs.random_placement {
+                api::job::SpotPlacement::Uniform => wp.random_point(),
+                api::job::SpotPlacement::Population => wp.random_place(),
+            };
+            GatheringSpot::new(p, gs.random_weight)
+        }));
+    }
+    rp
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use world_if::api::job;
+
+    /// Returns the paths of the invalid parts of `param`.
+    fn paths(param: &job::JobParam) -> Vec<String> {
+        super::validate(param)
+            .into_iter()
+            .map(|e| e.path.unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut param = job::JobParam {
+            stop_at: 10,
+            world_params: job::WorldParams {
+                population_size: 1000,
+                infected: 1.0,
+                ..Default::default()
+            },
+            scenario: Vec::new(),
+            tracing: None,
+            home_quarantine: None,
+            hospital: None,
+            immunity: None,
+            vaccination: None,
+            gathering_spots: None,
+            gathering_events: Vec::new(),
+            travel: None,
+            profiling: false,
+        };
+        assert!(super::validate(&param).is_empty());
+
+        // cells narrower than a unit in a field of 360
+        param.world_params.mesh = Some(400);
+        assert_eq!(paths(&param), ["worldParams.mesh"]);
+        param.world_params.mesh = Some(36);
+        assert!(super::validate(&param).is_empty());
+        param.world_params.mesh = None;
+
+        // the default pool has a single vaccine
+        param.tracing = Some(job::TracingParams {
+            operations: vec![job::TracingOperation::Vaccinate],
+            vaccine_type: 1,
+            quarantine_days: 0.0,
+        });
+        assert_eq!(paths(&param), ["tracing.vaccineType"]);
+        param.tracing = None;
+
+        param.vaccination = Some(job::VaccinationParams {
+            vaccines: vec![job::VaccineParams {
+                doses: vec![job::DoseParams {
+                    interval: 0.0,
+                    efficacy: 90.0,
+                }],
+                variant_efficacy: vec![50.0, 50.0],
+            }],
+            strategies: Vec::new(),
+            booster_min_days: 0.0,
+        });
+        assert_eq!(paths(&param), ["vaccination.vaccines[0].variantEfficacy"]);
+        param.vaccination.as_mut().unwrap().vaccines[0].variant_efficacy = vec![50.0];
+        let (_, rp, _) = super::new_params(&param).unwrap();
+        assert_eq!(rp.vaccine_pool.efficacy, [[0.5]]);
+        param.vaccination = None;
+
+        param.gathering_spots = Some(job::GatheringSpotsParams {
+            spots: vec![job::GatheringSpot {
+                x: 400.0,
+                y: 10.0,
+                weight: 1.0,
+                open: true,
+            }],
+            random_count: 0,
+            random_placement: job::SpotPlacement::Uniform,
+            random_weight: 1.0,
+            random_spot_rate: 0.0,
+        });
+        assert_eq!(paths(&param), ["gatheringSpots.spots[0]"]);
+        param.gathering_spots.as_mut().unwrap().spots[0].x = 10.0;
+        assert!(super::validate(&param).is_empty());
+        param.gathering_spots = None;
+
+        param.world_params.regions = vec![job::RegionParams {
+            width: 100,
+            height: 100,
+            population: 1000,
+            infected: 1.0,
+            mobility: Some(job::RegionMobilityParams {
+                frequency: Some(job::DistParams {
+                    min: 10.0,
+                    mode: 5.0,
+                    max: 20.0,
+                }),
+                ..Default::default()
+            }),
+            gatherings: Some(job::RegionGatheringParams {
+                frequency: Some(0.0),
+                ..Default::default()
+            }),
+            interventions: None,
+        }];
+        assert_eq!(paths(&param), ["worldParams.regions[0].mobility.frequency"]);
+        param.world_params.regions[0].mobility = None;
+        let (_, rp, _) = super::new_params(&param).unwrap();
+        assert_eq!(rp.rgn_prm[&0].gat_fr, Some(0.0));
+        assert!(rp.rgn_prm[&0].mob_freq.is_none());
+        param.world_params.regions.clear();
+
+        // no regions to control
+        param.travel = Some(job::TravelParams {
+            od_matrix: Vec::new(),
+            border_controls: vec![job::BorderControlParams {
+                region: 0,
+                quota: None,
+                test_on_arrival: false,
+                quarantine_days: 0.0,
+                imported_cases: 0.0,
+                imported_variant: 0,
+            }],
+        });
+        assert_eq!(paths(&param), ["travel.borderControls[0].region"]);
+        // every invalid part is reported rather than the first one
+        param.tracing = Some(job::TracingParams {
+            operations: vec![job::TracingOperation::Vaccinate],
+            vaccine_type: 1,
+            quarantine_days: 0.0,
+        });
+        let errors = super::validate(&param);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            paths(&param),
+            ["tracing.vaccineType", "travel.borderControls[0].region"]
+        );
+        param.world_params.mesh = Some(400);
+        assert_eq!(
+            paths(&param),
+            [
+                "worldParams.mesh",
+                "tracing.vaccineType",
+                "travel.borderControls[0].region"
+            ]
+        );
+        param.world_params.mesh = None;
+        param.tracing = None;
+        param.travel = None;
+
+        param.scenario = vec![
+            job::Operation {
+                condition: "days >= 5 AND dayz < 9".to_string(),
+                assignments: json!([{"value": {"gatheringFrequency": 10.0}}]),
+            },
+            job::Operation {
+                condition: "days == 20".to_string(),
+                assignments: json!([
+                    {"value": {"gatheringFrequency": 10.0}},
+                    {"value": {"gatheringSpot": {"index": 0, "value": {"open": false}}}},
+                    {"value": {"gatheringFrequncy": 10.0}},
+                    {"value": {"gatheringSpot": {"index": 0, "value": {"weight": -1.0}}}},
+                ]),
+            },
+        ];
+        let errors = super::validate(&param);
+        let paths = errors
+            .iter()
+            .map(|e| (e.path.as_deref().unwrap(), e.column))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            [
+                ("scenario[0].condition", Some(11)),
+                ("scenario[1].assignments[1]", None),
+                ("scenario[1].assignments[2]", None),
+                ("scenario[1].assignments[3]", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_n_cells() {
+        let mut param = job::WorldParams {
+            population_size: 1000,
+            mesh: Some(18),
+            ..Default::default()
+        };
+        assert_eq!(super::n_cells(&param, 1).unwrap(), 18 * 18);
+        param.geometry = Some(job::GeometryParams {
+            width: 360,
+            height: 180,
+            walls: Vec::new(),
+            friction_zones: Vec::new(),
+        });
+        assert_eq!(super::n_cells(&param, 1).unwrap(), 18 * 9);
+        // cells narrower than the view range
+        param.mesh = Some(36);
+        assert_eq!(super::n_cells(&param, 1).unwrap(), 36 * 18);
+        param.mesh = Some(400);
+        assert!(super::n_cells(&param, 1).is_err());
+        // more threads than the cells of the population need
+        param.mesh = None;
+        assert_eq!(super::n_cells(&param, 1).unwrap(), 12 * 6);
+        assert_eq!(super::n_cells(&param, 64).unwrap(), 23 * 12);
+    }
+}
@@ -9,6 +9,9 @@ struct Args {
     world_id: String,
     #[arg(long)]
     stat_dir: String,
+    /// threads to run the steps on, all the CPUs if omitted
+    #[arg(long)]
+    threads: Option<usize>,
 }
 
 fn main() {
@@ -16,7 +19,21 @@ fn main() {
         server_name,
         world_id,
         stat_dir,
+        threads,
     } = Args::parse();
+    // the output is written to the log of the task by the worker
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .init();
+    if let Some(threads) = threads {
+        if let Err(e) = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+        {
+            tracing::error!("could not build a thread pool due to {e}");
+            std::process::exit(1);
+        }
+    }
     let (req_tx, req_rx) = ipc::bytes_channel().unwrap();
     let (res_tx, res_rx) = ipc::bytes_channel().unwrap();
     let (stream_tx, stream_rx) = ipc::channel().unwrap();
@@ -24,9 +41,16 @@ fn main() {
     tx.send((world_if::IpcBiConnection::new(req_tx, res_rx), stream_rx))
         .unwrap();
     let bicon = world_if::IpcBiConnection::new(res_tx, req_rx);
-    let spawner = world::WorldSpawner::new(world_id, bicon, stream_tx, stat_dir).unwrap();
+    let spawner = match world::WorldSpawner::new(world_id, bicon, stream_tx, stat_dir) {
+        Ok(spawner) => spawner,
+        Err(e) => {
+            tracing::error!("could not create the world due to {e}");
+            std::process::exit(1);
+        }
+    };
     let handle = spawner.spawn().unwrap();
     if let Err(e) = handle.join().unwrap() {
         tracing::error!("stopped with {e}");
+        std::process::exit(1);
     }
 }